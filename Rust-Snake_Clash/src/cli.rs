@@ -0,0 +1,172 @@
+// Command line: `<server|master|client|bot|tournament> [--config PATH] [flags]`.
+// Flags override the config file and SNAKE_* environment variables.
+
+use crate::config::settings::{parse_format, parse_mode, parse_tick_rate, Settings};
+use crate::game::skin::SkinPattern;
+
+pub enum Command {
+    Server(Settings),
+    Master(Settings),
+    Client(Settings),
+    Bot(Settings),
+    Tournament(Settings),
+    Help,
+}
+
+pub const USAGE: &str = "\
+Usage: snake-rust [server|master|client|bot|tournament] [options]
+
+Common:
+  --config <path>          TOML config file (default: ./snake.toml if present, or $SNAKE_CONFIG)
+  -h, --help               Show this help
+
+server (game server, WebSocket):
+  --bind <addr>            Listen address            [SNAKE_SERVER_BIND]  (0.0.0.0:9001)
+  --public-addr <url>      Address given to clients  [SNAKE_PUBLIC_ADDR]  (ws://127.0.0.1:9001)
+  --master-url <url>       Master to register with   [SNAKE_MASTER_URL]   (http://127.0.0.1:9100)
+  --region <name>          Region label              [SNAKE_REGION]       (LOCAL)
+  --tick-rate <hz>         Simulation rate           [SNAKE_TICK_RATE]    (20)
+  --max-rooms <n>          Rooms the master may place here [SNAKE_MAX_ROOMS] (32)
+  --no-master              Do not start an embedded master [SNAKE_EMBED_MASTER=0]
+  --master-bind <addr>     Embedded master listen address [SNAKE_MASTER_BIND] (0.0.0.0:9100)
+  --rules <name|path>      Default room rules: classic, demo100, chaos, slither or a file [SNAKE_RULES] (classic)
+  --rules-dir <dir>        Directory of <name>.toml/.json rule files [SNAKE_RULES_DIR]
+  --mode <mode>            Mode of rooms joined directly: classic, endless, teams, royale [SNAKE_MODE] (classic)
+  --bot-fill <n>           Top rooms up to n snakes with server bots [SNAKE_BOT_FILL] (0 = off)
+
+master (room directory, HTTP):
+  --bind <addr>            Listen address            [SNAKE_MASTER_BIND]  (0.0.0.0:9100)
+  --default-server <url>   Game server used before any registers (ws://127.0.0.1:9001)
+
+client:
+  --master-url <url>       Master API                [SNAKE_MASTER_URL]   (http://127.0.0.1:9100)
+  --server-url <url>       Fallback game server      [SNAKE_SERVER_URL]   (ws://127.0.0.1:9001)
+  --rules <name|path>      Solo rules / rules of created rooms [SNAKE_RULES] (classic)
+  --name <name>            Player name, 16 characters max [SNAKE_PLAYER_NAME] (PLAYER)
+  --color <n>              Skin color, palette index 0..7 (6)
+  --pattern <pattern>      Skin pattern: solid, stripes, dots, gradient (solid)
+
+bot (sample pellet bot, no window; see WORKFLOW_PROJET.md to write your own):
+  --master-url <url>       Master used to find the room [SNAKE_MASTER_URL] (http://127.0.0.1:9100)
+  --server-url <url>       Game server if the room is not listed [SNAKE_SERVER_URL] (ws://127.0.0.1:9001)
+  --room <id>              Room to join              [SNAKE_BOT_ROOM]     (DEV-ROOM)
+  --name <name>            Player name               [SNAKE_BOT_NAME]     (BOT)
+
+tournament (headless bot-vs-bot matches, faster than real time):
+  --matches <n>            Number of matches (100)
+  --roster <a,b,...>       Bot profile of each snake (easy,normal,hard,hunter,farmer,rusher)
+  --rules <name|path>      Match rules (classic)
+  --rules-dir <dir>        Directory of <name>.toml/.json rule files
+  --mode <mode>            classic, teams or royale (classic)
+  --tick-rate <hz>         Simulation rate (20)
+  --format <csv|json>      Report format (csv)
+  --out <path>             Write the report to a file instead of stdout
+  --seed <n>               RNG seed for reproducible runs
+";
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args: Vec<String> = args.into_iter().collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        return Ok(Command::Help);
+    }
+
+    let sub = if args.first().map(|a| !a.starts_with('-')).unwrap_or(false) {
+        args.remove(0)
+    } else {
+        "client".to_owned()
+    };
+
+    let config_path = take_value(&mut args, "--config")?;
+    let mut settings = Settings::load(config_path.as_deref())?;
+
+    let mut it = args.into_iter();
+    while let Some(flag) = it.next() {
+        let mut value = || it.next().ok_or_else(|| format!("missing value for {}", flag));
+        match (sub.as_str(), flag.as_str()) {
+            ("server", "--bind") => settings.server.bind = value()?,
+            ("server", "--public-addr") => settings.server.public_addr = value()?,
+            ("server", "--master-url") => settings.server.master_url = value()?,
+            ("server", "--region") => settings.server.region = value()?,
+            ("server", "--tick-rate") => settings.server.tick_rate = parse_tick_rate(&value()?)?,
+            ("server", "--max-rooms") => {
+                let v = value()?;
+                settings.server.max_rooms =
+                    v.parse().map_err(|_| format!("invalid --max-rooms '{}'", v))?;
+            }
+            ("server", "--no-master") => settings.server.embed_master = false,
+            ("server", "--rules") => settings.server.rules = value()?,
+            ("server", "--rules-dir") => settings.server.rules_dir = Some(value()?),
+            ("server", "--mode") => settings.server.mode = parse_mode(&value()?)?,
+            ("server", "--bot-fill") => {
+                let v = value()?;
+                settings.server.bot_fill =
+                    v.parse().map_err(|_| format!("invalid --bot-fill '{}'", v))?;
+            }
+            ("server", "--master-bind") | ("master", "--bind") => settings.master.bind = value()?,
+            ("master", "--default-server") => settings.master.default_server_addr = value()?,
+            ("client" | "bot", "--master-url") => settings.client.master_url = value()?,
+            ("client" | "bot", "--server-url") => settings.client.server_url = value()?,
+            ("client", "--rules") => settings.client.rules = value()?,
+            ("client", "--name") => settings.client.name = value()?,
+            ("client", "--color") => {
+                let v = value()?;
+                settings.client.skin.color = v.parse().map_err(|_| format!("invalid --color '{}'", v))?;
+            }
+            ("client", "--pattern") => {
+                let v = value()?;
+                settings.client.skin.pattern = SkinPattern::parse(&v).ok_or_else(|| {
+                    format!("invalid --pattern '{}' (expected solid, stripes, dots, gradient)", v)
+                })?;
+            }
+            ("bot", "--room") => settings.bot.room = value()?,
+            ("bot", "--name") => settings.bot.name = value()?,
+            ("tournament", "--matches") => {
+                let v = value()?;
+                settings.tournament.matches =
+                    v.parse().map_err(|_| format!("invalid --matches '{}'", v))?;
+            }
+            ("tournament", "--roster") => {
+                settings.tournament.roster = value()?
+                    .split(',')
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
+            ("tournament", "--rules") => settings.tournament.rules = value()?,
+            ("tournament", "--rules-dir") => settings.tournament.rules_dir = Some(value()?),
+            ("tournament", "--mode") => settings.tournament.mode = parse_mode(&value()?)?,
+            ("tournament", "--tick-rate") => {
+                settings.tournament.tick_rate = parse_tick_rate(&value()?)?
+            }
+            ("tournament", "--format") => settings.tournament.format = parse_format(&value()?)?,
+            ("tournament", "--out") => settings.tournament.out = Some(value()?),
+            ("tournament", "--seed") => {
+                let v = value()?;
+                settings.tournament.seed =
+                    Some(v.parse().map_err(|_| format!("invalid --seed '{}'", v))?);
+            }
+            _ => return Err(format!("unknown option '{}' for '{}'", flag, sub)),
+        }
+    }
+
+    match sub.as_str() {
+        "server" => Ok(Command::Server(settings)),
+        "master" => Ok(Command::Master(settings)),
+        "client" => Ok(Command::Client(settings)),
+        "bot" => Ok(Command::Bot(settings)),
+        "tournament" => Ok(Command::Tournament(settings)),
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+fn take_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String> {
+    let Some(pos) = args.iter().position(|a| a == flag) else {
+        return Ok(None);
+    };
+    if pos + 1 >= args.len() {
+        return Err(format!("missing value for {}", flag));
+    }
+    let value = args.remove(pos + 1);
+    args.remove(pos);
+    Ok(Some(value))
+}
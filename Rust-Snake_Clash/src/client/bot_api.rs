@@ -0,0 +1,176 @@
+// Bot clients: programs that play through the same WebSocket protocol as the game
+// client. `BotClient` joins a room as device "bot" (flagged `is_bot` by the server)
+// and, after every snapshot, asks a callback for the next (dir, boost).
+// The raw protocol, for bots in other languages, is described in WORKFLOW_PROJET.md.
+
+use std::time::Duration;
+
+use macroquad::prelude::*;
+
+use crate::client::net::ClientRuntime;
+use crate::client::state::SnapshotBuffer;
+use crate::config::rules::GameRules;
+use crate::game::bot_profile::BotProfile;
+use crate::game::food::Pellets;
+use crate::game::mode::GameMode;
+use crate::game::skin::Skin;
+use crate::net::messages::{
+    ClientMessage, Event, PlayerState, ServerMessage, TokenState, Vec2f, BOT_DEVICE,
+};
+
+// Everything a bot knows after a snapshot.
+pub struct BotSnapshot<'a> {
+    pub server_tick: u32,
+    // Our snake (None if the server dropped it).
+    pub me: Option<&'a PlayerState>,
+    pub players: &'a [PlayerState],
+    pub pellets: &'a [Vec2f],
+    pub tokens: &'a [TokenState],
+    // Events since the previous callback (kills, deaths, time_up, ...).
+    pub events: &'a [Event],
+    pub time_left: f32,
+    pub countdown_left: f32,
+    // Battle royale safe zone, 0 in other modes.
+    pub zone_radius: f32,
+    pub rules: &'a GameRules,
+    pub mode: GameMode,
+}
+
+pub struct BotClient {
+    runtime: ClientRuntime,
+    snapshots: SnapshotBuffer,
+    player_id: Option<u32>,
+    seq: u32,
+}
+
+impl BotClient {
+    pub fn connect(server_url: String, room_id: &str, name: &str) -> Self {
+        let runtime = ClientRuntime::connect(server_url);
+        runtime.send(ClientMessage::JoinReq {
+            room_id: room_id.to_owned(),
+            name: name.to_owned(),
+            device: BOT_DEVICE.to_owned(),
+            client_time: 0.0,
+            skin: Skin::default(),
+            ticket: None,
+            profile: None,
+            spectator: false,
+        });
+        Self {
+            runtime,
+            snapshots: SnapshotBuffer::default(),
+            player_id: None,
+            seq: 0,
+        }
+    }
+
+    pub fn player_id(&self) -> Option<u32> {
+        self.player_id
+    }
+
+    // Blocks until the connection closes; `decide` runs once per received snapshot.
+    pub fn run<F>(&mut self, mut decide: F)
+    where
+        F: FnMut(&BotSnapshot) -> (Vec2f, bool),
+    {
+        while !self.runtime.is_closed() {
+            let mut fresh = false;
+            while let Some(msg) = self.runtime.try_recv() {
+                match &msg {
+                    ServerMessage::JoinOk { player_id, .. } => self.player_id = Some(*player_id),
+                    ServerMessage::Snapshot { .. } | ServerMessage::SnapshotDelta { .. } => {
+                        fresh = true
+                    }
+                    _ => {}
+                }
+                self.snapshots.push(msg);
+            }
+
+            if fresh && self.player_id.is_some() {
+                let mut players = self.snapshots.players_vec();
+                players.sort_by_key(|p| p.id);
+                let events = self.snapshots.take_events();
+                let buf = &self.snapshots;
+                let view = BotSnapshot {
+                    server_tick: buf.last_snapshot_tick,
+                    me: players.iter().find(|p| Some(p.id) == self.player_id),
+                    players: &players,
+                    pellets: &buf.pellets,
+                    tokens: &buf.tokens,
+                    events: &events,
+                    time_left: buf.time_left,
+                    countdown_left: buf.countdown_left,
+                    zone_radius: buf.zone_radius,
+                    rules: &buf.rules,
+                    mode: buf.mode,
+                };
+                let (dir, boost) = decide(&view);
+                self.send_input(dir, boost);
+            }
+
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn send_input(&mut self, dir: Vec2f, boost: bool) {
+        self.seq = self.seq.saturating_add(1);
+        let ack = self.snapshots.last_snapshot_tick;
+        self.runtime.send(ClientMessage::Input {
+            seq: self.seq,
+            tick: ack,
+            dir,
+            boost,
+            client_time: 0.0,
+            last_snapshot_ack: Some(ack),
+        });
+    }
+}
+
+// Sample bot (`snake-rust bot`): heads for the best pellet with the same scoring
+// as the built-in bots and steers back in near the edge. Never boosts.
+pub struct PelletBot {
+    pellets: Option<Pellets>,
+    search_radius: f32,
+    dir: Vec2,
+}
+
+impl Default for PelletBot {
+    fn default() -> Self {
+        Self {
+            pellets: None,
+            search_radius: BotProfile::default().pellet_search_radius,
+            dir: vec2(1.0, 0.0),
+        }
+    }
+}
+
+impl PelletBot {
+    pub fn decide(&mut self, view: &BotSnapshot) -> (Vec2f, bool) {
+        let Some(me) = view.me.filter(|p| p.alive) else {
+            return (Vec2f { x: self.dir.x, y: self.dir.y }, false);
+        };
+        let rules = view.rules;
+        let pellets = self
+            .pellets
+            .get_or_insert_with(|| Pellets::new(rules.pellet_bucket_size, rules.arena_radius));
+        pellets.clear();
+        for p in view.pellets {
+            pellets.spawn(vec2(p.x, p.y), rules.pellet_radius, 1, WHITE);
+        }
+
+        let head = vec2(me.head.x, me.head.y);
+        let mut desired = pellets
+            .best_pellet_target(head, self.search_radius)
+            .map(|target| (target - head).normalize_or_zero())
+            .unwrap_or(self.dir);
+
+        let edge = if view.zone_radius > 0.0 { view.zone_radius } else { rules.arena_radius };
+        if head.length() > edge * 0.80 {
+            desired = (desired * 0.62 - head.normalize_or_zero() * 1.10).normalize_or_zero();
+        }
+        if desired.length_squared() > 0.0001 {
+            self.dir = desired;
+        }
+        (Vec2f { x: self.dir.x, y: self.dir.y }, false)
+    }
+}
@@ -2,135 +2,883 @@ use std::sync::{Mutex, OnceLock};
 
 use macroquad::prelude::*;
 
-use crate::client::master_api::{create_room, delete_room, fetch_rooms, join_room, RoomInfo};
+use crate::client::master_api::{
+    local_room, poll_reply, request, requested_rules, CreateRoomRequest, LeaderboardPeriod, LeaderboardRow, MasterReply,
+    MasterRequest, MatchmakeRequest, PlayerProfile, RoomFilter, RoomInfo, ROOM_PAGE_SIZE,
+};
+use crate::client::menu::MenuState;
+use crate::client::prefs::{self, Prefs, RoomPrefs};
 use crate::client::runtime;
+use crate::game::mode::GameMode;
+use crate::game::skin::{SkinPattern, MAX_NAME_LEN, SKIN_PALETTE};
 
-static MENU_STATE: OnceLock<Mutex<MenuState>> = OnceLock::new();
+static LOBBY_UI: OnceLock<Mutex<LobbyUi>> = OnceLock::new();
 
-struct MenuState {
+const MAX_PLAYERS_RANGE: (u8, u8) = (2, 8);
+const FIELD_MAX_LEN: usize = 24;
+// Room list refresh while the menu is idle.
+const AUTO_REFRESH_SEC: f32 = 5.0;
+const ROW_H: f32 = 38.0;
+const ROW_GAP: f32 = 8.0;
+const RANK_ROW_H: f32 = 32.0;
+
+// Text field with the keyboard focus.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    RoomName,
+    Region,
+    AccessCode,
+    Rules,
+}
+
+struct CreateDialog {
+    name: String,
+    room: RoomPrefs,
+}
+
+// Leaderboard shown instead of the room list (RANKS button).
+struct RanksView {
+    period: LeaderboardPeriod,
+    // None = every mode.
+    mode: Option<GameMode>,
+    // Only the create-dialog region.
+    my_region: bool,
+    list: MenuState,
+    rows: Vec<LeaderboardRow>,
+    fetching: bool,
+    stale: bool,
+}
+
+impl Default for RanksView {
+    fn default() -> Self {
+        Self {
+            period: LeaderboardPeriod::default(),
+            mode: None,
+            my_region: false,
+            list: MenuState::Loading,
+            rows: Vec::new(),
+            fetching: false,
+            stale: false,
+        }
+    }
+}
+
+struct LobbyUi {
     in_game: bool,
+    list: MenuState,
+    // A FetchRooms is queued on the master worker.
+    fetching: bool,
+    // Filters or page changed while fetching: fetch again after the answer.
+    stale: bool,
+    // QuickPlay queued; joins the room it answers with.
+    matching: bool,
+    // CreateRoom queued; the dialog waits for the answer.
+    creating: bool,
+    // Room to select once it shows up in the list (just created).
+    select_after: Option<String>,
+    // Current page of the list, `total` rooms over all pages.
     rooms: Vec<RoomInfo>,
+    page: usize,
+    total: usize,
+    // First visible row of the page.
+    scroll: usize,
     selected: usize,
-    player_name: String,
     last_refresh: f32,
+    prefs: Prefs,
+    focus: Option<Field>,
+    dialog: Option<CreateDialog>,
+    // Last create/join failure, shown under the list.
+    notice: Option<String>,
+    // Master profile (rating, stats); asked for once per run.
+    profile: Option<PlayerProfile>,
+    profile_requested: bool,
+    show_ranks: bool,
+    ranks: RanksView,
 }
 
-impl Default for MenuState {
+impl Default for LobbyUi {
     fn default() -> Self {
         Self {
             in_game: false,
-            rooms: fetch_rooms(),
+            list: MenuState::Loading,
+            fetching: false,
+            stale: false,
+            matching: false,
+            creating: false,
+            select_after: None,
+            rooms: Vec::new(),
+            page: 0,
+            total: 0,
+            scroll: 0,
             selected: 0,
-            player_name: "PLAYER".to_owned(),
             last_refresh: 0.0,
+            prefs: prefs::get(),
+            focus: None,
+            dialog: None,
+            notice: None,
+            profile: None,
+            profile_requested: false,
+            show_ranks: false,
+            ranks: RanksView::default(),
+        }
+    }
+}
+
+impl LobbyUi {
+    // The current list stays on screen until the answer arrives.
+    fn refresh(&mut self) {
+        if self.fetching {
+            self.stale = true;
+            return;
+        }
+        self.fetching = true;
+        self.last_refresh = 0.0;
+        request(MasterRequest::FetchRooms {
+            filter: self.prefs.filter.clone(),
+            region: self.prefs.room.region.clone(),
+            page: self.page,
+        });
+    }
+
+    fn refresh_ranks(&mut self) {
+        if self.ranks.fetching {
+            self.ranks.stale = true;
+            return;
+        }
+        self.ranks.fetching = true;
+        request(MasterRequest::FetchLeaderboard {
+            period: self.ranks.period,
+            mode: self.ranks.mode,
+            region: self.ranks.my_region.then(|| self.prefs.room.region.clone()),
+        });
+    }
+
+    fn set_filter(&mut self, filter: RoomFilter) {
+        self.prefs.filter = filter;
+        self.save_prefs();
+        self.page = 0;
+        self.scroll = 0;
+        self.selected = 0;
+        self.refresh();
+    }
+
+    fn set_page(&mut self, page: usize) {
+        self.page = page;
+        self.scroll = 0;
+        self.selected = 0;
+        self.refresh();
+    }
+
+    fn page_count(&self) -> usize {
+        self.total.div_ceil(ROOM_PAGE_SIZE).max(1)
+    }
+
+    fn quick_play(&mut self) {
+        if self.matching {
+            return;
+        }
+        self.save_prefs();
+        self.matching = true;
+        self.notice = None;
+        request(MasterRequest::QuickPlay(MatchmakeRequest {
+            player_name: self.prefs.name.clone(),
+            region: self.prefs.room.region.clone(),
+            mode: None,
+            rules: None,
+        }));
+    }
+
+    fn handle_reply(&mut self, reply: MasterReply) {
+        match reply {
+            MasterReply::Rooms(result) => {
+                self.fetching = false;
+                self.last_refresh = 0.0;
+                match result {
+                    Ok(resp) => {
+                        self.list = if resp.total == 0 { MenuState::Empty } else { MenuState::Ready };
+                        self.rooms = resp.rooms;
+                        self.total = resp.total;
+                    }
+                    Err(err) => {
+                        self.list = MenuState::Error(err);
+                        self.rooms.clear();
+                        self.total = 0;
+                    }
+                }
+                // Rooms went away under the current page: go back to the last one.
+                if self.rooms.is_empty() && self.page >= self.page_count() {
+                    self.page = self.page_count() - 1;
+                    self.stale = true;
+                }
+                self.scroll = self.scroll.min(self.rooms.len().saturating_sub(1));
+                if let Some(room_id) = self.select_after.take()
+                    && let Some(pos) = self.rooms.iter().position(|r| r.room_id == room_id)
+                {
+                    self.selected = pos;
+                }
+                self.selected = self.selected.min(self.rooms.len().saturating_sub(1));
+                if self.stale {
+                    self.stale = false;
+                    self.refresh();
+                }
+            }
+            MasterReply::ProfileCreated(Ok(created)) | MasterReply::ProfileRenewed(Ok(Some(created))) => {
+                self.prefs.profile_id = created.profile.player_id.clone();
+                self.prefs.profile_token = created.token;
+                self.save_prefs();
+                self.profile = Some(created.profile);
+            }
+            MasterReply::ProfileCreated(Err(err)) | MasterReply::ProfileRenewed(Err(err)) => {
+                eprintln!("profile: {}", err)
+            }
+            // Unknown to the master (new master data) or token expired: start a new profile.
+            MasterReply::ProfileRenewed(Ok(None)) | MasterReply::Profile(Ok(None)) => {
+                request(MasterRequest::CreateProfile(self.prefs.name.clone()));
+            }
+            MasterReply::Profile(Ok(profile)) => self.profile = profile,
+            MasterReply::Profile(Err(err)) => eprintln!("profile: {}", err),
+            MasterReply::Leaderboard(result) => {
+                self.ranks.fetching = false;
+                match result {
+                    Ok(resp) => {
+                        self.ranks.list = if resp.entries.is_empty() { MenuState::Empty } else { MenuState::Ready };
+                        self.ranks.rows = resp.entries;
+                    }
+                    Err(err) => self.ranks.list = MenuState::Error(err),
+                }
+                if self.ranks.stale {
+                    self.ranks.stale = false;
+                    self.refresh_ranks();
+                }
+            }
+            MasterReply::Matched(result) => {
+                self.matching = false;
+                match result {
+                    Ok(matched) => self.join(&matched.room, Some(matched.token), false),
+                    Err(err) => self.notice = Some(format!("quick play failed: {}", err)),
+                }
+            }
+            MasterReply::Created(result) => {
+                self.creating = false;
+                match result {
+                    Ok(created) => {
+                        if let Some(dialog) = self.dialog.take() {
+                            self.prefs.room = dialog.room;
+                            self.save_prefs();
+                        }
+                        self.notice = None;
+                        self.select_after = Some(created.room_id);
+                        self.refresh();
+                    }
+                    Err(err) => self.notice = Some(format!("create failed: {}", err)),
+                }
+            }
+            MasterReply::Deleted(result) => match result {
+                Ok(_) => self.refresh(),
+                Err(err) => self.notice = Some(format!("delete failed: {}", err)),
+            },
+        }
+    }
+
+    fn save_prefs(&mut self) {
+        if let Err(err) = prefs::set(self.prefs.clone()) {
+            eprintln!("{}", err);
+        }
+    }
+
+    // The list already has the game server address, so joining needs no master call.
+    // Spectators enter without a snake and do not take a slot.
+    fn join(&mut self, room: &RoomInfo, ticket: Option<String>, spectator: bool) {
+        self.save_prefs();
+        runtime::init(room.server_addr.clone());
+        runtime::send_join(
+            room.room_id.clone(),
+            self.prefs.name.clone(),
+            self.prefs.skin,
+            "desktop".to_owned(),
+            ticket,
+            (!self.prefs.profile_token.is_empty()).then(|| self.prefs.profile_token.clone()),
+            spectator,
+        );
+        self.in_game = true;
+    }
+
+    fn text_mut(&mut self, field: Field) -> Option<&mut String> {
+        match field {
+            Field::Name => Some(&mut self.prefs.name),
+            Field::RoomName => self.dialog.as_mut().map(|d| &mut d.name),
+            Field::Region => self.dialog.as_mut().map(|d| &mut d.room.region),
+            Field::AccessCode => self.dialog.as_mut().map(|d| &mut d.room.access_code),
+            Field::Rules => self.dialog.as_mut().map(|d| &mut d.room.rules),
         }
     }
+
+    // Typed characters go to the focused field; Enter/Escape drop the focus.
+    fn handle_typing(&mut self) {
+        let typed: Vec<char> = std::iter::from_fn(get_char_pressed).collect();
+        let Some(field) = self.focus else {
+            return;
+        };
+        let max_len = if field == Field::Name { MAX_NAME_LEN } else { FIELD_MAX_LEN };
+        if let Some(text) = self.text_mut(field) {
+            for c in typed {
+                if !c.is_control() && text.chars().count() < max_len {
+                    text.push(c);
+                }
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                text.pop();
+            }
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Escape) {
+            self.set_focus(None);
+        }
+    }
+
+    fn set_focus(&mut self, focus: Option<Field>) {
+        if self.focus == Some(Field::Name) && focus != Some(Field::Name) {
+            self.save_prefs();
+        }
+        self.focus = focus;
+    }
+
+    fn create(&mut self) {
+        if self.creating {
+            return;
+        }
+        let Some(dialog) = self.dialog.as_ref() else {
+            return;
+        };
+        let room = &dialog.room;
+        let req = CreateRoomRequest {
+            name: dialog.name.trim().to_owned(),
+            region: room.region.trim().to_owned(),
+            max_players: room.max_players,
+            is_private: room.is_private,
+            access_code: (room.is_private && !room.access_code.trim().is_empty())
+                .then(|| room.access_code.trim().to_owned()),
+            rules: if room.rules.trim().is_empty() { requested_rules() } else { room.rules.trim().to_owned() },
+            mode: room.mode,
+        };
+        self.creating = true;
+        self.notice = None;
+        request(MasterRequest::CreateRoom(req));
+    }
 }
 
 pub fn update() -> bool {
-    let state = MENU_STATE.get_or_init(|| Mutex::new(MenuState::default()));
+    let state = LOBBY_UI.get_or_init(|| Mutex::new(LobbyUi::default()));
     let mut guard = match state.lock() {
         Ok(g) => g,
         Err(_) => return true,
     };
+    let ui = &mut *guard;
 
-    if guard.in_game {
+    if ui.in_game {
         return false;
     }
 
     let dt = get_frame_time();
-    guard.last_refresh += dt;
+    ui.last_refresh += dt;
+    while let Some(reply) = poll_reply() {
+        ui.handle_reply(reply);
+    }
+    let first_load = ui.list == MenuState::Loading && !ui.fetching;
+    let auto_refresh = ui.last_refresh >= AUTO_REFRESH_SEC && ui.dialog.is_none() && !ui.show_ranks;
+    if first_load || auto_refresh {
+        ui.refresh();
+    }
+    if !ui.profile_requested {
+        ui.profile_requested = true;
+        if ui.prefs.profile_id.is_empty() || ui.prefs.profile_token.is_empty() {
+            request(MasterRequest::CreateProfile(ui.prefs.name.clone()));
+        } else {
+            request(MasterRequest::RenewProfile {
+                player_id: ui.prefs.profile_id.clone(),
+                token: ui.prefs.profile_token.clone(),
+            });
+        }
+    }
+    ui.handle_typing();
+    if is_mouse_button_pressed(MouseButton::Left) {
+        // Clicking anywhere drops the focus; a field click takes it back below.
+        ui.set_focus(None);
+    }
 
     clear_background(Color::from_rgba(10, 12, 18, 255));
     let w = screen_width();
     let h = screen_height();
-    let ui_s = 1.0;
+    let interactive = ui.dialog.is_none();
 
     draw_text("Snake Clash MVP", 32.0, 56.0, 36.0, WHITE);
     draw_text("MENU", 32.0, 88.0, 22.0, Color::from_rgba(255, 255, 255, 180));
+    let view_label = if ui.show_ranks { "ROOMS" } else { "RANKS" };
+    if button_hit(w - 172.0, 30.0, 140.0, 36.0, view_label) && interactive {
+        ui.show_ranks = !ui.show_ranks;
+        if ui.show_ranks {
+            ui.refresh_ranks();
+        } else {
+            ui.refresh();
+        }
+    }
+
+    // Player settings
+    let name = ui.prefs.name.clone();
+    if text_field(32.0, 104.0, 280.0, 34.0, "NAME", &name, ui.focus == Some(Field::Name)) && interactive {
+        ui.set_focus(Some(Field::Name));
+    }
+    let (head, body) = ui.prefs.skin.colors();
+    draw_circle(346.0, 121.0, 14.0, head);
+    draw_circle(370.0, 121.0, 11.0, body);
+    if button_hit(392.0, 104.0, 110.0, 34.0, &format!("COLOR {}", ui.prefs.skin.color)) && interactive {
+        ui.prefs.skin.color = ((ui.prefs.skin.color as usize + 1) % SKIN_PALETTE.len()) as u8;
+        ui.save_prefs();
+    }
+    let pattern_label = format!("{:?}", ui.prefs.skin.pattern).to_uppercase();
+    if button_hit(512.0, 104.0, 130.0, 34.0, &pattern_label) && interactive {
+        let idx = SkinPattern::ALL
+            .iter()
+            .position(|p| *p == ui.prefs.skin.pattern)
+            .unwrap_or(0);
+        ui.prefs.skin.pattern = SkinPattern::ALL[(idx + 1) % SkinPattern::ALL.len()];
+        ui.save_prefs();
+    }
+    let stats = match &ui.profile {
+        Some(p) => format!(
+            "Rating {:.0}{}  |  {} games  {} wins  {} kills  |  best {}",
+            p.rating,
+            p.global_rank.map(|r| format!(" (#{})", r)).unwrap_or_default(),
+            p.games,
+            p.wins,
+            p.kills,
+            p.best_score
+        ),
+        None => "Offline profile".to_owned(),
+    };
+    draw_text(&stats, 32.0, 154.0, 16.0, Color::from_rgba(255, 255, 255, 140));
 
     // Room list panel
     let panel_x = 32.0;
-    let panel_y = 120.0;
+    let panel_y = 160.0;
     let panel_w = w - 64.0;
-    let panel_h = h - 240.0;
+    let panel_h = h - 280.0;
     draw_rectangle(panel_x, panel_y, panel_w, panel_h, Color::from_rgba(0, 0, 0, 90));
     draw_rectangle_lines(panel_x, panel_y, panel_w, panel_h, 2.0, Color::from_rgba(255, 255, 255, 40));
 
-    let y = panel_y + 36.0;
-    let mut join_target: Option<usize> = None;
-    for (idx, room) in guard.rooms.iter().enumerate() {
-        let row_h = 38.0;
-        let row_y = y + (idx as f32) * (row_h + 8.0);
-        let is_sel = idx == guard.selected;
-        let bg = if is_sel { Color::from_rgba(90, 210, 255, 40) } else { Color::from_rgba(0, 0, 0, 0) };
-        draw_rectangle(panel_x + 16.0, row_y - 22.0, panel_w - 32.0, row_h, bg);
-        draw_text(
-            &format!("{}  {} ({}/{})", room.room_id, room.name, room.players, room.max_players),
-            panel_x + 24.0,
-            row_y,
-            20.0 * ui_s,
-            WHITE,
-        );
-        if button_hit(panel_x + panel_w - 140.0, row_y - 22.0, 100.0, row_h, "JOIN") {
-            join_target = Some(idx);
-        }
+    if ui.show_ranks {
+        draw_ranks(ui, panel_x, panel_y, panel_w, panel_h);
+        return true;
     }
 
-    if let Some(idx) = join_target {
-        let room = guard.rooms.get(idx).cloned();
-        if let Some(room) = room {
-            guard.selected = idx;
-            if let Some(server_addr) = join_room(&room.room_id, &guard.player_name, None) {
-                runtime::init(server_addr);
-                    runtime::send_join(room.room_id.clone(), guard.player_name.clone(), "desktop".to_owned());
-                guard.in_game = true;
+    // Filters and sort
+    let filter = ui.prefs.filter.clone();
+    let mut next_filter = filter.clone();
+    let toggles = [
+        ("SPACE", filter.has_space),
+        ("PUBLIC", filter.hide_private),
+        ("WAITING", filter.waiting_only),
+        ("REGION", filter.my_region),
+    ];
+    let mut fx = panel_x + 16.0;
+    for (idx, (label, on)) in toggles.iter().enumerate() {
+        let text = format!("{} {}", if *on { "[x]" } else { "[ ]" }, label);
+        let bw = measure_text(&text, None, 20, 1.0).width + 32.0;
+        if button_hit(fx, panel_y + 12.0, bw, 32.0, &text) && interactive {
+            match idx {
+                0 => next_filter.has_space = !filter.has_space,
+                1 => next_filter.hide_private = !filter.hide_private,
+                2 => next_filter.waiting_only = !filter.waiting_only,
+                _ => next_filter.my_region = !filter.my_region,
             }
         }
+        fx += bw + 8.0;
     }
 
-    if button_hit(panel_x + 16.0, panel_y + panel_h - 56.0, 140.0, 36.0, "REFRESH") {
-        guard.rooms = fetch_rooms();
-        guard.selected = 0;
-        guard.last_refresh = 0.0;
+    let y = panel_y + 84.0;
+    let buttons_y = panel_y + panel_h - 56.0;
+    let pager_y = buttons_y - 46.0;
+    let list_bottom = pager_y - 10.0;
+    let sort_label = format!("SORT: {}", filter.sort.as_str().to_uppercase());
+    if button_hit(panel_x + 16.0, pager_y, 170.0, 36.0, &sort_label) && interactive {
+        next_filter.sort = filter.sort.next();
+    }
+    if next_filter != filter {
+        ui.set_filter(next_filter);
     }
 
-    if button_hit(panel_x + 172.0, panel_y + panel_h - 56.0, 140.0, 36.0, "CREATE") {
-        let room_name = format!("{}'s Room", guard.player_name);
-        if let Some(created) = create_room(&room_name, 4) {
-            guard.rooms = fetch_rooms();
-            if let Some(pos) = guard.rooms.iter().position(|r| r.room_id == created.room_id) {
-                guard.selected = pos;
+    let visible_rows = (((list_bottom - (y - 22.0)) / (ROW_H + ROW_GAP)).floor() as usize).max(1);
+    // Room to enter, and whether as a spectator.
+    let mut join_target: Option<(RoomInfo, bool)> = None;
+    match ui.list.clone() {
+        MenuState::Loading => {
+            draw_spinner(panel_x + 36.0, y - 7.0, 10.0);
+            draw_text("Loading rooms...", panel_x + 56.0, y, 22.0, Color::from_rgba(255, 255, 255, 180));
+        }
+        MenuState::Error(err) => {
+            draw_text("Master unreachable", panel_x + 24.0, y, 22.0, Color::from_rgba(255, 120, 110, 230));
+            draw_text(&err, panel_x + 24.0, y + 26.0, 18.0, Color::from_rgba(255, 255, 255, 140));
+            if button_hit(panel_x + 24.0, y + 44.0, 260.0, 36.0, "PLAY ON LOCAL SERVER") && interactive {
+                join_target = Some((local_room(), false));
             }
-            guard.last_refresh = 0.0;
         }
-    }
-
-    if button_hit(panel_x + 328.0, panel_y + panel_h - 56.0, 140.0, 36.0, "DELETE") {
-        if let Some(room) = guard.rooms.get(guard.selected) {
-            if delete_room(&room.room_id) {
-                guard.rooms = fetch_rooms();
-                if guard.rooms.is_empty() {
-                    guard.selected = 0;
-                } else {
-                    guard.selected = guard.selected.min(guard.rooms.len() - 1);
+        MenuState::Empty => {
+            let hint = if filter == RoomFilter::default() {
+                "No rooms yet: CREATE one or QUICK PLAY."
+            } else {
+                "No room matches the filters."
+            };
+            draw_text(hint, panel_x + 24.0, y, 22.0, Color::from_rgba(255, 255, 255, 180));
+        }
+        MenuState::Ready => {
+            let (_, wheel) = mouse_wheel();
+            let max_scroll = ui.rooms.len().saturating_sub(visible_rows);
+            if wheel < 0.0 && interactive {
+                ui.scroll = (ui.scroll + 1).min(max_scroll);
+            } else if wheel > 0.0 && interactive {
+                ui.scroll = ui.scroll.saturating_sub(1);
+            }
+            ui.scroll = ui.scroll.min(max_scroll);
+            for (row, (idx, room)) in ui.rooms.iter().enumerate().skip(ui.scroll).take(visible_rows).enumerate() {
+                let row_y = y + (row as f32) * (ROW_H + ROW_GAP);
+                let is_sel = idx == ui.selected;
+                let bg = if is_sel { Color::from_rgba(90, 210, 255, 40) } else { Color::from_rgba(0, 0, 0, 0) };
+                draw_rectangle(panel_x + 16.0, row_y - 22.0, panel_w - 44.0, ROW_H, bg);
+                let bots = if room.bots > 0 { format!(" {} bot", room.bots) } else { String::new() };
+                let private = if room.is_private { " [private]" } else { "" };
+                let watching = if room.spectators > 0 { format!(" {} watching", room.spectators) } else { String::new() };
+                let ping = room.ping_ms.map(|p| format!("  {}ms", p)).unwrap_or_default();
+                draw_text(
+                    &format!(
+                        "{}  {} [{}] ({}/{}{}){}{}{}",
+                        room.room_id,
+                        room.name,
+                        room.mode.as_str(),
+                        room.players,
+                        room.max_players,
+                        bots,
+                        watching,
+                        private,
+                        ping
+                    ),
+                    panel_x + 24.0,
+                    row_y,
+                    20.0,
+                    WHITE,
+                );
+                if button_hit(panel_x + panel_w - 244.0, row_y - 22.0, 84.0, ROW_H, "WATCH") && interactive {
+                    join_target = Some((room.clone(), true));
+                    ui.selected = idx;
                 }
-                guard.last_refresh = 0.0;
+                if button_hit(panel_x + panel_w - 152.0, row_y - 22.0, 100.0, ROW_H, "JOIN") && interactive {
+                    join_target = Some((room.clone(), false));
+                    ui.selected = idx;
+                }
+            }
+            // Scrollbar
+            if max_scroll > 0 {
+                let track_y = y - 22.0;
+                let track_h = visible_rows as f32 * (ROW_H + ROW_GAP) - ROW_GAP;
+                let thumb_h = (track_h * visible_rows as f32 / ui.rooms.len() as f32).max(16.0);
+                let thumb_y = track_y + (track_h - thumb_h) * ui.scroll as f32 / max_scroll as f32;
+                let bar_x = panel_x + panel_w - 22.0;
+                draw_rectangle(bar_x, track_y, 6.0, track_h, Color::from_rgba(255, 255, 255, 25));
+                draw_rectangle(bar_x, thumb_y, 6.0, thumb_h, Color::from_rgba(90, 210, 255, 160));
             }
         }
     }
 
+    if let Some((room, spectator)) = join_target {
+        ui.join(&room, None, spectator);
+    }
+
+    if button_hit(panel_x + 16.0, buttons_y, 140.0, 36.0, "REFRESH") && interactive {
+        ui.refresh();
+    }
+
+    if button_hit(panel_x + 172.0, buttons_y, 140.0, 36.0, "CREATE") && interactive {
+        ui.dialog = Some(CreateDialog {
+            name: format!("{}'s Room", ui.prefs.name),
+            room: ui.prefs.room.clone(),
+        });
+    }
+
+    if button_hit(panel_x + 328.0, buttons_y, 140.0, 36.0, "DELETE")
+        && interactive
+        && ui.list == MenuState::Ready
+        && let Some(room) = ui.rooms.get(ui.selected)
+    {
+        request(MasterRequest::DeleteRoom(room.room_id.clone()));
+    }
+
+    let quick_label = if ui.matching { "MATCHING" } else { "QUICK PLAY" };
+    if button_hit(panel_x + 484.0, buttons_y, 140.0, 36.0, quick_label) && interactive {
+        ui.quick_play();
+    }
+    if ui.matching {
+        draw_spinner(panel_x + 606.0, buttons_y + 18.0, 8.0);
+    }
+
+    // Pages: "<" / ">" on the right of the sort row.
+    if ui.list == MenuState::Ready {
+        let pages = ui.page_count();
+        let px = panel_x + panel_w - 140.0;
+        let label = format!("{}/{} ({})", ui.page + 1, pages, ui.total);
+        let label_w = measure_text(&label, None, 18, 1.0).width;
+        draw_text(&label, px - label_w - 12.0, pager_y + 24.0, 18.0, Color::from_rgba(255, 255, 255, 140));
+        if button_hit(px, pager_y, 58.0, 36.0, "<") && interactive && ui.page > 0 {
+            ui.set_page(ui.page - 1);
+        }
+        if button_hit(px + 66.0, pager_y, 58.0, 36.0, ">") && interactive && ui.page + 1 < pages {
+            ui.set_page(ui.page + 1);
+        }
+    }
+
+    if ui.fetching && ui.list != MenuState::Loading {
+        draw_spinner(panel_x + 204.0, pager_y + 18.0, 7.0);
+    }
     draw_text(
-        &format!("Last refresh: {:04.1}s", guard.last_refresh),
-        panel_x + 16.0,
-        panel_y + panel_h - 86.0,
+        &format!("{:04.1}s", ui.last_refresh),
+        panel_x + 218.0,
+        pager_y + 24.0,
         18.0,
         Color::from_rgba(255, 255, 255, 140),
     );
+    if let Some(notice) = &ui.notice {
+        draw_text(notice, panel_x + 16.0, panel_y + panel_h + 30.0, 20.0, Color::from_rgba(255, 120, 110, 230));
+    }
+
+    if ui.dialog.is_some() {
+        draw_create_dialog(ui, w, h);
+    }
 
     true
 }
 
+// Timed modes only: endless rooms never report results.
+fn next_board_mode(mode: Option<GameMode>) -> Option<GameMode> {
+    match mode {
+        None => Some(GameMode::Classic),
+        Some(GameMode::Classic) => Some(GameMode::Teams),
+        Some(GameMode::Teams) => Some(GameMode::Royale),
+        Some(_) => None,
+    }
+}
+
+fn draw_ranks(ui: &mut LobbyUi, panel_x: f32, panel_y: f32, panel_w: f32, panel_h: f32) {
+    let dim = Color::from_rgba(255, 255, 255, 140);
+
+    // Filters
+    let mut changed = false;
+    if button_hit(panel_x + 16.0, panel_y + 12.0, 140.0, 32.0, ui.ranks.period.label()) {
+        ui.ranks.period = ui.ranks.period.next();
+        changed = true;
+    }
+    let mode_label = ui.ranks.mode.map(|m| m.as_str().to_uppercase()).unwrap_or_else(|| "ALL MODES".to_owned());
+    if button_hit(panel_x + 164.0, panel_y + 12.0, 150.0, 32.0, &mode_label) {
+        ui.ranks.mode = next_board_mode(ui.ranks.mode);
+        changed = true;
+    }
+    let region_label = format!("{} REGION", if ui.ranks.my_region { "[x]" } else { "[ ]" });
+    if button_hit(panel_x + 322.0, panel_y + 12.0, 150.0, 32.0, &region_label) {
+        ui.ranks.my_region = !ui.ranks.my_region;
+        changed = true;
+    }
+    if changed {
+        ui.refresh_ranks();
+    }
+
+    let y = panel_y + 84.0;
+    let cols = [panel_x + 24.0, panel_x + 84.0, panel_x + 330.0, panel_x + 420.0, panel_x + 500.0, panel_x + 580.0];
+    for (x, title) in cols.iter().zip(["#", "NAME", "BEST", "WINS", "GAMES", "KILLS"]) {
+        draw_text(title, *x, y - 24.0, 18.0, dim);
+    }
+    match &ui.ranks.list {
+        MenuState::Loading => {
+            draw_spinner(panel_x + 36.0, y + 9.0, 10.0);
+            draw_text("Loading ranks...", panel_x + 56.0, y + 16.0, 22.0, Color::from_rgba(255, 255, 255, 180));
+        }
+        MenuState::Error(err) => {
+            draw_text("Master unreachable", panel_x + 24.0, y + 16.0, 22.0, Color::from_rgba(255, 120, 110, 230));
+            draw_text(err, panel_x + 24.0, y + 42.0, 18.0, dim);
+        }
+        MenuState::Empty => {
+            draw_text("No ranked games yet.", panel_x + 24.0, y + 16.0, 22.0, Color::from_rgba(255, 255, 255, 180));
+        }
+        MenuState::Ready => {
+            let rows_bottom = panel_y + panel_h - 66.0;
+            for (idx, row) in ui.ranks.rows.iter().enumerate() {
+                let row_y = y + idx as f32 * RANK_ROW_H;
+                if row_y + RANK_ROW_H > rows_bottom {
+                    break;
+                }
+                if row.player_id == ui.prefs.profile_id {
+                    draw_rectangle(panel_x + 16.0, row_y - 6.0, panel_w - 32.0, RANK_ROW_H - 4.0, Color::from_rgba(90, 210, 255, 40));
+                }
+                let cells = [
+                    row.rank.to_string(),
+                    row.name.clone(),
+                    row.best_score.to_string(),
+                    row.wins.to_string(),
+                    row.games.to_string(),
+                    row.kills.to_string(),
+                ];
+                for (x, cell) in cols.iter().zip(cells) {
+                    draw_text(&cell, *x, row_y + 16.0, 20.0, WHITE);
+                }
+            }
+        }
+    }
+
+    let buttons_y = panel_y + panel_h - 56.0;
+    if button_hit(panel_x + 16.0, buttons_y, 140.0, 36.0, "REFRESH") {
+        ui.refresh_ranks();
+    }
+    if ui.ranks.fetching && ui.ranks.list != MenuState::Loading {
+        draw_spinner(panel_x + 176.0, buttons_y + 18.0, 7.0);
+    }
+}
+
+fn draw_create_dialog(ui: &mut LobbyUi, w: f32, h: f32) {
+    draw_rectangle(0.0, 0.0, w, h, Color::from_rgba(0, 0, 0, 150));
+    let dw = 480.0;
+    let dh = 470.0;
+    let x = (w - dw) * 0.5;
+    let mut y = (h - dh) * 0.5;
+    draw_rectangle(x, y, dw, dh, Color::from_rgba(16, 20, 30, 250));
+    draw_rectangle_lines(x, y, dw, dh, 2.0, Color::from_rgba(255, 255, 255, 60));
+    draw_text("CREATE ROOM", x + 20.0, y + 34.0, 26.0, WHITE);
+    y += 62.0;
+
+    let fx = x + 160.0;
+    let fw = dw - 180.0;
+    let row = 50.0;
+    let Some(dialog) = ui.dialog.as_ref() else {
+        return;
+    };
+    let room = dialog.room.clone();
+    let name = dialog.name.clone();
+    let focus = ui.focus;
+
+    let mut clicked: Option<Field> = None;
+    draw_text("Name", x + 20.0, y + 22.0, 20.0, WHITE);
+    if text_field(fx, y, fw, 32.0, "", &name, focus == Some(Field::RoomName)) {
+        clicked = Some(Field::RoomName);
+    }
+    y += row;
+    draw_text("Region", x + 20.0, y + 22.0, 20.0, WHITE);
+    if text_field(fx, y, fw, 32.0, "", &room.region, focus == Some(Field::Region)) {
+        clicked = Some(Field::Region);
+    }
+    if room.region.is_empty() && focus != Some(Field::Region) {
+        draw_text("any", fx + 10.0, y + 22.0, 18.0, Color::from_rgba(255, 255, 255, 90));
+    }
+    y += row;
+
+    draw_text("Max players", x + 20.0, y + 22.0, 20.0, WHITE);
+    let mut max_players = room.max_players;
+    if button_hit(fx, y, 40.0, 32.0, "-") {
+        max_players = max_players.saturating_sub(1).max(MAX_PLAYERS_RANGE.0);
+    }
+    draw_text(&max_players.to_string(), fx + 56.0, y + 23.0, 22.0, WHITE);
+    if button_hit(fx + 90.0, y, 40.0, 32.0, "+") {
+        max_players = (max_players + 1).min(MAX_PLAYERS_RANGE.1);
+    }
+    y += row;
+
+    draw_text("Private", x + 20.0, y + 22.0, 20.0, WHITE);
+    let mut is_private = room.is_private;
+    if button_hit(fx, y, 80.0, 32.0, if is_private { "ON" } else { "OFF" }) {
+        is_private = !is_private;
+    }
+    y += row;
+
+    if is_private {
+        draw_text("Access code", x + 20.0, y + 22.0, 20.0, WHITE);
+        if text_field(fx, y, fw, 32.0, "", &room.access_code, focus == Some(Field::AccessCode)) {
+            clicked = Some(Field::AccessCode);
+        }
+    }
+    y += row;
+
+    draw_text("Rules", x + 20.0, y + 22.0, 20.0, WHITE);
+    if text_field(fx, y, fw, 32.0, "", &room.rules, focus == Some(Field::Rules)) {
+        clicked = Some(Field::Rules);
+    }
+    if room.rules.is_empty() && focus != Some(Field::Rules) {
+        let default_rules = requested_rules();
+        let hint = if default_rules.is_empty() { "server default".to_owned() } else { default_rules };
+        draw_text(&hint, fx + 10.0, y + 22.0, 18.0, Color::from_rgba(255, 255, 255, 90));
+    }
+    y += row;
+
+    draw_text("Mode", x + 20.0, y + 22.0, 20.0, WHITE);
+    let mut mode = room.mode;
+    if button_hit(fx, y, 160.0, 32.0, &mode.as_str().to_uppercase()) {
+        mode = mode.next();
+    }
+    y += row + 10.0;
+
+    let create = button_hit(x + 20.0, y, 140.0, 36.0, if ui.creating { "CREATING" } else { "CREATE" });
+    if ui.creating {
+        draw_spinner(x + 350.0, y + 18.0, 10.0);
+    } else if let Some(notice) = &ui.notice {
+        draw_text(notice, x + 20.0, y + 60.0, 18.0, Color::from_rgba(255, 120, 110, 230));
+    }
+    let cancel = button_hit(x + 176.0, y, 140.0, 36.0, "CANCEL");
+
+    if let Some(dialog) = ui.dialog.as_mut() {
+        dialog.room.max_players = max_players;
+        dialog.room.is_private = is_private;
+        dialog.room.mode = mode;
+    }
+    if clicked.is_some() {
+        ui.set_focus(clicked);
+    }
+    if create {
+        ui.create();
+    } else if cancel || is_key_pressed(KeyCode::Escape) && focus.is_none() {
+        // A late Created reply still refreshes the list.
+        ui.dialog = None;
+        ui.notice = None;
+    }
+}
+
+// Eight dots turning around (x, y).
+fn draw_spinner(x: f32, y: f32, radius: f32) {
+    let step = (get_time() * 10.0) as i64;
+    for i in 0..8 {
+        let angle = i as f32 * std::f32::consts::TAU / 8.0;
+        let age = (step - i as i64).rem_euclid(8) as f32;
+        let alpha = (255.0 * (1.0 - age / 8.0)) as u8;
+        draw_circle(
+            x + angle.cos() * radius,
+            y + angle.sin() * radius,
+            radius * 0.22,
+            Color::from_rgba(90, 210, 255, alpha),
+        );
+    }
+}
+
+// Returns true when clicked (the caller gives it the focus).
+fn text_field(x: f32, y: f32, w: f32, h: f32, label: &str, value: &str, focused: bool) -> bool {
+    let hovered = {
+        let (mx, my) = mouse_position();
+        mx >= x && mx <= x + w && my >= y && my <= y + h
+    };
+    let border = if focused { Color::from_rgba(90, 210, 255, 200) } else { Color::from_rgba(255, 255, 255, 50) };
+    draw_rectangle(x, y, w, h, Color::from_rgba(0, 0, 0, 90));
+    draw_rectangle_lines(x, y, w, h, 2.0, border);
+    let mut text_x = x + 10.0;
+    if !label.is_empty() {
+        draw_text(label, text_x, y + h * 0.68, 18.0, Color::from_rgba(255, 255, 255, 140));
+        text_x += measure_text(label, None, 18, 1.0).width + 12.0;
+    }
+    let caret = if focused && (get_time() * 2.0) as i64 % 2 == 0 { "_" } else { "" };
+    draw_text(&format!("{}{}", value, caret), text_x, y + h * 0.68, 20.0, WHITE);
+    hovered && is_mouse_button_pressed(MouseButton::Left)
+}
+
 fn button_hit(x: f32, y: f32, w: f32, h: f32, label: &str) -> bool {
     let hovered = {
         let (mx, my) = mouse_position();
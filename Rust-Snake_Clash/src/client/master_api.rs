@@ -1,10 +1,64 @@
 #![allow(dead_code)]
 
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::game::mode::GameMode;
+
+// Rooms per `GET /rooms` page in the menu.
+pub const ROOM_PAGE_SIZE: usize = 50;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomsResponse {
     pub rooms: Vec<RoomInfo>,
+    #[serde(default)]
+    pub total: usize,
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default)]
+    pub per_page: usize,
+}
+
+// Room browser filters, sent as the `GET /rooms` query string.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomFilter {
+    // Only rooms of the create-dialog region.
+    pub my_region: bool,
+    pub waiting_only: bool,
+    pub has_space: bool,
+    pub hide_private: bool,
+    pub sort: RoomSort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSort {
+    #[default]
+    Id,
+    Players,
+    Ping,
+}
+
+impl RoomSort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Players => "players",
+            Self::Ping => "ping",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Id => Self::Players,
+            Self::Players => Self::Ping,
+            Self::Ping => Self::Id,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,10 +68,19 @@ pub struct RoomInfo {
     pub server_addr: String,
     pub region: String,
     pub players: u8,
+    // External bot clients among `players`.
+    #[serde(default)]
+    pub bots: u8,
+    #[serde(default)]
+    pub spectators: u8,
     pub max_players: u8,
     pub is_private: bool,
     pub status: String,
     pub ping_ms: Option<u16>,
+    #[serde(default)]
+    pub rules: String,
+    #[serde(default)]
+    pub mode: GameMode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +90,8 @@ pub struct CreateRoomRequest {
     pub max_players: u8,
     pub is_private: bool,
     pub access_code: Option<String>,
+    pub rules: String,
+    pub mode: GameMode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,64 +100,369 @@ pub struct CreateRoomResponse {
     pub server_addr: String,
 }
 
-pub fn fetch_rooms() -> Vec<RoomInfo> {
-    let url = "http://127.0.0.1:9100/rooms";
-    let response = ureq::get(url).call();
-    if let Ok(resp) = response {
-        if let Ok(body) = resp.into_json::<RoomsResponse>() {
-            return body.rooms;
+// Subset of the master profile shown in the menu.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub player_id: String,
+    pub name: String,
+    pub rating: f32,
+    pub games: u32,
+    pub wins: u32,
+    pub kills: u32,
+    pub best_score: i32,
+    // Rank on the all-time board; None before the first ranked game.
+    #[serde(default)]
+    pub global_rank: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProfileResponse {
+    pub profile: PlayerProfile,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardPeriod {
+    Daily,
+    Weekly,
+    #[default]
+    AllTime,
+}
+
+impl LeaderboardPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::AllTime => "all_time",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Daily => "TODAY",
+            Self::Weekly => "WEEK",
+            Self::AllTime => "ALL TIME",
         }
     }
-    vec![RoomInfo {
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Daily => Self::Weekly,
+            Self::Weekly => Self::AllTime,
+            Self::AllTime => Self::Daily,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardResponse {
+    pub entries: Vec<LeaderboardRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardRow {
+    pub rank: u32,
+    pub player_id: String,
+    pub name: String,
+    pub best_score: i32,
+    pub games: u32,
+    pub wins: u32,
+    pub kills: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinRoomRequest {
+    pub room_id: String,
+    pub player_name: String,
+    pub access_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinRoomResponse {
+    pub token: String,
+    pub server_addr: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchmakeRequest {
+    pub player_name: String,
+    pub region: String,
+    pub mode: Option<GameMode>,
+    pub rules: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchmakeResponse {
+    pub room: RoomInfo,
+    pub created: bool,
+    pub token: String,
+    pub expires_at: u64,
+}
+
+// Every master call of the menu goes through one worker thread; the UI sends
+// `MasterRequest`s and polls `MasterReply`s each frame so it never blocks.
+static MASTER_WORKER: OnceLock<MasterWorker> = OnceLock::new();
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum MasterRequest {
+    FetchRooms { filter: RoomFilter, region: String, page: usize },
+    QuickPlay(MatchmakeRequest),
+    CreateRoom(CreateRoomRequest),
+    DeleteRoom(String),
+    // Player name of the new guest profile.
+    CreateProfile(String),
+    FetchProfile(String),
+    // Trades the saved profile token for a fresh one (they expire).
+    RenewProfile { player_id: String, token: String },
+    // None = every mode / every region.
+    FetchLeaderboard { period: LeaderboardPeriod, mode: Option<GameMode>, region: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+pub enum MasterReply {
+    Rooms(Result<RoomsResponse, String>),
+    Matched(Result<MatchmakeResponse, String>),
+    Created(Result<RoomInfo, String>),
+    Deleted(Result<String, String>),
+    ProfileCreated(Result<CreateProfileResponse, String>),
+    // Ok(None): unknown profile or token no longer valid.
+    ProfileRenewed(Result<Option<CreateProfileResponse>, String>),
+    // Ok(None): the master does not know this profile (anymore).
+    Profile(Result<Option<PlayerProfile>, String>),
+    Leaderboard(Result<LeaderboardResponse, String>),
+}
+
+struct MasterWorker {
+    requests: Mutex<Sender<MasterRequest>>,
+    replies: Mutex<Receiver<MasterReply>>,
+}
+
+fn worker() -> &'static MasterWorker {
+    MASTER_WORKER.get_or_init(|| {
+        let (req_tx, req_rx) = mpsc::channel::<MasterRequest>();
+        let (reply_tx, reply_rx) = mpsc::channel::<MasterReply>();
+        std::thread::spawn(move || {
+            while let Ok(req) = req_rx.recv() {
+                let reply = match req {
+                    MasterRequest::FetchRooms { filter, region, page } => {
+                        MasterReply::Rooms(fetch_rooms(&filter, &region, page))
+                    }
+                    MasterRequest::QuickPlay(req) => MasterReply::Matched(matchmake(&req)),
+                    MasterRequest::CreateProfile(name) => MasterReply::ProfileCreated(create_profile(&name)),
+                    MasterRequest::FetchProfile(player_id) => MasterReply::Profile(fetch_profile(&player_id)),
+                    MasterRequest::RenewProfile { player_id, token } => {
+                        MasterReply::ProfileRenewed(renew_profile(&player_id, &token))
+                    }
+                    MasterRequest::FetchLeaderboard { period, mode, region } => {
+                        MasterReply::Leaderboard(fetch_leaderboard(period, mode, region.as_deref()))
+                    }
+                    MasterRequest::CreateRoom(req) => MasterReply::Created(create_room(&req)),
+                    MasterRequest::DeleteRoom(room_id) => {
+                        MasterReply::Deleted(delete_room(&room_id).map(|_| room_id))
+                    }
+                };
+                if reply_tx.send(reply).is_err() {
+                    break;
+                }
+            }
+        });
+        MasterWorker {
+            requests: Mutex::new(req_tx),
+            replies: Mutex::new(reply_rx),
+        }
+    })
+}
+
+// Queues a call for the worker; the answer comes back through `poll_reply`.
+pub fn request(req: MasterRequest) {
+    if let Ok(tx) = worker().requests.lock() {
+        let _ = tx.send(req);
+    }
+}
+
+pub fn poll_reply() -> Option<MasterReply> {
+    worker().replies.lock().ok()?.try_recv().ok()
+}
+
+fn master_url(path: &str) -> String {
+    format!("{}{}", crate::client::config().master_url.trim_end_matches('/'), path)
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
+
+// One page of the room list; `region` is used when `filter.my_region` is set.
+pub fn fetch_rooms(filter: &RoomFilter, region: &str, page: usize) -> Result<RoomsResponse, String> {
+    let mut req = agent()
+        .get(&master_url("/rooms"))
+        .query("sort", filter.sort.as_str())
+        .query("page", &page.to_string())
+        .query("per_page", &ROOM_PAGE_SIZE.to_string());
+    if filter.my_region && !region.trim().is_empty() {
+        req = req.query("region", region.trim());
+    }
+    if filter.waiting_only {
+        req = req.query("status", "waiting");
+    }
+    if filter.has_space {
+        req = req.query("has_space", "true");
+    }
+    if filter.hide_private {
+        req = req.query("hide_private", "true");
+    }
+    let resp = req.call().map_err(|e| e.to_string())?;
+    resp.into_json::<RoomsResponse>()
+        .map_err(|e| format!("bad /rooms answer: {}", e))
+}
+
+pub fn matchmake(req: &MatchmakeRequest) -> Result<MatchmakeResponse, String> {
+    let resp = agent()
+        .post(&master_url("/matchmake"))
+        .send_json(req)
+        .map_err(|e| e.to_string())?;
+    resp.into_json::<MatchmakeResponse>()
+        .map_err(|e| format!("bad /matchmake answer: {}", e))
+}
+
+// Room on the configured game server, for when the master is unreachable.
+pub fn local_room() -> RoomInfo {
+    RoomInfo {
         room_id: "DEV-ROOM".to_owned(),
         name: "Local Room".to_owned(),
-        server_addr: "ws://127.0.0.1:9001".to_owned(),
+        server_addr: crate::client::config().server_url.clone(),
         region: "LOCAL".to_owned(),
         players: 0,
+        bots: 0,
+        spectators: 0,
         max_players: 4,
         is_private: false,
         status: "waiting".to_owned(),
         ping_ms: Some(1),
-    }]
+        rules: String::new(),
+        mode: GameMode::Classic,
+    }
 }
 
-pub fn join_room(room_id: &str, _player_name: &str, _access_code: Option<&str>) -> Option<String> {
-    let rooms = fetch_rooms();
-    rooms
-        .into_iter()
-        .find(|r| r.room_id == room_id)
-        .map(|r| r.server_addr)
+// Game server address of the room; the configured server when the master is down.
+pub fn join_room(room_id: &str, player_name: &str, access_code: Option<&str>) -> Option<String> {
+    let req = JoinRoomRequest {
+        room_id: room_id.to_owned(),
+        player_name: player_name.to_owned(),
+        access_code: access_code.map(str::to_owned),
+    };
+    let body = agent()
+        .post(&master_url("/rooms/join"))
+        .send_json(&req)
+        .ok()
+        .and_then(|r| r.into_json::<JoinRoomResponse>().ok());
+    match body {
+        Some(body) => Some(body.server_addr),
+        None => Some(crate::client::config().server_url.clone()),
+    }
 }
 
-pub fn create_room(name: &str, max_players: u8) -> Option<RoomInfo> {
-    let url = "http://127.0.0.1:9100/rooms";
-    let req = CreateRoomRequest {
-        name: name.to_owned(),
-        region: "LOCAL".to_owned(),
-        max_players,
-        is_private: false,
-        access_code: None,
-    };
-    let response = ureq::post(url).send_json(req);
-    if let Ok(resp) = response {
-        if let Ok(body) = resp.into_json::<CreateRoomResponse>() {
-            return Some(RoomInfo {
-                room_id: body.room_id,
-                name: name.to_owned(),
-                server_addr: body.server_addr,
-                region: "LOCAL".to_owned(),
-                players: 0,
-                max_players,
-                is_private: false,
-                status: "waiting".to_owned(),
-                ping_ms: Some(1),
-            });
-        }
+// Rooms run the client's configured rules; a file path is sent as its stem so
+// the game server can find the same file in its rules directory.
+pub fn requested_rules() -> String {
+    let rules = crate::client::config().rules.trim();
+    std::path::Path::new(rules)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(rules)
+        .to_owned()
+}
+
+pub fn create_room(req: &CreateRoomRequest) -> Result<RoomInfo, String> {
+    let resp = agent().post(&master_url("/rooms"))
+        .send_json(req)
+        .map_err(|e| e.to_string())?;
+    let body = resp
+        .into_json::<CreateRoomResponse>()
+        .map_err(|e| format!("bad /rooms answer: {}", e))?;
+    Ok(RoomInfo {
+        room_id: body.room_id,
+        name: req.name.clone(),
+        server_addr: body.server_addr,
+        region: req.region.clone(),
+        players: 0,
+        bots: 0,
+        spectators: 0,
+        max_players: req.max_players,
+        is_private: req.is_private,
+        status: "waiting".to_owned(),
+        ping_ms: Some(1),
+        rules: req.rules.clone(),
+        mode: req.mode,
+    })
+}
+
+pub fn create_profile(name: &str) -> Result<CreateProfileResponse, String> {
+    let resp = agent()
+        .post(&master_url("/players"))
+        .send_json(serde_json::json!({ "name": name }))
+        .map_err(|e| e.to_string())?;
+    resp.into_json::<CreateProfileResponse>()
+        .map_err(|e| format!("bad /players answer: {}", e))
+}
+
+pub fn renew_profile(player_id: &str, token: &str) -> Result<Option<CreateProfileResponse>, String> {
+    match agent()
+        .post(&master_url(&format!("/players/{}/token", player_id)))
+        .send_json(serde_json::json!({ "token": token }))
+    {
+        Ok(resp) => resp
+            .into_json::<CreateProfileResponse>()
+            .map(Some)
+            .map_err(|e| format!("bad /players answer: {}", e)),
+        Err(ureq::Error::Status(401 | 404, _)) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn fetch_profile(player_id: &str) -> Result<Option<PlayerProfile>, String> {
+    match agent().get(&master_url(&format!("/players/{}", player_id))).call() {
+        Ok(resp) => resp
+            .into_json::<PlayerProfile>()
+            .map(Some)
+            .map_err(|e| format!("bad /players answer: {}", e)),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn fetch_leaderboard(
+    period: LeaderboardPeriod,
+    mode: Option<GameMode>,
+    region: Option<&str>,
+) -> Result<LeaderboardResponse, String> {
+    let mut req = agent()
+        .get(&master_url("/leaderboard"))
+        .query("period", period.as_str());
+    if let Some(mode) = mode {
+        req = req.query("mode", mode.as_str());
+    }
+    if let Some(region) = region.map(str::trim).filter(|r| !r.is_empty()) {
+        req = req.query("region", region);
     }
-    None
+    let resp = req.call().map_err(|e| e.to_string())?;
+    resp.into_json::<LeaderboardResponse>()
+        .map_err(|e| format!("bad /leaderboard answer: {}", e))
 }
 
-pub fn delete_room(room_id: &str) -> bool {
-    let url = format!("http://127.0.0.1:9100/rooms/{}", room_id);
-    ureq::delete(&url).call().is_ok()
+pub fn delete_room(room_id: &str) -> Result<(), String> {
+    agent()
+        .delete(&master_url(&format!("/rooms/{}", room_id)))
+        .call()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
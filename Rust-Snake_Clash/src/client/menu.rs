@@ -1,8 +1,9 @@
-#![allow(dead_code)]
-
+// Status of the lobby room list.
+#[derive(Debug, Clone, PartialEq)]
 pub enum MenuState {
     Loading,
-    Error,
+    // Master unreachable or bad answer.
+    Error(String),
     Empty,
     Ready,
 }
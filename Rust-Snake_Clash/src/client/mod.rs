@@ -1,5 +1,11 @@
 #![allow(dead_code)]
 
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::skin::Skin;
+
 pub mod net;
 pub mod predict;
 pub mod state;
@@ -7,16 +13,47 @@ pub mod runtime;
 pub mod menu;
 pub mod master_api;
 pub mod lobby_ui;
+pub mod prefs;
+pub mod bot_api;
+
+static CLIENT_CONFIG: OnceLock<ClientConfig> = OnceLock::new();
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ClientConfig {
+    pub master_url: String,
+    // Fallback game server when the master is unreachable.
     pub server_url: String,
+    // GameRules for solo games and rooms created from the menu; empty = build default.
+    pub rules: String,
+    // Shown to the other players (the server sanitizes it).
+    pub name: String,
+    pub skin: Skin,
+    // Menu choices saved between runs (name, skin, create-room dialog); empty = not saved.
+    pub prefs_path: String,
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
         Self {
+            master_url: "http://127.0.0.1:9100".to_owned(),
             server_url: "ws://127.0.0.1:9001".to_owned(),
+            rules: String::new(),
+            name: "PLAYER".to_owned(),
+            skin: Skin {
+                color: 6,
+                ..Skin::default()
+            },
+            prefs_path: "snake_prefs.toml".to_owned(),
         }
     }
 }
+
+// Must be called before the first frame; later calls are ignored.
+pub fn init_config(config: ClientConfig) {
+    let _ = CLIENT_CONFIG.set(config);
+}
+
+pub fn config() -> &'static ClientConfig {
+    CLIENT_CONFIG.get_or_init(ClientConfig::default)
+}
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use futures_util::{SinkExt, StreamExt};
@@ -14,6 +15,8 @@ use crate::net::messages::{ClientMessage, ServerMessage};
 pub struct ClientRuntime {
     outbound_tx: UnboundedSender<ClientMessage>,
     inbound_rx: Arc<Mutex<UnboundedReceiver<ServerMessage>>>,
+    // Set once the connection failed or was closed.
+    closed: Arc<AtomicBool>,
 }
 
 impl ClientRuntime {
@@ -22,6 +25,8 @@ impl ClientRuntime {
         let (inbound_tx, inbound_rx) = unbounded_channel::<ServerMessage>();
         let inbound_rx = Arc::new(Mutex::new(inbound_rx));
         let inbound_handle = inbound_rx.clone();
+        let closed = Arc::new(AtomicBool::new(false));
+        let closed_flag = closed.clone();
 
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
@@ -64,11 +69,13 @@ impl ClientRuntime {
                     }
                 }
             });
+            closed_flag.store(true, Ordering::Relaxed);
         });
 
         Self {
             outbound_tx,
             inbound_rx: inbound_handle,
+            closed,
         }
     }
 
@@ -79,4 +86,8 @@ impl ClientRuntime {
     pub fn try_recv(&self) -> Option<ServerMessage> {
         self.inbound_rx.lock().ok()?.try_recv().ok()
     }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
 }
@@ -0,0 +1,114 @@
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::master_api::RoomFilter;
+use crate::game::mode::GameMode;
+use crate::game::skin::Skin;
+
+// Menu choices kept between runs in `ClientConfig::prefs_path` (TOML). Without
+// the file, the name and skin come from the client config.
+
+static PREFS: OnceLock<Mutex<Prefs>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Prefs {
+    pub name: String,
+    pub skin: Skin,
+    // Last values of the create-room dialog.
+    pub room: RoomPrefs,
+    // Room browser filters and sort.
+    pub filter: RoomFilter,
+    // Guest profile on the master (`POST /players`); empty until created.
+    pub profile_id: String,
+    pub profile_token: String,
+    // Best solo score.
+    pub best_score: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomPrefs {
+    // Empty: the master picks.
+    pub region: String,
+    pub max_players: u8,
+    pub is_private: bool,
+    pub access_code: String,
+    // Preset or rule file name; empty = the client rules.
+    pub rules: String,
+    pub mode: GameMode,
+}
+
+impl Default for Prefs {
+    fn default() -> Self {
+        let config = crate::client::config();
+        Self {
+            name: config.name.clone(),
+            skin: config.skin,
+            room: RoomPrefs::default(),
+            filter: RoomFilter::default(),
+            profile_id: String::new(),
+            profile_token: String::new(),
+            best_score: 0,
+        }
+    }
+}
+
+impl Default for RoomPrefs {
+    fn default() -> Self {
+        Self {
+            region: String::new(),
+            max_players: 4,
+            is_private: false,
+            access_code: String::new(),
+            rules: String::new(),
+            mode: GameMode::Classic,
+        }
+    }
+}
+
+fn prefs() -> &'static Mutex<Prefs> {
+    PREFS.get_or_init(|| {
+        let path = &crate::client::config().prefs_path;
+        let loaded = if path.trim().is_empty() {
+            Prefs::default()
+        } else {
+            load(Path::new(path)).unwrap_or_else(|err| {
+                eprintln!("{}, using defaults", err);
+                Prefs::default()
+            })
+        };
+        Mutex::new(loaded)
+    })
+}
+
+fn load(path: &Path) -> Result<Prefs, String> {
+    if !path.exists() {
+        return Ok(Prefs::default());
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("invalid prefs {}: {}", path.display(), e))
+}
+
+pub fn get() -> Prefs {
+    prefs().lock().map(|p| p.clone()).unwrap_or_default()
+}
+
+// Stores `value` and writes the file when it changed.
+pub fn set(value: Prefs) -> Result<(), String> {
+    {
+        let mut guard = prefs().lock().map_err(|_| "prefs lock poisoned".to_owned())?;
+        if *guard == value {
+            return Ok(());
+        }
+        *guard = value.clone();
+    }
+    let path = &crate::client::config().prefs_path;
+    if path.trim().is_empty() {
+        return Ok(());
+    }
+    let text = toml::to_string_pretty(&value).map_err(|e| format!("cannot encode prefs: {}", e))?;
+    std::fs::write(path, text).map_err(|e| format!("cannot write {}: {}", path, e))
+}
@@ -2,7 +2,11 @@ use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::client::net::ClientRuntime;
 use crate::client::state::SnapshotBuffer;
-use crate::net::messages::{ClientMessage, Vec2f};
+use std::collections::HashMap;
+
+use crate::config::rules::GameRules;
+use crate::game::skin::Skin;
+use crate::net::messages::{ClientMessage, PlayerInfo, Vec2f};
 
 static CLIENT_HANDLE: OnceLock<Arc<ClientHandle>> = OnceLock::new();
 
@@ -11,6 +15,10 @@ pub struct ClientHandle {
     snapshots: Mutex<SnapshotBuffer>,
     seq: Mutex<u32>,
     player_id: Mutex<Option<u32>>,
+    // Joined as a spectator (no snake).
+    spectator: Mutex<bool>,
+    // Rules from the last JoinOk, until the game loop picks them up.
+    pending_rules: Mutex<Option<GameRules>>,
 }
 
 impl ClientHandle {
@@ -20,6 +28,8 @@ impl ClientHandle {
             snapshots: Mutex::new(SnapshotBuffer::default()),
             seq: Mutex::new(0),
             player_id: Mutex::new(None),
+            spectator: Mutex::new(false),
+            pending_rules: Mutex::new(None),
         }
     }
 }
@@ -40,9 +50,15 @@ pub fn is_ready() -> bool {
 pub fn poll() {
     if let Some(handle) = CLIENT_HANDLE.get() {
         while let Some(msg) = handle.runtime.try_recv() {
-            if let crate::net::messages::ServerMessage::JoinOk { player_id, .. } = msg {
+            if let crate::net::messages::ServerMessage::JoinOk { player_id, rules, spectator, .. } = &msg {
                 if let Ok(mut guard) = handle.player_id.lock() {
-                    *guard = Some(player_id);
+                    *guard = (!*spectator).then_some(*player_id);
+                }
+                if let Ok(mut guard) = handle.spectator.lock() {
+                    *guard = *spectator;
+                }
+                if let Ok(mut guard) = handle.pending_rules.lock() {
+                    *guard = Some((**rules).clone());
                 }
             }
             if let Ok(mut guard) = handle.snapshots.lock() {
@@ -73,85 +89,160 @@ pub fn send_input(dir: Vec2f, boost: bool) {
     }
 }
 
-pub fn send_join(room_id: String, name: String, device: String) {
+pub fn send_chat(text: String) {
+    if let Some(handle) = CLIENT_HANDLE.get() {
+        handle.runtime.send(ClientMessage::Chat { text });
+    }
+}
+
+pub fn send_emote(id: u8) {
+    if let Some(handle) = CLIENT_HANDLE.get() {
+        handle.runtime.send(ClientMessage::Emote { id });
+    }
+}
+
+pub fn send_join(
+    room_id: String,
+    name: String,
+    skin: Skin,
+    device: String,
+    ticket: Option<String>,
+    profile: Option<String>,
+    spectator: bool,
+) {
     if let Some(handle) = CLIENT_HANDLE.get() {
         handle.runtime.send(ClientMessage::JoinReq {
             room_id,
             name,
             device,
             client_time: 0.0,
+            skin,
+            ticket,
+            profile,
+            spectator,
         });
     }
 }
 
 pub fn latest_players() -> Vec<crate::net::messages::PlayerState> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.players_vec();
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.players_vec();
     }
     Vec::new()
 }
 
+// Names and skins by player id.
+pub fn player_infos() -> HashMap<u32, PlayerInfo> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.player_infos.clone();
+    }
+    HashMap::new()
+}
+
 pub fn latest_time_left() -> f32 {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.time_left;
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.time_left;
     }
     0.0
 }
 
 pub fn latest_countdown_left() -> f32 {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.countdown_left;
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.countdown_left;
     }
     0.0
 }
 
+// (current, next) safe zone radius of a battle royale room.
+pub fn latest_zone() -> (f32, f32) {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return (guard.zone_radius, guard.zone_next_radius);
+    }
+    (0.0, 0.0)
+}
+
 pub fn latest_pellets() -> Vec<crate::net::messages::Vec2f> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.pellets_vec();
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.pellets_vec();
+    }
+    Vec::new()
+}
+
+pub fn latest_pellet_teams() -> Vec<u8> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.pellet_teams_vec();
     }
     Vec::new()
 }
 
 pub fn latest_tokens() -> Vec<crate::net::messages::TokenState> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.tokens_vec();
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.tokens_vec();
     }
     Vec::new()
 }
 
 pub fn drain_events() -> Vec<crate::net::messages::Event> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(mut guard) = handle.snapshots.lock() {
-            return guard.take_events();
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(mut guard) = handle.snapshots.lock() {
+        return guard.take_events();
+    }
+    Vec::new()
+}
+
+// Last chat lines of the room, oldest first.
+pub fn chat_lines() -> Vec<(String, String)> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.chat.iter().cloned().collect();
+    }
+    Vec::new()
+}
+
+// Emotes received since the last call: (player id, emote id).
+pub fn take_emotes() -> Vec<(u32, u8)> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(mut guard) = handle.snapshots.lock() {
+        return guard.take_emotes();
     }
     Vec::new()
 }
 
 pub fn trail_for(player_id: u32) -> Vec<crate::net::messages::Vec2f> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.snapshots.lock() {
-            return guard.trail_for(player_id);
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.trail_for(player_id);
     }
     Vec::new()
 }
 
 pub fn local_player_id() -> Option<u32> {
-    if let Some(handle) = CLIENT_HANDLE.get() {
-        if let Ok(guard) = handle.player_id.lock() {
-            return *guard;
-        }
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.player_id.lock() {
+        return *guard;
     }
     None
 }
+
+pub fn is_spectator() -> bool {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.spectator.lock() {
+        return *guard;
+    }
+    false
+}
+
+pub fn take_rules() -> Option<GameRules> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(mut guard) = handle.pending_rules.lock() {
+        return guard.take();
+    }
+    None
+}
+
+pub fn game_mode() -> Option<crate::game::mode::GameMode> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return Some(guard.mode);
+    }
+    None
+}
+
+pub fn latest_leaderboard() -> Vec<crate::net::messages::LeaderboardEntry> {
+    if let Some(handle) = CLIENT_HANDLE.get() && let Ok(guard) = handle.snapshots.lock() {
+        return guard.leaderboard.clone();
+    }
+    Vec::new()
+}
@@ -2,19 +2,38 @@ use std::collections::HashMap;
 
 use std::collections::VecDeque;
 
-use crate::config::{BASE_SNAKE_LENGTH, SCORE_PER_SEGMENT, SNAKE_SPACING_MULT};
-use crate::net::messages::{Event, PlayerDelta, PlayerState, ServerMessage, TokenState, Vec2f};
+use crate::config::rules::GameRules;
+use crate::game::effects::Effects;
+use crate::game::mode::GameMode;
+use crate::net::messages::{
+    Event, LeaderboardEntry, PlayerDelta, PlayerInfo, PlayerState, ServerMessage, TokenState, Vec2f,
+};
+
+// Chat lines kept for the countdown / results screens.
+pub const CHAT_HISTORY: usize = 6;
 
 #[derive(Default)]
 pub struct SnapshotBuffer {
     pub snapshots: Vec<ServerMessage>,
     pub last_snapshot_tick: u32,
+    pub tick_rate: u16,
     pub players: HashMap<u32, PlayerState>,
     pub pellets: Vec<Vec2f>,
+    pub pellet_teams: Vec<u8>,
     pub tokens: Vec<TokenState>,
     pub events: Vec<Event>,
     pub time_left: f32,
     pub countdown_left: f32,
+    pub zone_radius: f32,
+    pub zone_next_radius: f32,
+    pub rules: GameRules,
+    pub mode: GameMode,
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub player_infos: HashMap<u32, PlayerInfo>,
+    // (sender name, text), oldest first.
+    pub chat: VecDeque<(String, String)>,
+    // (player id, emote id) not yet shown by the game loop.
+    pub emotes: Vec<(u32, u8)>,
     trails: HashMap<u32, RenderTrail>,
 }
 
@@ -38,10 +57,13 @@ impl SnapshotBuffer {
             ServerMessage::Snapshot {
                 players,
                 pellets,
+                pellet_teams,
                 tokens,
                 events,
                 time_left,
                 countdown_left,
+                zone_radius,
+                zone_next_radius,
                 ..
             } => {
                 self.players.clear();
@@ -49,31 +71,77 @@ impl SnapshotBuffer {
                     self.players.insert(p.id, p.clone());
                 }
                 self.pellets = pellets.clone();
+                self.pellet_teams = pellet_teams.clone();
                 self.tokens = tokens.clone();
                 self.events = events.clone();
                 self.time_left = *time_left;
                 self.countdown_left = *countdown_left;
+                self.zone_radius = *zone_radius;
+                self.zone_next_radius = *zone_next_radius;
                 self.update_trails();
             }
             ServerMessage::SnapshotDelta {
                 players,
                 pellets,
+                pellet_teams,
                 tokens,
                 events,
                 time_left,
                 countdown_left,
+                zone_radius,
+                zone_next_radius,
+                server_tick,
                 ..
             } => {
+                // Effects only come with a delta when they change: count them down.
+                if self.tick_rate > 0 {
+                    let ticks = server_tick.saturating_sub(self.last_snapshot_tick);
+                    let dt = ticks as f32 / self.tick_rate as f32;
+                    for p in self.players.values_mut() {
+                        let mut effects = Effects::from_active(std::mem::take(&mut p.effects));
+                        effects.tick(dt);
+                        p.effects = effects.active().to_vec();
+                    }
+                }
                 for delta in players {
                     apply_delta(&mut self.players, delta);
                 }
                 self.pellets = pellets.clone();
+                self.pellet_teams = pellet_teams.clone();
                 self.tokens = tokens.clone();
                 self.events.extend(events.iter().cloned());
                 self.time_left = *time_left;
                 self.countdown_left = *countdown_left;
+                self.zone_radius = *zone_radius;
+                self.zone_next_radius = *zone_next_radius;
                 self.update_trails();
             }
+            ServerMessage::JoinOk { rules, mode, tick_rate, players, .. } => {
+                self.tick_rate = *tick_rate;
+                self.player_infos = players.iter().map(|p| (p.id, p.clone())).collect();
+                self.rules = (**rules).clone();
+                self.mode = *mode;
+            }
+            ServerMessage::PlayerJoined { player } => {
+                self.player_infos.insert(player.id, player.clone());
+            }
+            ServerMessage::PlayerLeft { id } => {
+                self.player_infos.remove(id);
+                self.players.remove(id);
+                self.trails.remove(id);
+            }
+            ServerMessage::Leaderboard { entries } => {
+                self.leaderboard = entries.clone();
+            }
+            ServerMessage::Chat { name, text, .. } => {
+                self.chat.push_back((name.clone(), text.clone()));
+                if self.chat.len() > CHAT_HISTORY {
+                    self.chat.pop_front();
+                }
+            }
+            ServerMessage::Emote { from, id } => {
+                self.emotes.push((*from, *id));
+            }
             _ => {}
         }
     }
@@ -86,10 +154,18 @@ impl SnapshotBuffer {
         self.pellets.clone()
     }
 
+    pub fn pellet_teams_vec(&self) -> Vec<u8> {
+        self.pellet_teams.clone()
+    }
+
     pub fn tokens_vec(&self) -> Vec<TokenState> {
         self.tokens.clone()
     }
 
+    pub fn take_emotes(&mut self) -> Vec<(u32, u8)> {
+        std::mem::take(&mut self.emotes)
+    }
+
     pub fn take_events(&mut self) -> Vec<Event> {
         let mut out = Vec::new();
         std::mem::swap(&mut out, &mut self.events);
@@ -106,7 +182,7 @@ impl SnapshotBuffer {
     fn update_trails(&mut self) {
         for player in self.players.values() {
             let entry = self.trails.entry(player.id).or_default();
-            entry.push(player.head, player.radius, player.score);
+            entry.push(&self.rules, player.head, player.radius, player.score);
         }
         let live_ids: Vec<u32> = self.players.keys().copied().collect();
         self.trails.retain(|id, _| live_ids.contains(id));
@@ -122,6 +198,10 @@ fn apply_delta(players: &mut HashMap<u32, PlayerState>, delta: &PlayerDelta) {
         radius: 18.0,
         score: 0,
         boost: 100.0,
+        team: 0,
+        is_bot: false,
+        kills: 0,
+        effects: Vec::new(),
     });
 
     if let Some(v) = delta.alive {
@@ -142,6 +222,18 @@ fn apply_delta(players: &mut HashMap<u32, PlayerState>, delta: &PlayerDelta) {
     if let Some(v) = delta.boost {
         entry.boost = v;
     }
+    if let Some(v) = delta.team {
+        entry.team = v;
+    }
+    if let Some(v) = delta.is_bot {
+        entry.is_bot = v;
+    }
+    if let Some(v) = delta.kills {
+        entry.kills = v;
+    }
+    if let Some(v) = &delta.effects {
+        entry.effects = v.clone();
+    }
 }
 
 #[derive(Default)]
@@ -150,11 +242,10 @@ struct RenderTrail {
 }
 
 impl RenderTrail {
-    fn push(&mut self, head: Vec2f, radius: f32, score: i32) {
+    fn push(&mut self, rules: &GameRules, head: Vec2f, radius: f32, score: i32) {
         self.points.push_front(head);
-        let extra = (score / SCORE_PER_SEGMENT).max(0) as usize;
-        let target_length = (BASE_SNAKE_LENGTH + extra).clamp(BASE_SNAKE_LENGTH, 900);
-        let spacing = (radius * SNAKE_SPACING_MULT).max(5.2);
+        let target_length = rules.target_length(score);
+        let spacing = (radius * rules.snake_spacing_mult).max(5.2);
         let max_points = (target_length as f32 * (spacing / 10.0)).ceil() as usize;
         let max_points = max_points.clamp(12, 260);
         while self.points.len() > max_points {
@@ -1,4 +1,9 @@
 // Centralized configuration/constants for the MVP baseline.
+// Gameplay values below are the "classic" defaults; at runtime the game reads
+// them through `rules::GameRules` (presets / rule files, per room).
+
+pub mod rules;
+pub mod settings;
 
 // Legacy (ancienne version grille/pommes). Gardé pour référence.
 #[allow(dead_code)]
@@ -18,12 +23,11 @@ pub const APPLE_MIN_DIST_PX: f32 = 80.0;
 // Point 2 (snake.io-like): monde + pellets
 #[allow(dead_code)]
 pub const WORLD_HALF_SIZE: f32 = 4000.0;
-#[cfg(any(feature = "demo100", feature = "demo_play100"))]
-pub const PELLET_TARGET_COUNT: usize = 1800;
-#[cfg(not(any(feature = "demo100", feature = "demo_play100")))]
 pub const PELLET_TARGET_COUNT: usize = 4000;
 pub const PELLET_BUCKET_SIZE: f32 = 140.0;
 pub const PELLET_RADIUS: f32 = 4.0;
+// Cellule de la grille des corps (collisions tête-corps).
+pub const COLLISION_BUCKET_SIZE: f32 = 96.0;
 
 // MVP Snake Clash (solo)
 pub const MATCH_DURATION_SEC: f32 = 90.0;
@@ -60,12 +64,11 @@ pub const BOOST_ENERGY_REGEN_PER_SEC: f32 = 32.0;
 pub const SMALL_SNAKE_SPEED_MULT: f32 = 0.72;
 
 // Tokens (PDF)
-#[cfg(any(feature = "demo100", feature = "demo_play100"))]
-pub const TOKEN_TARGET_COUNT: usize = 8;
-#[cfg(not(any(feature = "demo100", feature = "demo_play100")))]
 pub const TOKEN_TARGET_COUNT: usize = 12;
 pub const TOKEN_DURATION_SEC: f32 = 10.0;
 pub const TOKEN_TIME_ADD_SEC: f32 = 10.0;
+pub const TOKEN_TTL_SEC: f32 = 30.0;
+pub const TOKEN_RESPAWN_DELAY_SEC: f32 = 3.0;
 pub const MAGNET_PICKUP_BONUS_PX: f32 = 28.0;
 pub const SPEEDUP_MULT: f32 = 1.50;
 
@@ -82,12 +85,12 @@ pub const PELLET_EAT_MAX_PER_FRAME: usize = 10;
 
 // Death / corpse drop: convert your score to pellets along the body shape
 // Keep pellet count bounded for perf while preserving total value.
-#[cfg(any(feature = "demo100", feature = "demo_play100"))]
-pub const CORPSE_DROP_MAX_PELLETS: usize = 180;
-#[cfg(not(any(feature = "demo100", feature = "demo_play100")))]
 pub const CORPSE_DROP_MAX_PELLETS: usize = 650;
 pub const CORPSE_DROP_SPREAD_PX: f32 = 10.0;
 
+// Endless mode: delay before a dead snake comes back
+pub const RESPAWN_DELAY_SEC: f32 = 3.0;
+
 // Visual tuning (2D isométrique)
 pub const ISO_SCALE: f32 = 0.90;
 
@@ -127,6 +130,3 @@ pub const UI_ZOOM_PLUS_CENTER: (f32, f32) = (640.0, 560.0);
 #[allow(dead_code)]
 pub const UI_ZOOM_MINUS_CENTER: (f32, f32) = (640.0, 660.0);
 pub const UI_ZOOM_BUTTON_RADIUS: f32 = 42.0;
-
-// UI
-pub const ENERGY_BAR_MAX: f32 = BOOST_ENERGY_MAX;
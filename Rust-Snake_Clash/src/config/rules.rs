@@ -0,0 +1,476 @@
+// Gameplay tuning loaded at runtime (per room on the server, per session on the client).
+// The `pub const`s in `config` are the "classic" defaults; presets and rule files
+// override them without recompiling.
+//
+// Rule file (TOML or JSON): any subset of the fields below, plus an optional
+// `preset = "<name>"` used as the base for the missing ones.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::*;
+use crate::game::bot_profile::{BotProfile, DIFFICULTY_NAMES, PERSONALITY_NAMES};
+use crate::game::collision::HeadToHeadRule;
+use crate::game::effects::{Stacking, TokenRule, TokenRules};
+use crate::game::food::TokenKind;
+use crate::game::mode::MAX_TEAMS;
+use crate::game::zone::ZonePhase;
+
+pub const PRESET_NAMES: &[&str] = &["classic", "demo100", "chaos", "slither"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    // Preset (or file stem) these rules were built from, for display only.
+    pub name: String,
+
+    pub arena_radius: f32,
+    pub match_duration_sec: f32,
+    pub match_start_countdown_sec: f32,
+
+    pub pellet_target_count: usize,
+    pub pellet_bucket_size: f32,
+    pub pellet_radius: f32,
+    pub pellet_eat_max_per_frame: usize,
+
+    pub base_snake_length: usize,
+    pub score_per_segment: i32,
+    pub base_snake_radius: f32,
+    pub max_snake_radius: f32,
+    pub snake_radius_score_half: f32,
+    pub snake_radius_growth_exp: f32,
+    pub base_snake_spacing: f32,
+    pub snake_spacing_mult: f32,
+    pub snake_spacing_max: f32,
+    pub trail_sample_min_dist: f32,
+
+    pub base_speed: f32,
+    pub small_snake_speed_mult: f32,
+    pub boost_speed_mult: f32,
+    pub boost_energy_max: f32,
+    pub boost_energy_drain_per_sec: f32,
+    pub boost_energy_regen_per_sec: f32,
+    // Mass cost (0 = off): boosting burns score and drops `boost_drop_frac` of it
+    // as pellets behind the tail; no boosting at or under `boost_min_score`.
+    pub boost_score_cost_per_sec: f32,
+    pub boost_drop_frac: f32,
+    pub boost_min_score: i32,
+    // Share of the score above `size_decay_min_score` lost per second when not boosting.
+    pub size_decay_per_sec: f32,
+    pub size_decay_min_score: i32,
+
+    pub token_target_count: usize,
+    // Tokens despawn after about `token_ttl_sec` (0 = never); picked up or
+    // despawned ones come back `token_respawn_delay_sec` later.
+    pub token_ttl_sec: f32,
+    pub token_respawn_delay_sec: f32,
+    // Spawn weight, duration and stacking of each token type (`[tokens.<kind>]`).
+    pub tokens: TokenRules,
+    pub token_time_add_sec: f32,
+    pub speedup_mult: f32,
+    // Confuse token: snakes this close to the picker get the debuff.
+    pub confuse_radius: f32,
+    pub magnet_pickup_bonus_px: f32,
+    pub magnet_attract_radius: f32,
+    pub magnet_attract_speed: f32,
+    pub magnet_attract_max_per_frame: usize,
+
+    pub corpse_drop_max_pellets: usize,
+    pub corpse_drop_spread_px: f32,
+
+    // Collisions (game::collision), shared by the server and the solo game.
+    // Heads die on their own body when on.
+    pub self_collision: bool,
+    pub head_to_head: HeadToHeadRule,
+    // Scales the collision radii: < 1 forgives grazes, > 1 is stricter.
+    pub collision_tolerance: f32,
+    // Cell size of the body segment grid (broadphase), ~ two max radii.
+    pub collision_bucket_size: f32,
+
+    // Endless mode
+    pub respawn_delay_sec: f32,
+    pub leaderboard_size: usize,
+
+    // Team mode
+    pub team_count: u8,
+    // false: heads pass through teammates' bodies (and teammates' heads).
+    pub team_friendly_fire: bool,
+
+    // Battle royale: shrink schedule (from match start) and damage outside the zone.
+    pub zone_phases: Vec<ZonePhase>,
+    pub zone_damage_per_sec: f32,
+    // Extra damage, as a fraction of the current score per second.
+    pub zone_damage_frac_per_sec: f32,
+
+    // Bot profiles handed out in turn to bots (solo and server-side), e.g.
+    // ["normal", "hard-hunter", "easy-farmer"]. See game::bot_profile.
+    pub bot_mix: Vec<String>,
+    // Extra named profiles usable in `bot_mix`.
+    pub bot_profiles: Vec<BotProfile>,
+
+    // Offline (solo) mode only.
+    pub bot_count: usize,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl GameRules {
+    pub fn classic() -> Self {
+        Self {
+            name: "classic".to_owned(),
+            arena_radius: ARENA_RADIUS,
+            match_duration_sec: MATCH_DURATION_SEC,
+            match_start_countdown_sec: MATCH_START_COUNTDOWN_SEC,
+            pellet_target_count: PELLET_TARGET_COUNT,
+            pellet_bucket_size: PELLET_BUCKET_SIZE,
+            pellet_radius: PELLET_RADIUS,
+            pellet_eat_max_per_frame: PELLET_EAT_MAX_PER_FRAME,
+            base_snake_length: BASE_SNAKE_LENGTH,
+            score_per_segment: SCORE_PER_SEGMENT,
+            base_snake_radius: BASE_SNAKE_RADIUS,
+            max_snake_radius: MAX_SNAKE_RADIUS,
+            snake_radius_score_half: SNAKE_RADIUS_SCORE_HALF,
+            snake_radius_growth_exp: SNAKE_RADIUS_GROWTH_EXP,
+            base_snake_spacing: BASE_SNAKE_SPACING,
+            snake_spacing_mult: SNAKE_SPACING_MULT,
+            snake_spacing_max: SNAKE_SPACING_MAX,
+            trail_sample_min_dist: TRAIL_SAMPLE_MIN_DIST,
+            base_speed: BASE_SPEED,
+            small_snake_speed_mult: SMALL_SNAKE_SPEED_MULT,
+            boost_speed_mult: BOOST_SPEED_MULT,
+            boost_energy_max: BOOST_ENERGY_MAX,
+            boost_energy_drain_per_sec: BOOST_ENERGY_DRAIN_PER_SEC,
+            boost_energy_regen_per_sec: BOOST_ENERGY_REGEN_PER_SEC,
+            boost_score_cost_per_sec: 0.0,
+            boost_drop_frac: 1.0,
+            boost_min_score: 0,
+            size_decay_per_sec: 0.0,
+            size_decay_min_score: 0,
+            token_target_count: TOKEN_TARGET_COUNT,
+            token_ttl_sec: TOKEN_TTL_SEC,
+            token_respawn_delay_sec: TOKEN_RESPAWN_DELAY_SEC,
+            tokens: TokenRules::default(),
+            token_time_add_sec: TOKEN_TIME_ADD_SEC,
+            speedup_mult: SPEEDUP_MULT,
+            confuse_radius: 420.0,
+            magnet_pickup_bonus_px: MAGNET_PICKUP_BONUS_PX,
+            magnet_attract_radius: MAGNET_ATTRACT_RADIUS,
+            magnet_attract_speed: MAGNET_ATTRACT_SPEED,
+            magnet_attract_max_per_frame: MAGNET_ATTRACT_MAX_PER_FRAME,
+            corpse_drop_max_pellets: CORPSE_DROP_MAX_PELLETS,
+            corpse_drop_spread_px: CORPSE_DROP_SPREAD_PX,
+            self_collision: false,
+            head_to_head: HeadToHeadRule::Score,
+            collision_tolerance: 1.0,
+            collision_bucket_size: COLLISION_BUCKET_SIZE,
+            respawn_delay_sec: RESPAWN_DELAY_SEC,
+            leaderboard_size: 10,
+            team_count: 2,
+            team_friendly_fire: false,
+            zone_phases: vec![
+                ZonePhase { wait_sec: 15.0, shrink_sec: 20.0, radius_frac: 0.65 },
+                ZonePhase { wait_sec: 10.0, shrink_sec: 20.0, radius_frac: 0.35 },
+                ZonePhase { wait_sec: 5.0, shrink_sec: 15.0, radius_frac: 0.12 },
+            ],
+            zone_damage_per_sec: 20.0,
+            zone_damage_frac_per_sec: 0.08,
+            bot_mix: vec!["normal".to_owned()],
+            bot_profiles: Vec::new(),
+            bot_count: 5,
+        }
+    }
+
+    // 100 bots: fewer pellets/tokens/corpse pellets to keep the frame rate up.
+    pub fn demo100() -> Self {
+        Self {
+            name: "demo100".to_owned(),
+            pellet_target_count: 1800,
+            token_target_count: 8,
+            corpse_drop_max_pellets: 180,
+            bot_mix: ["normal", "hard-hunter", "easy-farmer", "rusher", "easy"]
+                .map(str::to_owned)
+                .to_vec(),
+            bot_count: DEMO_BOT_COUNT,
+            ..Self::classic()
+        }
+    }
+
+    // Small, fast, token-heavy arena with short matches.
+    pub fn chaos() -> Self {
+        Self {
+            name: "chaos".to_owned(),
+            arena_radius: 1900.0,
+            match_duration_sec: 60.0,
+            match_start_countdown_sec: 5.0,
+            pellet_target_count: 5000,
+            score_per_segment: 8,
+            base_speed: 260.0,
+            boost_speed_mult: 1.75,
+            boost_energy_drain_per_sec: 40.0,
+            boost_energy_regen_per_sec: 48.0,
+            token_target_count: 30,
+            tokens: TokenRules {
+                magnet: TokenRule::new(32, 6.0, Stacking::Refresh),
+                speedup: TokenRule::new(32, 6.0, Stacking::Refresh),
+                ..TokenRules::default()
+            },
+            token_time_add_sec: 5.0,
+            speedup_mult: 1.8,
+            magnet_attract_radius: 360.0,
+            respawn_delay_sec: 1.5,
+            bot_mix: ["hard-hunter", "rusher", "hard"].map(str::to_owned).to_vec(),
+            zone_phases: vec![
+                ZonePhase { wait_sec: 10.0, shrink_sec: 12.0, radius_frac: 0.6 },
+                ZonePhase { wait_sec: 5.0, shrink_sec: 12.0, radius_frac: 0.3 },
+                ZonePhase { wait_sec: 3.0, shrink_sec: 10.0, radius_frac: 0.1 },
+            ],
+            bot_count: 12,
+            ..Self::classic()
+        }
+    }
+
+    // Slither-like: boosting costs mass and leaves pellets, big snakes slowly shrink.
+    pub fn slither() -> Self {
+        Self {
+            name: "slither".to_owned(),
+            boost_energy_drain_per_sec: 0.0,
+            boost_score_cost_per_sec: 8.0,
+            boost_drop_frac: 0.6,
+            boost_min_score: 24,
+            size_decay_per_sec: 0.004,
+            size_decay_min_score: 300,
+            ..Self::classic()
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "classic" => Some(Self::classic()),
+            "demo100" => Some(Self::demo100()),
+            "chaos" => Some(Self::chaos()),
+            "slither" => Some(Self::slither()),
+            _ => None,
+        }
+    }
+
+    // Build-time default: the demo features now only pick a preset.
+    pub fn build_default() -> Self {
+        if cfg!(any(feature = "demo100", feature = "demo_play100")) {
+            Self::demo100()
+        } else {
+            Self::classic()
+        }
+    }
+
+    // `spec` is a preset name, a path to a .toml/.json file, or a name looked up
+    // in `rules_dir`. Paths are only accepted from local config / CLI.
+    pub fn resolve(spec: &str, rules_dir: Option<&str>) -> Result<Self, String> {
+        let direct = Path::new(spec);
+        if Self::preset(spec).is_none() && direct.is_file() {
+            return Self::load(direct);
+        }
+        Self::resolve_named(spec, rules_dir)
+    }
+
+    // Rules requested over the network (room specs): presets, or
+    // `<rules_dir>/<name>.toml` / `.json` only.
+    pub fn resolve_named(name: &str, rules_dir: Option<&str>) -> Result<Self, String> {
+        if let Some(rules) = Self::preset(name) {
+            return Ok(rules);
+        }
+        let safe = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if let Some(dir) = rules_dir
+            && safe
+        {
+            for ext in ["toml", "json"] {
+                let path = Path::new(dir).join(format!("{}.{}", name, ext));
+                if path.is_file() {
+                    return Self::load(&path);
+                }
+            }
+        }
+        Err(format!("unknown rules '{}' (presets: {})", name, PRESET_NAMES.join(", ")))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let overrides: serde_json::Value = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?,
+            _ => {
+                let value: toml::Value =
+                    toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+                serde_json::to_value(value).map_err(|e| e.to_string())?
+            }
+        };
+        let serde_json::Value::Object(mut overrides) = overrides else {
+            return Err(format!("{}: expected a table of rules", path.display()));
+        };
+
+        let base = match overrides.remove("preset") {
+            Some(serde_json::Value::String(name)) => Self::preset(&name)
+                .ok_or_else(|| format!("{}: unknown preset '{}'", path.display(), name))?,
+            Some(_) => return Err(format!("{}: 'preset' must be a string", path.display())),
+            None => Self::classic(),
+        };
+
+        let mut merged = serde_json::to_value(base).map_err(|e| e.to_string())?;
+        if let serde_json::Value::Object(fields) = &mut merged {
+            fields.insert(
+                "name".to_owned(),
+                serde_json::Value::String(
+                    path.file_stem().and_then(|s| s.to_str()).unwrap_or("custom").to_owned(),
+                ),
+            );
+        }
+        merge_json(&mut merged, serde_json::Value::Object(overrides), "")
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let rules: Self = serde_json::from_value(merged).map_err(|e| format!("{}: {}", path.display(), e))?;
+        rules.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(rules)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for kind in TokenKind::ALL {
+            let rule = self.tokens.get(kind);
+            if rule.duration_sec < 0.0 || rule.max_sec < 0.0 {
+                return Err(format!("tokens.{}: durations must be >= 0", kind.as_str()));
+            }
+        }
+        if self.token_ttl_sec < 0.0 || self.token_respawn_delay_sec < 0.0 {
+            return Err("token_ttl_sec and token_respawn_delay_sec must be >= 0".to_owned());
+        }
+        if self.arena_radius <= 0.0 {
+            return Err("arena_radius must be > 0".to_owned());
+        }
+        if self.pellet_bucket_size <= 0.0 {
+            return Err("pellet_bucket_size must be > 0".to_owned());
+        }
+        if self.collision_bucket_size <= 0.0 {
+            return Err("collision_bucket_size must be > 0".to_owned());
+        }
+        if self.score_per_segment <= 0 {
+            return Err("score_per_segment must be > 0".to_owned());
+        }
+        if self.base_snake_length == 0 {
+            return Err("base_snake_length must be > 0".to_owned());
+        }
+        if self.max_snake_radius < self.base_snake_radius {
+            return Err("max_snake_radius must be >= base_snake_radius".to_owned());
+        }
+        if self.boost_energy_max <= 0.0 {
+            return Err("boost_energy_max must be > 0".to_owned());
+        }
+        if self.boost_score_cost_per_sec < 0.0 || self.size_decay_per_sec < 0.0 {
+            return Err("boost_score_cost_per_sec and size_decay_per_sec must be >= 0".to_owned());
+        }
+        if !(0.0..=1.0).contains(&self.boost_drop_frac) {
+            return Err("boost_drop_frac must be in 0..=1".to_owned());
+        }
+        if !(self.collision_tolerance > 0.0 && self.collision_tolerance <= 2.0) {
+            return Err("collision_tolerance must be > 0 and <= 2".to_owned());
+        }
+        if !(2..=MAX_TEAMS).contains(&self.team_count) {
+            return Err(format!("team_count must be between 2 and {}", MAX_TEAMS));
+        }
+        for name in &self.bot_mix {
+            self.find_bot_profile(name).ok_or_else(|| {
+                format!(
+                    "bot_mix: unknown profile '{}' (<difficulty>-<personality>, difficulties: {}, personalities: {})",
+                    name,
+                    DIFFICULTY_NAMES.join(", "),
+                    PERSONALITY_NAMES.join(", ")
+                )
+            })?;
+        }
+        for phase in &self.zone_phases {
+            if phase.wait_sec < 0.0 || phase.shrink_sec < 0.0 || !(0.0..=1.0).contains(&phase.radius_frac) {
+                return Err("zone_phases: wait_sec/shrink_sec must be >= 0, radius_frac in 0..1".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    // Profile of the `index`-th bot of a match.
+    pub fn bot_profile(&self, index: usize) -> BotProfile {
+        if self.bot_mix.is_empty() {
+            return BotProfile::default();
+        }
+        let name = &self.bot_mix[index % self.bot_mix.len()];
+        self.find_bot_profile(name).unwrap_or_default()
+    }
+
+    // Custom `[[bot_profiles]]` first, then built-in names.
+    pub fn find_bot_profile(&self, name: &str) -> Option<BotProfile> {
+        self.bot_profiles
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+            .cloned()
+            .or_else(|| BotProfile::named(name))
+    }
+
+    // Body length (segments) for a given score.
+    pub fn target_length(&self, score: i32) -> usize {
+        let extra = (score / self.score_per_segment).max(0) as usize;
+        (self.base_snake_length + extra).clamp(self.base_snake_length, 900)
+    }
+
+    // Smooth asymptotic growth: t = score / (score + half)
+    pub fn target_radius(&self, score: f32) -> f32 {
+        let s = score.max(0.0);
+        let t = if s <= 0.0 {
+            0.0
+        } else {
+            (s / (s + self.snake_radius_score_half)).clamp(0.0, 1.0)
+        };
+        (self.base_snake_radius
+            + (self.max_snake_radius - self.base_snake_radius) * t.powf(self.snake_radius_growth_exp))
+            .clamp(self.base_snake_radius, self.max_snake_radius)
+    }
+
+    pub fn target_spacing(&self, radius: f32) -> f32 {
+        (radius * self.snake_spacing_mult)
+            .max((radius * 0.78).max(5.2))
+            .min(self.snake_spacing_max)
+    }
+
+    // Small snakes feel slower on screen.
+    pub fn size_speed_mult(&self, radius: f32) -> f32 {
+        let span = (self.max_snake_radius - self.base_snake_radius).max(0.0001);
+        let size_t = ((radius - self.base_snake_radius) / span).clamp(0.0, 1.0);
+        self.small_snake_speed_mult + (1.0 - self.small_snake_speed_mult) * size_t
+    }
+
+    // Mass cost on: too small to boost.
+    pub fn boost_allowed(&self, score: f32) -> bool {
+        self.boost_score_cost_per_sec <= 0.0 || score > self.boost_min_score as f32
+    }
+}
+
+// Overrides replace values, except tables which are merged key by key
+// (`[tokens.shield]` only changes the fields it sets). A key the preset does
+// not have is a typo: an error naming it (`tokens.shield.duraton`), rather
+// than a setting that silently keeps the preset value.
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value, path: &str) -> Result<(), String> {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                let key_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                let slot = base
+                    .get_mut(&key)
+                    .ok_or_else(|| format!("unknown rule '{}'", key_path))?;
+                merge_json(slot, value, &key_path)?;
+            }
+            Ok(())
+        }
+        (slot, value) => {
+            *slot = value;
+            Ok(())
+        }
+    }
+}
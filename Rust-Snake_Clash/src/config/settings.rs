@@ -0,0 +1,287 @@
+// Runtime settings (addresses, ports, region, tick rate).
+// Priority: defaults < TOML file < environment (SNAKE_*) < CLI flags.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::ClientConfig;
+use crate::game::mode::{GameMode, MODE_NAMES};
+
+pub const DEFAULT_CONFIG_FILE: &str = "snake.toml";
+// Dev default shared by master and game server; set SNAKE_TOKEN_SECRET in production.
+// It is public: with it (or an empty secret) match reports are refused.
+pub const DEFAULT_TOKEN_SECRET: &str = "dev-secret";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub master: MasterSettings,
+    pub client: ClientConfig,
+    pub bot: BotSettings,
+    pub tournament: TournamentSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    // WebSocket listen address of the game server.
+    pub bind: String,
+    // Address advertised to clients through the master (what they connect to).
+    pub public_addr: String,
+    // Master API the game server registers with.
+    pub master_url: String,
+    pub region: String,
+    pub tick_rate: u16,
+    // Max rooms the master may allocate on this server.
+    pub max_rooms: u16,
+    // Dev convenience: `server` also starts a master on `master.bind`.
+    pub embed_master: bool,
+    // GameRules for rooms created without explicit rules (preset name or file).
+    pub rules: String,
+    // Where `<name>.toml` / `<name>.json` rule files requested by rooms are looked up.
+    pub rules_dir: Option<String>,
+    // Mode of rooms not created through the master (direct joins).
+    pub mode: GameMode,
+    // Server-side bots top each room up to this many snakes (0 = no bots).
+    pub bot_fill: u8,
+    // Checks master join tickets (same value as `master.token_secret`); empty = no check.
+    pub token_secret: String,
+    // File of chat words masked by the default filter (`state::chat::WordMask`),
+    // one per line; empty = no filter.
+    pub chat_blocklist_path: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:9001".to_owned(),
+            public_addr: "ws://127.0.0.1:9001".to_owned(),
+            master_url: "http://127.0.0.1:9100".to_owned(),
+            region: "LOCAL".to_owned(),
+            tick_rate: 20,
+            max_rooms: 32,
+            embed_master: true,
+            rules: "classic".to_owned(),
+            rules_dir: None,
+            mode: GameMode::Classic,
+            bot_fill: 0,
+            token_secret: DEFAULT_TOKEN_SECRET.to_owned(),
+            chat_blocklist_path: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MasterSettings {
+    pub bind: String,
+    // Used for new rooms when no game server has registered yet.
+    pub default_server_addr: String,
+    // Size of the rooms `POST /matchmake` creates when none has a free slot.
+    pub quick_play_max_players: u8,
+    // Players per match formed by the matchmaking queue.
+    pub queue_match_size: u8,
+    // Signs join tickets; game servers need the same value.
+    pub token_secret: String,
+    // Player profiles and match history (JSON); empty = kept in memory only.
+    pub data_path: String,
+}
+
+impl Default for MasterSettings {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:9100".to_owned(),
+            default_server_addr: "ws://127.0.0.1:9001".to_owned(),
+            quick_play_max_players: 4,
+            queue_match_size: 4,
+            token_secret: DEFAULT_TOKEN_SECRET.to_owned(),
+            data_path: "snake_master.json".to_owned(),
+        }
+    }
+}
+
+// `bot` command: scripted client (uses `client.master_url` / `client.server_url`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotSettings {
+    // Room to join; looked up on the master, else joined on `client.server_url`.
+    pub room: String,
+    pub name: String,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        Self {
+            room: "DEV-ROOM".to_owned(),
+            name: "BOT".to_owned(),
+        }
+    }
+}
+
+// `tournament` command: headless bot-vs-bot matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TournamentSettings {
+    pub matches: u32,
+    // Bot profile of each snake of a match; repeat a name for several snakes.
+    pub roster: Vec<String>,
+    pub rules: String,
+    pub rules_dir: Option<String>,
+    // Timed modes only (classic, teams, royale).
+    pub mode: GameMode,
+    pub tick_rate: u16,
+    pub format: ReportFormat,
+    // Report file; stdout if unset.
+    pub out: Option<String>,
+    // Fixed RNG seed for reproducible runs.
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl Default for TournamentSettings {
+    fn default() -> Self {
+        Self {
+            matches: 100,
+            roster: ["easy", "normal", "hard", "hunter", "farmer", "rusher"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            rules: "classic".to_owned(),
+            rules_dir: None,
+            mode: GameMode::Classic,
+            tick_rate: 20,
+            format: ReportFormat::Csv,
+            out: None,
+            seed: None,
+        }
+    }
+}
+
+impl Settings {
+    // `path` = explicit --config; otherwise SNAKE_CONFIG, then ./snake.toml if present.
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let env_path = std::env::var("SNAKE_CONFIG").ok();
+        let mut settings = match path.or(env_path.as_deref()) {
+            Some(p) => Self::from_file(Path::new(p))?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Self::default(),
+        };
+        settings.apply_env()?;
+        Ok(settings)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        if let Some(v) = var("SNAKE_SERVER_BIND") {
+            self.server.bind = v;
+        }
+        if let Some(v) = var("SNAKE_PUBLIC_ADDR") {
+            self.server.public_addr = v;
+        }
+        if let Some(v) = var("SNAKE_MASTER_URL") {
+            self.server.master_url = v.clone();
+            self.client.master_url = v;
+        }
+        if let Some(v) = var("SNAKE_MASTER_BIND") {
+            self.master.bind = v;
+        }
+        if let Some(v) = var("SNAKE_REGION") {
+            self.server.region = v;
+        }
+        if let Some(v) = var("SNAKE_TICK_RATE") {
+            self.server.tick_rate = parse_tick_rate(&v)?;
+        }
+        if let Some(v) = var("SNAKE_MAX_ROOMS") {
+            self.server.max_rooms = v
+                .parse()
+                .map_err(|_| format!("SNAKE_MAX_ROOMS: invalid number '{}'", v))?;
+        }
+        if let Some(v) = var("SNAKE_EMBED_MASTER") {
+            self.server.embed_master = parse_bool(&v)
+                .ok_or_else(|| format!("SNAKE_EMBED_MASTER: invalid bool '{}'", v))?;
+        }
+        if let Some(v) = var("SNAKE_SERVER_URL") {
+            self.client.server_url = v;
+        }
+        if let Some(v) = var("SNAKE_PLAYER_NAME") {
+            self.client.name = v;
+        }
+        if let Some(v) = var("SNAKE_RULES") {
+            self.server.rules = v.clone();
+            self.client.rules = v;
+        }
+        if let Some(v) = var("SNAKE_RULES_DIR") {
+            self.server.rules_dir = Some(v);
+        }
+        if let Some(v) = var("SNAKE_MODE") {
+            self.server.mode = parse_mode(&v)?;
+        }
+        if let Some(v) = var("SNAKE_BOT_ROOM") {
+            self.bot.room = v;
+        }
+        if let Some(v) = var("SNAKE_BOT_NAME") {
+            self.bot.name = v;
+        }
+        if let Some(v) = var("SNAKE_TOKEN_SECRET") {
+            self.server.token_secret = v.clone();
+            self.master.token_secret = v;
+        }
+        if let Some(v) = var("SNAKE_BOT_FILL") {
+            self.server.bot_fill = v
+                .parse()
+                .map_err(|_| format!("SNAKE_BOT_FILL: invalid number '{}'", v))?;
+        }
+        Ok(())
+    }
+}
+
+// Empty or the public `DEFAULT_TOKEN_SECRET`: anyone can sign with it.
+pub fn insecure_secret(secret: &str) -> bool {
+    secret.is_empty() || secret == DEFAULT_TOKEN_SECRET
+}
+
+pub fn parse_tick_rate(value: &str) -> Result<u16, String> {
+    match value.parse::<u16>() {
+        Ok(v) if (1..=240).contains(&v) => Ok(v),
+        _ => Err(format!("invalid tick rate '{}' (expected 1..=240)", value)),
+    }
+}
+
+pub fn parse_format(value: &str) -> Result<ReportFormat, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "csv" => Ok(ReportFormat::Csv),
+        "json" => Ok(ReportFormat::Json),
+        _ => Err(format!("invalid format '{}' (expected csv, json)", value)),
+    }
+}
+
+pub fn parse_mode(value: &str) -> Result<GameMode, String> {
+    GameMode::parse(value)
+        .ok_or_else(|| format!("invalid mode '{}' (expected {})", value, MODE_NAMES.join(", ")))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
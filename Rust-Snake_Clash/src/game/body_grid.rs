@@ -0,0 +1,117 @@
+use macroquad::prelude::*;
+
+// ---- Body grid ----
+
+// Broadphase for head-to-body collisions: snake segments bucketed by cell, rebuilt
+// every tick (same layout as the `Pellets` buckets). A head only tests the segments
+// of the few cells around it instead of every segment of every snake.
+
+// Consecutive segments `start..end` of body `body` (index in the caller's list)
+// that fall in the same cell.
+#[derive(Clone, Copy)]
+pub struct BodySpan {
+    pub body: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Default)]
+pub struct BodyGrid {
+    bucket_size: f32,
+    min_cell: i32,
+    dim: i32,
+    buckets: Vec<Vec<BodySpan>>,
+    // Non-empty buckets, so a reset only clears those.
+    used: Vec<usize>,
+}
+
+impl BodyGrid {
+    // Empties the grid; resizes it when the arena or the bucket size changed.
+    pub fn reset(&mut self, bucket_size: f32, world_half_size: f32) {
+        let bucket_size = bucket_size.max(1.0);
+        let half_cells = ((world_half_size / bucket_size).ceil() as i32) + 2;
+        let dim = half_cells * 2 + 1;
+        if bucket_size != self.bucket_size || dim != self.dim {
+            self.bucket_size = bucket_size;
+            self.min_cell = -half_cells;
+            self.dim = dim;
+            self.buckets = vec![Vec::new(); (dim as usize) * (dim as usize)];
+            self.used.clear();
+            return;
+        }
+        for idx in self.used.drain(..) {
+            self.buckets[idx].clear();
+        }
+    }
+
+    // Adds `segments[from..]` of body `body`.
+    pub fn insert_body(&mut self, body: u32, segments: &[Vec2], from: usize) {
+        if self.buckets.is_empty() {
+            return;
+        }
+        let mut run: Option<(usize, usize)> = None;
+        for (k, pos) in segments.iter().enumerate().skip(from) {
+            let (cx, cy) = self.cell_of(*pos);
+            let idx = self.bucket_index(cx, cy);
+            match run {
+                Some((run_idx, _)) if run_idx == idx => {}
+                Some((run_idx, start)) => {
+                    self.push_span(run_idx, BodySpan { body, start: start as u32, end: k as u32 });
+                    run = Some((idx, k));
+                }
+                None => run = Some((idx, k)),
+            }
+        }
+        if let Some((run_idx, start)) = run {
+            self.push_span(run_idx, BodySpan { body, start: start as u32, end: segments.len() as u32 });
+        }
+    }
+
+    // Calls `f` for the spans in the cells overlapping the square around `pos`,
+    // until it returns true. Returns whether it did.
+    pub fn any_near<F>(&self, pos: Vec2, radius: f32, mut f: F) -> bool
+    where
+        F: FnMut(&BodySpan) -> bool,
+    {
+        if self.buckets.is_empty() {
+            return false;
+        }
+        let (min_cx, min_cy) = self.cell_of(pos - vec2(radius, radius));
+        let (max_cx, max_cy) = self.cell_of(pos + vec2(radius, radius));
+        for cy in min_cy..=max_cy {
+            for cx in min_cx..=max_cx {
+                let idx = self.bucket_index(cx, cy);
+                if self.buckets[idx].iter().any(&mut f) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn push_span(&mut self, idx: usize, span: BodySpan) {
+        if self.buckets[idx].is_empty() {
+            self.used.push(idx);
+        }
+        self.buckets[idx].push(span);
+    }
+
+    fn cell_of(&self, pos: Vec2) -> (i32, i32) {
+        let cx = (pos.x / self.bucket_size).floor() as i32;
+        let cy = (pos.y / self.bucket_size).floor() as i32;
+        (
+            cx.clamp(self.min_cell, self.max_cell()),
+            cy.clamp(self.min_cell, self.max_cell()),
+        )
+    }
+
+    fn max_cell(&self) -> i32 {
+        self.min_cell + self.dim - 1
+    }
+
+    fn bucket_index(&self, cx: i32, cy: i32) -> usize {
+        let x = cx.clamp(self.min_cell, self.max_cell()) - self.min_cell;
+        let y = cy.clamp(self.min_cell, self.max_cell()) - self.min_cell;
+        ((y * self.dim + x) as usize).min(self.buckets.len().saturating_sub(1))
+    }
+}
@@ -0,0 +1,229 @@
+// Bot brain shared by the solo loop and server rooms: flee bigger snakes, hunt or
+// chase smaller ones, else go for tokens and pellets. Reads a `BotView` of the
+// world and returns the same `InputState` a human client would send.
+
+use macroquad::prelude::*;
+use macroquad::rand::gen_range;
+
+use crate::game::bot_profile::BotProfile;
+use crate::game::effects::Effects;
+use crate::game::food::{Pellets, TokenKind, Tokens};
+use crate::game::mode::is_teammate;
+use crate::game::sim::InputState;
+use crate::game::world::{random_unit_dir, AgentSnapshot};
+
+// Read-only view of the world for one bot.
+pub struct BotView<'a> {
+    // Index of the bot in `snakes`.
+    pub me: usize,
+    pub snakes: &'a [AgentSnapshot],
+    pub pellets: &'a Pellets,
+    pub tokens: &'a Tokens,
+    // Radius bots keep away from the edge of (safe zone in battle royale).
+    pub arena_radius: f32,
+    pub boost_energy: f32,
+    pub effects: &'a Effects,
+    // Crowded arenas (100 bots): wider search radii, later "late game".
+    pub heavy_mode: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct BotController {
+    profile: BotProfile,
+    dir: Vec2,
+    boost_intent: f32,
+    // Snake id being hunted, for `hunt_left` more seconds.
+    hunt_target: Option<u32>,
+    hunt_left: f32,
+}
+
+impl BotController {
+    pub fn new(dir: Vec2, profile: BotProfile) -> Self {
+        Self {
+            profile,
+            dir,
+            boost_intent: 0.0,
+            hunt_target: None,
+            hunt_left: 0.0,
+        }
+    }
+
+    pub fn think(&mut self, view: &BotView, dt: f32) -> InputState {
+        let norm = |v: Vec2| {
+            if v.length_squared() > 0.0001 {
+                v.normalize()
+            } else {
+                vec2(0.0, 0.0)
+            }
+        };
+
+        let p = &self.profile;
+        let heavy_mode = view.heavy_mode;
+        let me = view.snakes[view.me];
+        let head = me.head;
+        let my_r = me.radius;
+        let others = || {
+            view.snakes
+                .iter()
+                .enumerate()
+                .filter(move |(j, s)| s.alive && *j != view.me && !is_teammate(me.team, s.team))
+                .map(|(_, s)| s)
+        };
+
+        let alive_count = view.snakes.iter().filter(|s| s.alive).count();
+        let late_game = if heavy_mode { alive_count <= 18 } else { alive_count <= 7 };
+        let big_enough = my_r >= p.hunt_min_radius - if heavy_mode { 4.0 } else { 0.0 };
+
+        self.hunt_left = (self.hunt_left - dt).max(0.0);
+        let mut hunt: Option<(Vec2, f32)> = None;
+
+        if self.hunt_left > 0.0 {
+            if let Some(target) = self.hunt_target
+                && let Some(s) = others().find(|s| s.id == target)
+                && my_r > s.radius * p.hunt_ratio
+            {
+                hunt = Some((s.head, head.distance(s.head)));
+            }
+            if hunt.is_none() {
+                self.hunt_target = None;
+                self.hunt_left = 0.0;
+            }
+        }
+
+        if hunt.is_none() && p.hunt_radius > 0.0 && (late_game || big_enough) {
+            let chase_r = p.hunt_radius * if heavy_mode { 1.5 } else { 1.0 };
+            let mut best: Option<(u32, Vec2, f32, f32)> = None;
+            for s in others() {
+                if my_r <= s.radius * p.hunt_ratio {
+                    continue;
+                }
+                let dist = head.distance(s.head);
+                if dist > chase_r {
+                    continue;
+                }
+                let ratio = (my_r / (s.radius).max(0.01)).clamp(1.0, 3.0);
+                let score = ratio * 900.0 - dist;
+                if best.as_ref().map(|b| score > b.3).unwrap_or(true) {
+                    best = Some((s.id, s.head, dist, score));
+                }
+            }
+            if let Some((target, pos, dist, _score)) = best {
+                self.hunt_target = Some(target);
+                self.hunt_left = gen_range(1.2f32, if heavy_mode { 2.8f32 } else { 2.2f32 });
+                hunt = Some((pos, dist));
+            }
+        }
+
+        let d = head.length();
+        let inward = if d > view.arena_radius * 0.80 {
+            norm(-head)
+        } else {
+            vec2(0.0, 0.0)
+        };
+
+        let mut flee = vec2(0.0, 0.0);
+        let mut flee_w = 0.0;
+        let danger_r = p.flee_radius;
+        for s in others() {
+            if s.radius <= my_r * p.flee_ratio {
+                continue;
+            }
+            let dist = head.distance(s.head);
+            if dist < danger_r {
+                let dir = (head - s.head) / dist.max(0.001);
+                let w = (1.0 - dist / danger_r).clamp(0.0, 1.0);
+                flee += dir * w;
+                flee_w += w;
+            }
+        }
+
+        let mut chase: Option<(Vec2, f32)> = None;
+        let chase_r = p.chase_radius * if heavy_mode { 1.3 } else { 1.0 };
+        if flee_w <= 0.01 && hunt.is_none() {
+            let mut best_d = f32::INFINITY;
+            for s in others() {
+                if my_r <= s.radius * p.chase_ratio {
+                    continue;
+                }
+                let dist = head.distance(s.head);
+                if dist < chase_r && dist < best_d {
+                    best_d = dist;
+                    chase = Some((s.head, dist));
+                }
+            }
+        }
+
+        let token_target = view
+            .tokens
+            .best_target(head, |k| {
+                // Running effects are not worth a detour.
+                if k.effect().is_some_and(|e| view.effects.has(e)) {
+                    return None;
+                }
+                Some(match k {
+                    TokenKind::TimeAdd => 1.7,
+                    TokenKind::Magnet => 2.2,
+                    TokenKind::SpeedUp => 2.0,
+                    TokenKind::Shield => 2.4,
+                    TokenKind::Ghost => 1.6,
+                    TokenKind::DoublePoints => 1.9,
+                    TokenKind::Confuse => 1.2,
+                })
+            })
+            .filter(|(tpos, _)| head.distance(*tpos) <= p.token_search_radius);
+
+        let pellet_target = view.pellets.best_pellet_target(head, p.pellet_search_radius);
+
+        let energy = view.boost_energy;
+        let flee_move = (flee_w > 0.01).then(|| (norm(flee), energy > p.flee_boost_min));
+        let hunt_move = hunt.map(|(tpos, dist)| {
+            let wants = energy > p.hunt_boost_min && dist < (if heavy_mode { 980.0 } else { 760.0 });
+            (norm(tpos - head), wants)
+        });
+        let chase_move =
+            chase.map(|(tpos, dist)| (norm(tpos - head), energy > p.chase_boost_min && dist < 360.0));
+        let token_move = token_target.map(|(tpos, tkind)| {
+            let min_energy = match tkind {
+                TokenKind::TimeAdd => p.token_boost_min + 10.0,
+                _ => p.token_boost_min,
+            };
+            (norm(tpos - head), energy > min_energy && head.distance(tpos) < 520.0)
+        });
+        let pellet_move = pellet_target.map(|ppos| (norm(ppos - head), false));
+
+        let picked = if p.tokens_first {
+            flee_move.or(token_move).or(hunt_move).or(chase_move)
+        } else {
+            flee_move.or(hunt_move).or(chase_move).or(token_move)
+        };
+        let (mut desired, wants_boost) = picked.or(pellet_move).unwrap_or_else(|| {
+            let jitter = random_unit_dir();
+            (norm(self.dir.lerp(jitter, 0.08)), false)
+        });
+
+        if p.aim_jitter > 0.0 {
+            desired = Vec2::from_angle(gen_range(-p.aim_jitter, p.aim_jitter)).rotate(desired);
+        }
+
+        if desired.length_squared() <= 0.0001 {
+            desired = self.dir;
+        }
+
+        if inward.length_squared() > 0.0001 {
+            desired = norm(desired * 0.62 + inward * 1.10);
+        }
+
+        let t = (3.4 * dt).clamp(0.0, 1.0);
+        self.dir = norm(self.dir.lerp(desired, p.turn_rate * t));
+
+        self.boost_intent = (self.boost_intent - dt).max(0.0);
+        if wants_boost && self.boost_intent <= 0.0 {
+            self.boost_intent = gen_range(0.25f32, 0.55f32);
+        }
+
+        InputState {
+            dir: self.dir,
+            boost: self.boost_intent > 0.0,
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Bot tuning: a difficulty tier (easy / normal / hard) applied over a personality
+// (balanced / hunter / farmer / rusher). Named "<difficulty>-<personality>",
+// "<difficulty>" or "<personality>"; rule files can also define their own
+// (`[[bot_profiles]]`) and pick the match mix with `bot_mix`.
+
+use serde::{Deserialize, Serialize};
+
+pub const DIFFICULTY_NAMES: &[&str] = &["easy", "normal", "hard"];
+pub const PERSONALITY_NAMES: &[&str] = &["balanced", "hunter", "farmer", "rusher"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotProfile {
+    pub name: String,
+
+    // Flee snakes bigger than `flee_ratio` x me within `flee_radius`.
+    pub flee_radius: f32,
+    pub flee_ratio: f32,
+    // Hunt snakes smaller than me / `hunt_ratio` within `hunt_radius`, once my
+    // radius reaches `hunt_min_radius` (or in the late game). 0 radius = never.
+    pub hunt_ratio: f32,
+    pub hunt_radius: f32,
+    pub hunt_min_radius: f32,
+    // Short-range opportunistic chase.
+    pub chase_ratio: f32,
+    pub chase_radius: f32,
+
+    // Min boost energy before boosting in each situation.
+    pub flee_boost_min: f32,
+    pub hunt_boost_min: f32,
+    pub chase_boost_min: f32,
+    pub token_boost_min: f32,
+
+    // Tokens further than this are ignored (0 = never go for tokens).
+    pub token_search_radius: f32,
+    // Go for tokens before hunting / chasing (still flees first).
+    pub tokens_first: bool,
+    pub pellet_search_radius: f32,
+
+    // Steering responsiveness (lerp weight towards the wanted direction).
+    pub turn_rate: f32,
+    // Random aim error, radians.
+    pub aim_jitter: f32,
+}
+
+impl Default for BotProfile {
+    fn default() -> Self {
+        Self::balanced()
+    }
+}
+
+impl BotProfile {
+    // The original solo bot.
+    pub fn balanced() -> Self {
+        Self {
+            name: "normal-balanced".to_owned(),
+            flee_radius: 620.0,
+            flee_ratio: 1.08,
+            hunt_ratio: 1.07,
+            hunt_radius: 1200.0,
+            hunt_min_radius: 22.0,
+            chase_ratio: 1.12,
+            chase_radius: 560.0,
+            flee_boost_min: 30.0,
+            hunt_boost_min: 35.0,
+            chase_boost_min: 45.0,
+            token_boost_min: 55.0,
+            token_search_radius: 100_000.0,
+            tokens_first: false,
+            pellet_search_radius: 820.0,
+            turn_rate: 0.55,
+            aim_jitter: 0.0,
+        }
+    }
+
+    // Goes after anything smaller, early, and boosts to catch it.
+    pub fn hunter() -> Self {
+        Self {
+            flee_radius: 480.0,
+            hunt_ratio: 1.03,
+            hunt_radius: 1600.0,
+            hunt_min_radius: 0.0,
+            chase_ratio: 1.05,
+            chase_radius: 760.0,
+            hunt_boost_min: 20.0,
+            chase_boost_min: 25.0,
+            token_search_radius: 500.0,
+            ..Self::balanced()
+        }
+    }
+
+    // Avoids fights, eats pellets and nearby tokens.
+    pub fn farmer() -> Self {
+        Self {
+            flee_radius: 820.0,
+            flee_ratio: 1.0,
+            flee_boost_min: 20.0,
+            hunt_radius: 0.0,
+            chase_radius: 0.0,
+            token_search_radius: 700.0,
+            pellet_search_radius: 1100.0,
+            ..Self::balanced()
+        }
+    }
+
+    // Beelines for tokens, boosting when it can.
+    pub fn rusher() -> Self {
+        Self {
+            tokens_first: true,
+            token_boost_min: 25.0,
+            hunt_radius: 700.0,
+            ..Self::balanced()
+        }
+    }
+
+    fn personality(name: &str) -> Option<Self> {
+        match name {
+            "balanced" => Some(Self::balanced()),
+            "hunter" => Some(Self::hunter()),
+            "farmer" => Some(Self::farmer()),
+            "rusher" => Some(Self::rusher()),
+            _ => None,
+        }
+    }
+
+    // Scales reaction, awareness and boost use; "normal" keeps the personality as is.
+    fn with_difficulty(mut self, difficulty: &str) -> Option<Self> {
+        match difficulty {
+            "easy" => {
+                self.turn_rate *= 0.55;
+                self.aim_jitter += 0.35;
+                self.flee_radius *= 0.6;
+                self.hunt_ratio += 0.15;
+                self.pellet_search_radius *= 0.6;
+                self.flee_boost_min += 25.0;
+                self.hunt_boost_min += 25.0;
+                self.chase_boost_min += 25.0;
+                self.token_boost_min += 25.0;
+            }
+            "normal" => {}
+            "hard" => {
+                self.turn_rate = (self.turn_rate * 1.5).min(1.0);
+                self.aim_jitter = 0.0;
+                self.flee_radius *= 1.25;
+                self.hunt_ratio = (self.hunt_ratio - 0.03).max(1.01);
+                self.pellet_search_radius *= 1.2;
+                self.flee_boost_min = (self.flee_boost_min - 10.0).max(5.0);
+                self.hunt_boost_min = (self.hunt_boost_min - 10.0).max(5.0);
+                self.chase_boost_min = (self.chase_boost_min - 10.0).max(5.0);
+            }
+            _ => return None,
+        }
+        Some(self)
+    }
+
+    // Built-in profile by name.
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let (difficulty, personality) = match name.split_once('-') {
+            Some((d, p)) => (d, p),
+            None if DIFFICULTY_NAMES.contains(&name.as_str()) => (name.as_str(), "balanced"),
+            None => ("normal", name.as_str()),
+        };
+        let mut profile = Self::personality(personality)?.with_difficulty(difficulty)?;
+        profile.name = format!("{}-{}", difficulty, personality);
+        Some(profile)
+    }
+}
@@ -1,112 +1,337 @@
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::config::{ARENA_RADIUS, CORPSE_DROP_MAX_PELLETS, CORPSE_DROP_SPREAD_PX};
+use crate::config::rules::GameRules;
+use crate::game::body_grid::BodyGrid;
+use crate::game::math::dist_sq_to_segment;
 use crate::game::food::Pellets;
-use crate::game::world::{Agent, AgentKind, FrameScratch};
+use crate::game::mode::is_teammate;
+use crate::game::snake_sim::SnakeSim;
+use crate::game::world::{Agent, FrameScratch};
 
-pub fn check_arena_bounds(agents: &[Agent], scratch: &mut FrameScratch) {
-    for i in 0..agents.len() {
-        if !agents[i].alive {
+// Why a snake died; sent with `death` / `kill` events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    ArenaEdge,
+    // Lost a head-on collision (both die on a tie, with no killer).
+    HeadToHead,
+    // Ran into another snake's body; the body's owner gets the kill.
+    Body,
+    #[serde(rename = "self")]
+    SelfHit,
+    // Battle royale: ran out of score outside the safe zone.
+    Zone,
+}
+
+// `killer`: agent index (solo) or player id (server).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Death {
+    pub cause: DeathCause,
+    pub killer: Option<u32>,
+}
+
+impl Death {
+    pub fn new(cause: DeathCause, killer: Option<u32>) -> Self {
+        Self { cause, killer }
+    }
+}
+
+pub fn check_arena_bounds(agents: &[Agent], scratch: &mut FrameScratch, arena_radius: f32) {
+    for (i, a) in agents.iter().enumerate() {
+        if !a.alive {
             continue;
         }
-        if agents[i].snake.head_pos().distance_squared(vec2(0.0, 0.0)) > ARENA_RADIUS * ARENA_RADIUS {
-            scratch.to_die[i] = true;
+        if a.snake.head_pos().distance_squared(vec2(0.0, 0.0)) > arena_radius * arena_radius {
+            scratch.to_die[i] = Some(Death::new(DeathCause::ArenaEdge, None));
         }
     }
 }
 
-pub fn check_head_to_head(agents: &[Agent], scratch: &mut FrameScratch) {
-    for i in 0..agents.len() {
-        if !agents[i].alive || scratch.to_die[i] {
-            continue;
-        }
-        for j in (i + 1)..agents.len() {
-            if !agents[j].alive || scratch.to_die[j] {
+// Head-to-head resolution (`GameRules::head_to_head`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadToHeadRule {
+    // Higher score survives; equal scores both die.
+    #[default]
+    Score,
+    // Bigger radius survives unless within `HEAD_TO_HEAD_RADIUS_TIE` of each other.
+    Radius,
+    // Both always die.
+    Mutual,
+}
+
+// Radius ratio under which `HeadToHeadRule::Radius` is a tie.
+const HEAD_TO_HEAD_RADIUS_TIE: f32 = 1.10;
+// Self-collision ignores the body closer to the head than this many radii
+// (the neck, which always touches the head).
+const SELF_NECK_RADII: f32 = 6.0;
+
+// What collision checks need from a snake; built by the room (server) and the
+// solo loop so both resolve collisions with the same code.
+pub struct CollisionBody<'a> {
+    pub snake: &'a SnakeSim,
+    pub team: u8,
+    pub alive: bool,
+    pub score: f32,
+    // Ghost effect: the head passes through the other snakes.
+    pub ghost: bool,
+}
+
+// Head-to-head, head-to-body and self collisions between live `bodies`, with
+// heads swept from `prev_head` so fast snakes cannot tunnel. Sets `deaths[i]`
+// (kept if already set) with killers as indices into `bodies`; rebuilds `grid`.
+pub fn find_collisions(
+    bodies: &[CollisionBody],
+    rules: &GameRules,
+    grid: &mut BodyGrid,
+    deaths: &mut [Option<Death>],
+) {
+    let tolerance = rules.collision_tolerance;
+    let blocks = |a: &CollisionBody, b: &CollisionBody| {
+        rules.team_friendly_fire || !is_teammate(a.team, b.team)
+    };
+
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let (a, b) = (&bodies[i], &bodies[j]);
+            if !a.alive || !b.alive || a.ghost || b.ghost || !blocks(a, b) {
+                continue;
+            }
+            // Closest approach of the two moving heads during the step.
+            let r = (a.snake.radius + b.snake.radius) * tolerance;
+            let start = a.snake.prev_head() - b.snake.prev_head();
+            let end = a.snake.head_pos() - b.snake.head_pos();
+            if dist_sq_to_segment(Vec2::ZERO, start, end) > r * r {
                 continue;
             }
-            let pi = agents[i].snake.head_pos();
-            let pj = agents[j].snake.head_pos();
-            let r = agents[i].snake.radius + agents[j].snake.radius;
-            if pi.distance_squared(pj) <= (r * 0.95) * (r * 0.95) {
-                let ai = agents[i].snake.radius.max(0.01);
-                let aj = agents[j].snake.radius.max(0.01);
-                let ratio = if ai > aj { ai / aj } else { aj / ai };
-                if ratio < 1.10 {
-                    scratch.to_die[i] = true;
-                    scratch.to_die[j] = true;
-                } else if ai > aj {
-                    scratch.to_die[j] = true;
-                } else {
-                    scratch.to_die[i] = true;
+            let a_wins = match rules.head_to_head {
+                HeadToHeadRule::Score if a.score != b.score => Some(a.score > b.score),
+                HeadToHeadRule::Radius => {
+                    let (ra, rb) = (a.snake.radius.max(0.01), b.snake.radius.max(0.01));
+                    (ra.max(rb) / ra.min(rb) >= HEAD_TO_HEAD_RADIUS_TIE).then_some(ra > rb)
                 }
+                _ => None,
+            };
+            let (ki, kj) = match a_wins {
+                Some(true) => (None, Some(Death::new(DeathCause::HeadToHead, Some(i as u32)))),
+                Some(false) => (Some(Death::new(DeathCause::HeadToHead, Some(j as u32))), None),
+                None => (
+                    Some(Death::new(DeathCause::HeadToHead, None)),
+                    Some(Death::new(DeathCause::HeadToHead, None)),
+                ),
+            };
+            if deaths[i].is_none() {
+                deaths[i] = ki;
+            }
+            if deaths[j].is_none() {
+                deaths[j] = kj;
             }
         }
     }
-}
 
-pub fn check_head_to_body(agents: &[Agent], scratch: &mut FrameScratch, heavy_mode: bool) {
-    for i in 0..agents.len() {
-        if !agents[i].alive || scratch.to_die[i] {
+    grid.reset(rules.collision_bucket_size, rules.arena_radius);
+    let mut max_radius = 0.0f32;
+    for (j, body) in bodies.iter().enumerate().filter(|(_, b)| b.alive) {
+        max_radius = max_radius.max(body.snake.radius);
+        grid.insert_body(j as u32, body.snake.segments(), 1);
+    }
+
+    for (i, attacker) in bodies.iter().enumerate() {
+        if !attacker.alive || attacker.ghost || deaths[i].is_some() {
             continue;
         }
-        let head = agents[i].snake.head_pos();
-        let hr = agents[i].snake.radius;
-        'outer: for j in 0..agents.len() {
-            if i == j || !agents[j].alive {
-                continue;
+        let (from, head) = (attacker.snake.prev_head(), attacker.snake.head_pos());
+        let sweep = from.distance(head);
+        // The neck grows with the sweep: the path starts behind the head.
+        let neck = SELF_NECK_RADII * attacker.snake.radius + sweep;
+        let first_own = if rules.self_collision && attacker.snake.trail_reaches(neck) {
+            (neck / attacker.snake.segment_spacing.max(0.01)).ceil() as u32
+        } else {
+            u32::MAX
+        };
+        let mut hit = None;
+        let reach = sweep * 0.5 + attacker.snake.radius + max_radius * tolerance;
+        grid.any_near((from + head) * 0.5, reach, |span| {
+            let j = span.body as usize;
+            let victim = &bodies[j];
+            let own = i == j;
+            let start = if own { span.start.max(first_own) } else { span.start };
+            if start >= span.end || (!own && !blocks(attacker, victim)) {
+                return false;
             }
-            let br = agents[j].snake.radius;
-            let segs = agents[j].snake.segments();
-            let step = if heavy_mode {
-                (segs.len() / 110).max(1).min(6)
+            let r = attacker.snake.radius + victim.snake.radius * tolerance;
+            let segs = &victim.snake.segments()[start as usize..span.end as usize];
+            if !segs.iter().any(|p| dist_sq_to_segment(*p, from, head) <= r * r) {
+                return false;
+            }
+            hit = Some(if own {
+                Death::new(DeathCause::SelfHit, None)
             } else {
-                1
-            };
+                Death::new(DeathCause::Body, Some(j as u32))
+            });
+            true
+        });
+        if hit.is_some() {
+            deaths[i] = hit;
+        }
+    }
+}
 
-            let sample_padding = if heavy_mode {
-                ((step as f32) - 1.0).max(0.0) * agents[j].snake.segment_spacing * 0.55
-            } else {
-                0.0
-            };
+// Solo loop: collisions between agents (killer = agent index).
+pub fn check_collisions(agents: &[Agent], scratch: &mut FrameScratch, rules: &GameRules) {
+    let bodies: Vec<CollisionBody> = agents
+        .iter()
+        .map(|a| CollisionBody {
+            snake: &a.snake,
+            team: a.team,
+            alive: a.alive,
+            score: a.score,
+            ghost: a.effects.ghost(),
+        })
+        .collect();
+    find_collisions(&bodies, rules, &mut scratch.body_grid, &mut scratch.to_die);
+}
 
-            let r = (hr + br * 0.92 + sample_padding).max(0.0);
-            let r2 = r * r;
-            let mut k: usize = 3;
-            while k < segs.len() {
-                let p = segs[k];
-                if head.distance_squared(p) <= r2 {
-                    scratch.to_die[i] = true;
-                    break 'outer;
-                }
-                k += step;
-            }
+// Shield effect: cancels the collision deaths of shielded agents. Returns the saved indices.
+pub fn absorb_shields(agents: &mut [Agent], scratch: &mut FrameScratch) -> Vec<usize> {
+    let mut saved = Vec::new();
+    for (i, (a, mark)) in agents.iter_mut().zip(scratch.to_die.iter_mut()).enumerate() {
+        if let Some(death) = mark
+            && a.alive
+            && a.effects.absorbs(death)
+        {
+            *mark = None;
+            saved.push(i);
         }
     }
+    saved
 }
 
-pub fn apply_deaths(agents: &mut [Agent], scratch: &mut FrameScratch, pellets: &mut Pellets) -> bool {
-    let mut player_died = false;
+// Kills the marked agents and credits their killers. Returns (victim index, death).
+pub fn apply_deaths(
+    agents: &mut [Agent],
+    scratch: &mut FrameScratch,
+    pellets: &mut Pellets,
+    rules: &GameRules,
+) -> Vec<(usize, Death)> {
+    let mut deaths = Vec::new();
 
-    for i in 0..agents.len() {
-        if !agents[i].alive || !scratch.to_die[i] {
+    for (i, a) in agents.iter_mut().enumerate() {
+        let Some(death) = scratch.to_die[i] else {
+            continue;
+        };
+        if !a.alive {
             continue;
         }
 
         pellets.spawn_corpse_score(
-            agents[i].snake.segments(),
-            agents[i].score as i32,
-            CORPSE_DROP_MAX_PELLETS,
-            CORPSE_DROP_SPREAD_PX.max(2.0),
+            a.snake.segments(),
+            a.score as i32,
+            rules.corpse_drop_max_pellets,
+            rules.corpse_drop_spread_px.max(2.0),
+            0,
         );
 
-        agents[i].alive = false;
-        agents[i].magnet_left = 0.0;
-        agents[i].speedup_left = 0.0;
+        a.alive = false;
+        a.effects.clear();
+        deaths.push((i, death));
+    }
 
-        if agents[i].kind == AgentKind::Player {
-            player_died = true;
+    for (_, death) in &deaths {
+        if let Some(killer) = death.killer.and_then(|k| agents.get_mut(k as usize)) {
+            killer.kills += 1;
         }
     }
 
-    player_died
+    deaths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.05;
+
+    // Straight vertical body along x = 0, head at the top (y ~ 200).
+    fn vertical_body(rules: &GameRules) -> SnakeSim {
+        let mut snake = SnakeSim::new_at(vec2(0.0, -200.0), vec2(0.0, 1.0), rules);
+        snake.speed = 200.0;
+        for _ in 0..40 {
+            snake.update_dir(DT, vec2(0.0, 1.0));
+        }
+        snake
+    }
+
+    // Snake that moves `step` px to the right in one tick, starting at (x, y).
+    fn moved_right(rules: &GameRules, x: f32, y: f32, step: f32) -> SnakeSim {
+        let mut snake = SnakeSim::new_at(vec2(x, y), vec2(1.0, 0.0), rules);
+        snake.speed = step / DT;
+        snake.update_dir(DT, vec2(1.0, 0.0));
+        snake
+    }
+
+    fn collide(snakes: &[&SnakeSim], rules: &GameRules) -> Vec<Option<Death>> {
+        let bodies: Vec<CollisionBody> = snakes
+            .iter()
+            .map(|s| CollisionBody {
+                snake: s,
+                team: 0,
+                alive: true,
+                score: 10.0,
+                ghost: false,
+            })
+            .collect();
+        let mut deaths = vec![None; bodies.len()];
+        find_collisions(&bodies, rules, &mut BodyGrid::default(), &mut deaths);
+        deaths
+    }
+
+    #[test]
+    fn fast_head_crossing_a_body_always_collides() {
+        let rules = GameRules::default();
+        let body = vertical_body(&rules);
+        let reach = rules.base_snake_radius * 2.0;
+        for step in [28.0, 40.0, 60.0, 90.0] {
+            let mut y = 90.0;
+            while y <= 160.0 {
+                // Both ends of the step are out of reach: only the sweep can see the hit.
+                let x = -(reach + 2.0);
+                let attacker = moved_right(&rules, x, y, step);
+                assert!(attacker.head_pos().x > reach);
+                let deaths = collide(&[&attacker, &body], &rules);
+                assert_eq!(
+                    deaths[0],
+                    Some(Death::new(DeathCause::Body, Some(1))),
+                    "step {} at y {} tunneled through the body",
+                    step,
+                    y
+                );
+                assert_eq!(deaths[1], None);
+                y += 5.0;
+            }
+        }
+    }
+
+    #[test]
+    fn fast_head_beside_a_body_survives() {
+        let rules = GameRules::default();
+        let body = vertical_body(&rules);
+        let mut attacker = SnakeSim::new_at(vec2(-20.0, 90.0), vec2(0.0, 1.0), &rules);
+        attacker.speed = 60.0 / DT;
+        attacker.update_dir(DT, vec2(0.0, 1.0));
+        assert_eq!(collide(&[&attacker, &body], &rules), vec![None, None]);
+    }
+
+    #[test]
+    fn fast_heads_crossing_each_other_collide() {
+        let rules = GameRules::default();
+        let a = moved_right(&rules, -20.0, 0.0, 40.0);
+        let mut b = SnakeSim::new_at(vec2(20.0, 0.0), vec2(-1.0, 0.0), &rules);
+        b.speed = 40.0 / DT;
+        b.update_dir(DT, vec2(-1.0, 0.0));
+        let deaths = collide(&[&a, &b], &rules);
+        let tie = Some(Death::new(DeathCause::HeadToHead, None));
+        assert_eq!(deaths, vec![tie, tie]);
+    }
 }
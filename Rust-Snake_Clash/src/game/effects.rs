@@ -0,0 +1,235 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::rules::GameRules;
+use crate::config::TOKEN_DURATION_SEC;
+use crate::game::collision::{Death, DeathCause};
+use crate::game::food::TokenKind;
+
+// Timed power-ups on a snake, granted by tokens. Their tuning is data
+// (`GameRules::tokens`); the server and the solo game apply them through the
+// hooks of `Effects` (movement, scoring, collisions).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    Magnet,
+    SpeedUp,
+    // Survives the next fatal collision (then `SHIELD_GRACE_SEC` of ghost to get clear).
+    Shield,
+    // Head passes through the other snakes.
+    Ghost,
+    DoublePoints,
+    // Debuff from a confuse token picked up nearby: steering is reversed.
+    Confused,
+}
+
+impl EffectKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EffectKind::Magnet => "magnet",
+            EffectKind::SpeedUp => "speedup",
+            EffectKind::Shield => "shield",
+            EffectKind::Ghost => "ghost",
+            EffectKind::DoublePoints => "double_points",
+            EffectKind::Confused => "confused",
+        }
+    }
+
+    // Token granting the effect (its HUD icon).
+    pub fn token(self) -> TokenKind {
+        match self {
+            EffectKind::Magnet => TokenKind::Magnet,
+            EffectKind::SpeedUp => TokenKind::SpeedUp,
+            EffectKind::Shield => TokenKind::Shield,
+            EffectKind::Ghost => TokenKind::Ghost,
+            EffectKind::DoublePoints => TokenKind::DoublePoints,
+            EffectKind::Confused => TokenKind::Confuse,
+        }
+    }
+}
+
+// Picking up an effect that is already running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stacking {
+    // Restart at the full duration.
+    #[default]
+    Refresh,
+    // Add the duration, up to `TokenRule::max_sec`.
+    Extend,
+    // The token stays on the ground while the effect runs.
+    Ignore,
+}
+
+// One token type: how often it spawns and the effect it grants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenRule {
+    // Relative spawn chance; 0 never spawns.
+    pub weight: u32,
+    pub duration_sec: f32,
+    pub stacking: Stacking,
+    pub max_sec: f32,
+}
+
+impl Default for TokenRule {
+    fn default() -> Self {
+        Self {
+            weight: 10,
+            duration_sec: TOKEN_DURATION_SEC,
+            stacking: Stacking::Refresh,
+            max_sec: TOKEN_DURATION_SEC * 2.0,
+        }
+    }
+}
+
+impl TokenRule {
+    pub fn new(weight: u32, duration_sec: f32, stacking: Stacking) -> Self {
+        Self {
+            weight,
+            duration_sec,
+            stacking,
+            max_sec: duration_sec * 2.0,
+        }
+    }
+}
+
+// `time_add` only has a weight (it adds `token_time_add_sec` to the clock);
+// `confuse` lasts `duration_sec` on the snakes within `confuse_radius` of the picker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenRules {
+    pub magnet: TokenRule,
+    pub speedup: TokenRule,
+    pub time_add: TokenRule,
+    pub shield: TokenRule,
+    pub ghost: TokenRule,
+    pub double_points: TokenRule,
+    pub confuse: TokenRule,
+}
+
+impl Default for TokenRules {
+    fn default() -> Self {
+        Self {
+            magnet: TokenRule::new(32, TOKEN_DURATION_SEC, Stacking::Refresh),
+            speedup: TokenRule::new(32, TOKEN_DURATION_SEC, Stacking::Refresh),
+            time_add: TokenRule::new(16, 0.0, Stacking::Refresh),
+            shield: TokenRule::new(6, 20.0, Stacking::Ignore),
+            ghost: TokenRule::new(6, 5.0, Stacking::Refresh),
+            double_points: TokenRule::new(8, 10.0, Stacking::Extend),
+            confuse: TokenRule::new(6, 3.0, Stacking::Refresh),
+        }
+    }
+}
+
+impl TokenRules {
+    pub fn get(&self, kind: TokenKind) -> &TokenRule {
+        match kind {
+            TokenKind::Magnet => &self.magnet,
+            TokenKind::SpeedUp => &self.speedup,
+            TokenKind::TimeAdd => &self.time_add,
+            TokenKind::Shield => &self.shield,
+            TokenKind::Ghost => &self.ghost,
+            TokenKind::DoublePoints => &self.double_points,
+            TokenKind::Confuse => &self.confuse,
+        }
+    }
+}
+
+// Ghost time left after a shield saved its snake, to get out of the body it hit.
+const SHIELD_GRACE_SEC: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    // Seconds left.
+    pub left: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Effects {
+    active: Vec<ActiveEffect>,
+}
+
+impl Effects {
+    // Effects replicated from the server.
+    pub fn from_active(active: Vec<ActiveEffect>) -> Self {
+        Self { active }
+    }
+
+    pub fn active(&self) -> &[ActiveEffect] {
+        &self.active
+    }
+
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.active.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn left(&self, kind: EffectKind) -> f32 {
+        self.active.iter().find(|e| e.kind == kind).map(|e| e.left).unwrap_or(0.0)
+    }
+
+    // Whether a token granting `kind` is picked up now.
+    pub fn accepts(&self, kind: EffectKind, rule: &TokenRule) -> bool {
+        !(rule.stacking == Stacking::Ignore && self.has(kind))
+    }
+
+    pub fn grant(&mut self, kind: EffectKind, rule: &TokenRule) {
+        let left = match rule.stacking {
+            Stacking::Extend => (self.left(kind) + rule.duration_sec).min(rule.max_sec.max(rule.duration_sec)),
+            Stacking::Refresh | Stacking::Ignore => rule.duration_sec,
+        };
+        self.set(kind, left);
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        for e in &mut self.active {
+            e.left -= dt;
+        }
+        self.active.retain(|e| e.left > 0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    // ---- Hooks ----
+
+    pub fn speed_mult(&self, rules: &GameRules) -> f32 {
+        if self.has(EffectKind::SpeedUp) { rules.speedup_mult } else { 1.0 }
+    }
+
+    pub fn steer(&self, desired_dir: Vec2) -> Vec2 {
+        if self.has(EffectKind::Confused) { -desired_dir } else { desired_dir }
+    }
+
+    pub fn score_mult(&self) -> i32 {
+        if self.has(EffectKind::DoublePoints) { 2 } else { 1 }
+    }
+
+    pub fn ghost(&self) -> bool {
+        self.has(EffectKind::Ghost)
+    }
+
+    // Shield: cancels a collision death (not the arena edge or the zone).
+    pub fn absorbs(&mut self, death: &Death) -> bool {
+        let collision = matches!(
+            death.cause,
+            DeathCause::HeadToHead | DeathCause::Body | DeathCause::SelfHit
+        );
+        if !collision || !self.has(EffectKind::Shield) {
+            return false;
+        }
+        self.active.retain(|e| e.kind != EffectKind::Shield);
+        self.set(EffectKind::Ghost, self.left(EffectKind::Ghost).max(SHIELD_GRACE_SEC));
+        true
+    }
+
+    fn set(&mut self, kind: EffectKind, left: f32) {
+        match self.active.iter_mut().find(|e| e.kind == kind) {
+            Some(e) => e.left = left,
+            None => self.active.push(ActiveEffect { kind, left }),
+        }
+    }
+}
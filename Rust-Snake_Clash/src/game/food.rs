@@ -1,6 +1,10 @@
 use macroquad::prelude::*;
 use macroquad::rand::gen_range;
 
+use crate::game::effects::{EffectKind, TokenRules};
+use crate::game::math::dist_sq_to_segment;
+use crate::game::mode::team_colors;
+
 // ---- Pellets ----
 
 #[derive(Clone, Copy)]
@@ -9,6 +13,8 @@ pub struct Pellet {
     pub radius: f32,
     pub value: i32,
     pub color: Color,
+    // Team whose snake dropped it (team mode corpses), 0 otherwise.
+    pub team: u8,
 }
 
 pub struct Pellets {
@@ -67,10 +73,36 @@ impl Pellets {
             radius,
             value,
             color,
+            team: 0,
         };
         self.insert(pellet);
     }
 
+    // Boost mass cost: `count` small value-1 pellets scattered around the tail.
+    pub fn spawn_boost_drop(&mut self, tail: Vec2, count: usize, spread_px: f32, radius: f32) {
+        let color = Color::from_rgba(255, 240, 200, 255);
+        for _ in 0..count {
+            let jitter = vec2(gen_range(-1.0f32, 1.0), gen_range(-1.0f32, 1.0)) * spread_px;
+            self.spawn(tail + jitter, radius, 1, color);
+        }
+    }
+
+    // Battle royale: drop pellets outside `radius`; refills only spawn inside it.
+    pub fn shrink_to(&mut self, radius: f32) {
+        if radius >= self.world_radius {
+            return;
+        }
+        self.world_radius = radius;
+        let r2 = radius * radius;
+        let mut removed = 0;
+        for b in &mut self.buckets {
+            let before = b.len();
+            b.retain(|p| p.pos.length_squared() <= r2);
+            removed += before - b.len();
+        }
+        self.total -= removed;
+    }
+
     pub fn draw_visible_aabb<F>(&self, top_left: Vec2, bottom_right: Vec2, mut world_to_screen: F, radius_scale: f32)
     where
         F: FnMut(Vec2) -> Vec2,
@@ -104,6 +136,17 @@ impl Pellets {
         out
     }
 
+    // Same order as `positions()`.
+    pub fn teams(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.total);
+        for bucket in &self.buckets {
+            for p in bucket {
+                out.push(p.team);
+            }
+        }
+        out
+    }
+
     #[allow(dead_code)]
     pub fn spawn_corpse(&mut self, segments: &[Vec2], max_pellets: usize) {
         if segments.is_empty() {
@@ -122,7 +165,15 @@ impl Pellets {
         }
     }
 
-    pub fn spawn_corpse_score(&mut self, segments: &[Vec2], total_value: i32, max_pellets: usize, spread_px: f32) {
+    // `team` != 0 tints the pellets with the team color.
+    pub fn spawn_corpse_score(
+        &mut self,
+        segments: &[Vec2],
+        total_value: i32,
+        max_pellets: usize,
+        spread_px: f32,
+        team: u8,
+    ) {
         if segments.is_empty() || total_value <= 0 || max_pellets == 0 {
             return;
         }
@@ -131,7 +182,11 @@ impl Pellets {
         let base = (total_value / count as i32).max(1);
         let remainder = (total_value - base * count as i32).max(0) as usize;
 
-        let base_color = Color::from_rgba(255, 220, 140, 255);
+        let base_color = if team != 0 {
+            team_colors(team).0
+        } else {
+            Color::from_rgba(255, 220, 140, 255)
+        };
 
         for k in 0..count {
             let idx = (k * segments.len()) / count;
@@ -161,18 +216,32 @@ impl Pellets {
                 1.0,
             );
 
-            self.spawn(p + off, radius, value, color);
+            self.insert(Pellet {
+                pos: p + off,
+                radius,
+                value,
+                color,
+                team,
+            });
         }
     }
 
-    pub fn eat_colliding(&mut self, head: Vec2, head_radius: f32, pickup_bonus: f32, max_eat: usize) -> i32 {
+    // Eats the pellets touched by the head on its way from `from` to `head`.
+    pub fn eat_colliding(
+        &mut self,
+        from: Vec2,
+        head: Vec2,
+        head_radius: f32,
+        pickup_bonus: f32,
+        max_eat: usize,
+    ) -> i32 {
         if max_eat == 0 {
             return 0;
         }
 
         let reach = head_radius + 6.0 + pickup_bonus;
-        let (min_cell_x, min_cell_y) = self.cell_of(head - vec2(reach, reach));
-        let (max_cell_x, max_cell_y) = self.cell_of(head + vec2(reach, reach));
+        let (min_cell_x, min_cell_y) = self.cell_of(from.min(head) - vec2(reach, reach));
+        let (max_cell_x, max_cell_y) = self.cell_of(from.max(head) + vec2(reach, reach));
 
         let mut gained = 0;
         let mut eaten: usize = 0;
@@ -194,7 +263,7 @@ impl Pellets {
 
                     let p = bucket[i];
                     let r = head_radius + pickup_bonus + p.radius;
-                    if head.distance_squared(p.pos) <= r * r {
+                    if dist_sq_to_segment(p.pos, from, head) <= r * r {
                         gained += p.value;
                         bucket.swap_remove(i);
                         self.total -= 1;
@@ -377,6 +446,7 @@ impl Pellets {
             radius: r,
             value,
             color,
+            team: 0,
         }
     }
 }
@@ -388,50 +458,176 @@ pub enum TokenKind {
     Magnet,
     SpeedUp,
     TimeAdd,
+    Shield,
+    Ghost,
+    DoublePoints,
+    // Confuses the snakes around the picker.
+    Confuse,
+}
+
+impl TokenKind {
+    pub const ALL: [TokenKind; 7] = [
+        TokenKind::Magnet,
+        TokenKind::SpeedUp,
+        TokenKind::TimeAdd,
+        TokenKind::Shield,
+        TokenKind::Ghost,
+        TokenKind::DoublePoints,
+        TokenKind::Confuse,
+    ];
+
+    // Name on the wire (`TokenState::kind`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenKind::Magnet => "magnet",
+            TokenKind::SpeedUp => "speed",
+            TokenKind::TimeAdd => "time",
+            TokenKind::Shield => "shield",
+            TokenKind::Ghost => "ghost",
+            TokenKind::DoublePoints => "double",
+            TokenKind::Confuse => "confuse",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == value)
+    }
+
+    // Effect given to the picker (`Confuse` hits the others, `TimeAdd` the clock).
+    pub fn effect(self) -> Option<EffectKind> {
+        match self {
+            TokenKind::Magnet => Some(EffectKind::Magnet),
+            TokenKind::SpeedUp => Some(EffectKind::SpeedUp),
+            TokenKind::Shield => Some(EffectKind::Shield),
+            TokenKind::Ghost => Some(EffectKind::Ghost),
+            TokenKind::DoublePoints => Some(EffectKind::DoublePoints),
+            TokenKind::TimeAdd | TokenKind::Confuse => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Token {
+    // Stable while the token is on the ground (`TokenState::id`).
+    pub id: u32,
     pub pos: Vec2,
     pub kind: TokenKind,
+    // Seconds before it despawns; 0 = no lifetime.
+    pub ttl: f32,
 }
 
 pub struct Tokens {
     arena_radius: f32,
     target_count: usize,
     items: Vec<Token>,
+    next_id: u32,
+    ttl_sec: f32,
+    respawn_delay_sec: f32,
+    // Countdowns of the slots freed by pickups and despawns.
+    respawn_in: Vec<f32>,
+    // Spawn weight per kind (`GameRules::tokens`).
+    weights: Vec<(TokenKind, u32)>,
+    // Untimed modes (endless) have no use for +time tokens.
+    time_add_enabled: bool,
 }
 
 impl Tokens {
-    pub fn new(arena_radius: f32, target_count: usize) -> Self {
+    pub fn new(arena_radius: f32, target_count: usize, rules: &TokenRules) -> Self {
         Self {
             arena_radius,
             target_count,
             items: Vec::new(),
+            next_id: 0,
+            ttl_sec: 0.0,
+            respawn_delay_sec: 0.0,
+            respawn_in: Vec::new(),
+            weights: TokenKind::ALL.iter().map(|k| (*k, rules.get(*k).weight)).collect(),
+            time_add_enabled: true,
+        }
+    }
+
+    pub fn set_time_add_enabled(&mut self, enabled: bool) {
+        self.time_add_enabled = enabled;
+        if !enabled {
+            self.items.retain(|t| t.kind != TokenKind::TimeAdd);
+        }
+    }
+
+    // Tokens despawn after about `ttl_sec` (0 = never); a freed slot refills
+    // `respawn_delay_sec` later.
+    pub fn set_lifetime(&mut self, ttl_sec: f32, respawn_delay_sec: f32) {
+        self.ttl_sec = ttl_sec;
+        self.respawn_delay_sec = respawn_delay_sec;
+    }
+
+    // Counts lifetimes and respawn delays down, despawning expired tokens.
+    pub fn tick(&mut self, dt: f32) {
+        for left in &mut self.respawn_in {
+            *left -= dt;
+        }
+        self.respawn_in.retain(|left| *left > 0.0);
+
+        if self.ttl_sec <= 0.0 {
+            return;
+        }
+        let mut i = 0;
+        while i < self.items.len() {
+            let t = &mut self.items[i];
+            t.ttl -= dt;
+            if t.ttl <= 0.0 {
+                self.items.swap_remove(i);
+                self.free_slot();
+                continue;
+            }
+            i += 1;
         }
     }
 
+    // Tokens on the ground plus slots waiting to respawn.
     pub fn total(&self) -> usize {
-        self.items.len()
+        self.items.len() + self.respawn_in.len()
+    }
+
+    // Battle royale: same as `Pellets::shrink_to`.
+    pub fn shrink_to(&mut self, radius: f32) {
+        if radius >= self.arena_radius {
+            return;
+        }
+        self.arena_radius = radius;
+        let r2 = radius * radius;
+        self.items.retain(|t| t.pos.length_squared() <= r2);
     }
 
     pub fn clear(&mut self) {
         self.items.clear();
+        self.respawn_in.clear();
     }
 
+    // Timed modes always keep one +time token on the ground.
     pub fn populate_random(&mut self) {
-        while self.items.len() < self.target_count {
-            let (m, s, t) = self.count_kinds();
-            let kind = if t == 0 {
-                TokenKind::TimeAdd
-            } else if m == 0 {
-                TokenKind::Magnet
-            } else if s == 0 {
-                TokenKind::SpeedUp
+        while self.total() < self.target_count {
+            let has_time = self.items.iter().any(|t| t.kind == TokenKind::TimeAdd);
+            let kind = if !has_time && self.weight(TokenKind::TimeAdd) > 0 {
+                Some(TokenKind::TimeAdd)
             } else {
-                self.random_token().kind
+                self.random_kind()
+            };
+            let Some(kind) = kind else {
+                return;
             };
-            self.items.push(Token { pos: self.random_pos(), kind });
+            // Spread lifetimes so a batch spawned together does not vanish at once.
+            let ttl = if self.ttl_sec > 0.0 {
+                self.ttl_sec * gen_range(0.75f32, 1.25f32)
+            } else {
+                0.0
+            };
+            self.items.push(Token {
+                id: self.next_id,
+                pos: self.random_pos(),
+                kind,
+                ttl,
+            });
+            self.next_id = self.next_id.wrapping_add(1);
         }
     }
 
@@ -440,11 +636,18 @@ impl Tokens {
     }
 
     #[allow(dead_code)]
-    pub fn collect_colliding(&mut self, head: Vec2, head_radius: f32) -> Vec<TokenKind> {
-        self.collect_colliding_filtered(head, head_radius, |_| true)
+    pub fn collect_colliding(&mut self, from: Vec2, head: Vec2, head_radius: f32) -> Vec<TokenKind> {
+        self.collect_colliding_filtered(from, head, head_radius, |_| true)
     }
 
-    pub fn collect_colliding_filtered<F>(&mut self, head: Vec2, head_radius: f32, mut allow: F) -> Vec<TokenKind>
+    // Same sweep as `Pellets::eat_colliding`.
+    pub fn collect_colliding_filtered<F>(
+        &mut self,
+        from: Vec2,
+        head: Vec2,
+        head_radius: f32,
+        mut allow: F,
+    ) -> Vec<TokenKind>
     where
         F: FnMut(TokenKind) -> bool,
     {
@@ -455,12 +658,11 @@ impl Tokens {
             let t = self.items[i];
             let tr = token_radius(t.kind);
             let r = head_radius + tr;
-            if head.distance_squared(t.pos) <= r * r {
-                if allow(t.kind) {
-                    collected.push(t.kind);
-                    self.items.swap_remove(i);
-                    continue;
-                }
+            if dist_sq_to_segment(t.pos, from, head) <= r * r && allow(t.kind) {
+                collected.push(t.kind);
+                self.items.swap_remove(i);
+                self.free_slot();
+                continue;
             }
             i += 1;
         }
@@ -500,6 +702,9 @@ impl Tokens {
                 continue;
             }
 
+            if token_blink_hidden(t.ttl) {
+                continue;
+            }
             let sp = world_to_screen(t.pos);
             let r = token_radius(t.kind);
             draw_token_screen(sp, r, t.kind);
@@ -510,47 +715,54 @@ impl Tokens {
         &self.items
     }
 
+    fn free_slot(&mut self) {
+        if self.respawn_delay_sec > 0.0 {
+            self.respawn_in.push(self.respawn_delay_sec);
+        }
+    }
+
     fn random_pos(&self) -> Vec2 {
         let a = gen_range(0.0f32, std::f32::consts::TAU);
         let r = gen_range(0.0f32, 1.0f32).sqrt() * self.arena_radius;
         vec2(a.cos() * r, a.sin() * r)
     }
 
-    fn random_token(&self) -> Token {
-        let roll = gen_range(0, 100);
-        let kind = if roll < 40 {
-            TokenKind::Magnet
-        } else if roll < 80 {
-            TokenKind::SpeedUp
-        } else {
-            TokenKind::TimeAdd
-        };
-        Token {
-            pos: self.random_pos(),
-            kind,
+    fn weight(&self, kind: TokenKind) -> u32 {
+        if kind == TokenKind::TimeAdd && !self.time_add_enabled {
+            return 0;
         }
+        self.weights.iter().find(|(k, _)| *k == kind).map(|(_, w)| *w).unwrap_or(0)
     }
 
-    fn count_kinds(&self) -> (usize, usize, usize) {
-        let mut m = 0;
-        let mut s = 0;
-        let mut t = 0;
-        for it in &self.items {
-            match it.kind {
-                TokenKind::Magnet => m += 1,
-                TokenKind::SpeedUp => s += 1,
-                TokenKind::TimeAdd => t += 1,
+    // None when every weight is 0.
+    fn random_kind(&self) -> Option<TokenKind> {
+        let total: u32 = TokenKind::ALL.iter().map(|k| self.weight(*k)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = gen_range(0, total);
+        for kind in TokenKind::ALL {
+            let w = self.weight(kind);
+            if roll < w {
+                return Some(kind);
             }
+            roll -= w;
         }
-        (m, s, t)
+        None
     }
 }
 
+// Tokens about to despawn blink during their last seconds.
+pub fn token_blink_hidden(ttl: f32) -> bool {
+    ttl > 0.0 && ttl < 3.0 && (get_time() * 6.0) as i64 % 2 == 0
+}
+
 pub fn token_radius(kind: TokenKind) -> f32 {
     match kind {
         TokenKind::Magnet => 19.0,
         TokenKind::SpeedUp => 19.0,
         TokenKind::TimeAdd => 21.0,
+        TokenKind::Shield | TokenKind::Ghost | TokenKind::DoublePoints | TokenKind::Confuse => 19.0,
     }
 }
 
@@ -559,6 +771,10 @@ pub fn draw_token_screen(screen_pos: Vec2, radius: f32, kind: TokenKind) {
         TokenKind::Magnet => (Color::from_rgba(120, 220, 255, 200), Color::from_rgba(120, 220, 255, 255)),
         TokenKind::SpeedUp => (Color::from_rgba(170, 255, 130, 200), Color::from_rgba(170, 255, 130, 255)),
         TokenKind::TimeAdd => (Color::from_rgba(255, 120, 200, 200), Color::from_rgba(255, 120, 200, 255)),
+        TokenKind::Shield => (Color::from_rgba(110, 160, 255, 200), Color::from_rgba(150, 190, 255, 255)),
+        TokenKind::Ghost => (Color::from_rgba(230, 230, 255, 120), Color::from_rgba(240, 240, 255, 230)),
+        TokenKind::DoublePoints => (Color::from_rgba(255, 200, 60, 200), Color::from_rgba(255, 220, 110, 255)),
+        TokenKind::Confuse => (Color::from_rgba(180, 110, 255, 200), Color::from_rgba(200, 150, 255, 255)),
     };
 
     match kind {
@@ -598,5 +814,69 @@ pub fn draw_token_screen(screen_pos: Vec2, radius: f32, kind: TokenKind) {
             draw_rectangle_lines(p.x - r * 0.2, p.y - r, r * 0.4, r * 2.0, 2.0, stroke);
             draw_rectangle_lines(p.x - r, p.y - r * 0.2, r * 2.0, r * 0.4, 2.0, stroke);
         }
+        TokenKind::Shield => {
+            let p = screen_pos;
+            draw_poly(p.x, p.y, 6, radius, 90.0, fill);
+            draw_poly_lines(p.x, p.y, 6, radius, 90.0, 2.0, stroke);
+            draw_poly_lines(p.x, p.y, 6, radius * 0.55, 90.0, 2.0, stroke);
+        }
+        TokenKind::Ghost => {
+            let p = screen_pos;
+            let r = radius;
+            draw_circle(p.x, p.y, r, fill);
+            draw_circle_lines(p.x, p.y, r, 2.0, stroke);
+            let eye = Color::from_rgba(40, 40, 70, 220);
+            draw_circle(p.x - r * 0.35, p.y - r * 0.15, r * 0.18, eye);
+            draw_circle(p.x + r * 0.35, p.y - r * 0.15, r * 0.18, eye);
+        }
+        TokenKind::DoublePoints => {
+            let p = screen_pos;
+            let r = radius;
+            draw_circle(p.x, p.y, r, fill);
+            draw_circle_lines(p.x, p.y, r, 2.0, stroke);
+            let size = r * 1.3;
+            let dims = measure_text("x2", None, size as u16, 1.0);
+            draw_text("x2", p.x - dims.width * 0.5, p.y + dims.height * 0.4, size, Color::from_rgba(60, 40, 0, 255));
+        }
+        TokenKind::Confuse => {
+            let p = screen_pos;
+            let r = radius;
+            draw_poly(p.x, p.y, 5, r, -90.0, fill);
+            draw_poly_lines(p.x, p.y, 5, r, -90.0, 2.0, stroke);
+            let size = r * 1.3;
+            let dims = measure_text("?", None, size as u16, 1.0);
+            draw_text("?", p.x - dims.width * 0.5, p.y + dims.height * 0.4, size, WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_head_eats_pellets_on_its_path() {
+        let mut pellets = Pellets::new(140.0, 2600.0);
+        pellets.spawn(vec2(0.0, 0.0), 4.0, 1, WHITE);
+        // Both ends are out of reach of the pellet; the path crosses it.
+        let (from, head) = (vec2(-30.0, 0.0), vec2(30.0, 0.0));
+        assert_eq!(pellets.eat_colliding(head, head, 6.0, 0.0, 8), 0);
+        assert_eq!(pellets.eat_colliding(from, head, 6.0, 0.0, 8), 1);
+        assert_eq!(pellets.total(), 0);
+    }
+
+    #[test]
+    fn fast_head_collects_tokens_on_its_path() {
+        let mut tokens = Tokens::new(2600.0, 0, &TokenRules::default());
+        tokens.items.push(Token {
+            id: 0,
+            pos: vec2(0.0, 0.0),
+            kind: TokenKind::Magnet,
+            ttl: 0.0,
+        });
+        let (from, head) = (vec2(0.0, -60.0), vec2(0.0, 60.0));
+        assert!(tokens.collect_colliding(head, head, 6.0).is_empty());
+        assert_eq!(tokens.collect_colliding(from, head, 6.0), vec![TokenKind::Magnet]);
+        assert_eq!(tokens.total(), 0);
     }
 }
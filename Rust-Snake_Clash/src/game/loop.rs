@@ -1,36 +1,37 @@
+use std::collections::HashMap;
+
 use macroquad::prelude::*;
-use macroquad::rand::gen_range;
 
 use crate::config::{
-    ARENA_RADIUS, BASE_SNAKE_LENGTH, BASE_SPEED, BOOST_ENERGY_DRAIN_PER_SEC, BOOST_ENERGY_MAX,
-    BOOST_ENERGY_REGEN_PER_SEC, BOOST_SPEED_MULT, ENERGY_BAR_MAX,
-    ISO_SCALE, MATCH_DURATION_SEC, PELLET_BUCKET_SIZE, PELLET_RADIUS, PELLET_TARGET_COUNT,
-    SCORE_PER_SEGMENT, UI_BOOST_BUTTON_CENTER, UI_BOOST_BUTTON_RADIUS, UI_JOYSTICK_CENTER,
-    MAGNET_ATTRACT_MAX_PER_FRAME, MAGNET_ATTRACT_RADIUS, MAGNET_ATTRACT_SPEED, MAGNET_PICKUP_BONUS_PX,
-    SPEEDUP_MULT, TOKEN_DURATION_SEC, TOKEN_TARGET_COUNT, TOKEN_TIME_ADD_SEC,
+    ISO_SCALE, UI_BOOST_BUTTON_CENTER, UI_BOOST_BUTTON_RADIUS, UI_JOYSTICK_CENTER,
     UI_JOYSTICK_DEADZONE, UI_JOYSTICK_RADIUS,
-    BASE_SNAKE_RADIUS, MAX_SNAKE_RADIUS, SNAKE_RADIUS_GROWTH_EXP, SNAKE_RADIUS_SCORE_HALF,
-    SNAKE_SPACING_MAX, SNAKE_SPACING_MULT,
     CAMERA_CENTER_BODY_BLEND, CAMERA_FIT_SCREEN_FRACTION, CAMERA_SCALE_BASE, CAMERA_SCALE_MAX, CAMERA_SCALE_MIN,
-    PELLET_EAT_MAX_PER_FRAME,
-    SMALL_SNAKE_SPEED_MULT,
     CAMERA_SNAKE_EXTENT_WORLD_MIN, CAMERA_SNAKE_EXTENT_WORLD_MULT,
     SPECTATE_CAMERA_CLAMP_MULT, SPECTATE_PAN_BOOST_MULT, SPECTATE_PAN_SPEED,
     SPECTATE_ZOOM_MAX, SPECTATE_ZOOM_MIN, SPECTATE_ZOOM_WHEEL_SENS,
     UI_ZOOM_BUTTON_RADIUS,
     UI_SCALE,
 };
+use crate::config::rules::GameRules;
+use crate::game::bot::{BotController, BotView};
+use crate::game::bot_profile::BotProfile;
 use crate::game::collision::{
-    apply_deaths, check_arena_bounds, check_head_to_body, check_head_to_head,
+    absorb_shields, apply_deaths, check_arena_bounds, check_collisions, DeathCause,
 };
-use crate::game::food::{draw_token_screen, Pellets, TokenKind, Tokens};
+use crate::game::effects::{EffectKind, Effects};
+use crate::game::food::{draw_token_screen, token_blink_hidden, Pellets, TokenKind, Tokens};
+use crate::game::mode::{is_teammate, team_colors, team_name, GameMode};
+use crate::game::skin::Skin;
+use crate::net::messages::{PlayerInfo, EMOTES};
 use crate::game::math::{
     input_pos_scale, point_in_circle, screen_to_world, screen_vec_to_world_dir, ui_anchor_portrait, world_to_screen,
 };
 use crate::game::world::{
-    make_initial_agents, random_unit_dir, AgentKind, FinishReason, FrameScratch, RunState,
+    make_initial_agents, AgentKind, FinishReason, FrameScratch, RunState,
 };
+use crate::game::sim::MassBurn;
 use crate::game::snake_sim::SnakeSim;
+use crate::client::master_api::{self, MasterReply, MasterRequest};
 use crate::client::runtime;
 
 fn draw_token_badge(x: f32, y: f32, kind: TokenKind, seconds_left: f32) {
@@ -43,12 +44,7 @@ fn draw_token_badge(x: f32, y: f32, kind: TokenKind, seconds_left: f32) {
     let icon_center = vec2(x + 18.0 * s, y + h * 0.5);
     draw_token_screen(icon_center, 10.0 * s, kind);
 
-    let label = match kind {
-        TokenKind::Magnet => "MAGNET",
-        TokenKind::SpeedUp => "SPEED",
-        TokenKind::TimeAdd => "TIME",
-    };
-    draw_text(label, x + 36.0 * s, y + 22.0 * s, 18.0 * s, Color::from_rgba(255, 255, 255, 220));
+    draw_text(token_label(kind), x + 36.0 * s, y + 22.0 * s, 18.0 * s, Color::from_rgba(255, 255, 255, 220));
     draw_text(
         &format!("{:04.1}", seconds_left.max(0.0)),
         x + w - 44.0 * s,
@@ -58,73 +54,207 @@ fn draw_token_badge(x: f32, y: f32, kind: TokenKind, seconds_left: f32) {
     );
 }
 
-fn agents_from_players(players: &[crate::net::messages::PlayerState]) -> Vec<crate::game::world::Agent> {
-    let palette = [
-        (Color::from_rgba(255, 140, 90, 255), Color::from_rgba(255, 120, 60, 255)),
-        (Color::from_rgba(110, 220, 255, 255), Color::from_rgba(80, 180, 240, 255)),
-        (Color::from_rgba(170, 255, 130, 255), Color::from_rgba(120, 220, 90, 255)),
-        (Color::from_rgba(255, 120, 200, 255), Color::from_rgba(220, 90, 180, 255)),
-        (Color::from_rgba(220, 220, 255, 255), Color::from_rgba(180, 180, 240, 255)),
-        (Color::from_rgba(255, 210, 120, 255), Color::from_rgba(255, 190, 80, 255)),
-    ];
+// One body circle with the agent's skin pattern (`index` 0 = head).
+fn draw_skin_segment(sp: Vec2, radius: f32, a: &crate::game::world::Agent, index: usize, count: usize) {
+    let (col, dot) = a.skin.segment_color(a.color_head, a.color_body, index, count);
+    draw_circle(sp.x, sp.y, radius, col);
+    if dot {
+        draw_circle(sp.x, sp.y, radius * 0.35, Color::from_rgba(0, 0, 0, 80));
+    }
+}
+
+// Running effects around a snake head (every snake, so the others' power-ups show too).
+// `head` and `dir` are in screen space.
+fn draw_effects(head: Vec2, dir: Vec2, radius: f32, effects: &Effects) {
+    let t = get_time() as f32;
+    for e in effects.active() {
+        match e.kind {
+            EffectKind::Magnet => {
+                let r = radius * (2.2 + 0.3 * (t * 5.0).sin());
+                draw_circle_lines(head.x, head.y, r, 2.0, Color::from_rgba(120, 220, 255, 150));
+            }
+            EffectKind::SpeedUp => {
+                let back = -dir;
+                let side = vec2(-dir.y, dir.x) * radius * 0.6;
+                for k in [-1.0f32, 0.0, 1.0] {
+                    let from = head + back * radius * 1.3 + side * k;
+                    let to = from + back * radius * (1.6 - 0.4 * k.abs());
+                    draw_line(from.x, from.y, to.x, to.y, 2.0, Color::from_rgba(170, 255, 130, 170));
+                }
+            }
+            EffectKind::Shield => {
+                draw_circle_lines(head.x, head.y, radius * 1.6, 3.0, Color::from_rgba(150, 190, 255, 210));
+            }
+            EffectKind::Ghost => {
+                draw_circle(head.x, head.y, radius * 1.8, Color::from_rgba(240, 240, 255, 50));
+            }
+            EffectKind::DoublePoints => {
+                draw_text("x2", head.x - radius * 0.6, head.y - radius * 1.6, 20.0, Color::from_rgba(255, 220, 110, 230));
+            }
+            EffectKind::Confused => {
+                draw_text("?", head.x + radius * 0.8, head.y - radius * 1.4, 24.0, Color::from_rgba(200, 150, 255, 230));
+            }
+        }
+    }
+}
+
+fn token_label(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::Magnet => "MAGNET",
+        TokenKind::SpeedUp => "SPEED",
+        TokenKind::TimeAdd => "TIME",
+        TokenKind::Shield => "SHIELD",
+        TokenKind::Ghost => "GHOST",
+        TokenKind::DoublePoints => "x2 PTS",
+        TokenKind::Confuse => "CONFUSED",
+    }
+}
 
+fn agents_from_players(
+    players: &[crate::net::messages::PlayerState],
+    infos: &HashMap<u32, PlayerInfo>,
+    rules: &GameRules,
+) -> Vec<crate::game::world::Agent> {
     players
         .iter()
-        .enumerate()
-        .map(|(idx, p)| {
-            let (head, body) = palette[idx % palette.len()];
+        .map(|p| {
+            let info = infos.get(&p.id);
+            let skin = info.map(|i| i.skin).unwrap_or_else(|| Skin::for_bot(p.id));
+            let (head, body) = if p.team != 0 {
+                team_colors(p.team)
+            } else {
+                skin.colors()
+            };
             let head_pos = vec2(p.head.x, p.head.y);
             let dir = vec2(p.dir.x, p.dir.y);
-            let mut snake = SnakeSim::new_at(head_pos, dir);
+            let mut snake = SnakeSim::new_at(head_pos, dir, rules);
             snake.radius = p.radius;
             crate::game::world::Agent {
-                kind: AgentKind::Player,
-                name: format!("P{}", p.id),
+                id: p.id,
+                kind: if p.is_bot { AgentKind::Bot } else { AgentKind::Player },
+                name: info.map(|i| i.name.clone()).unwrap_or_else(|| format!("P{}", p.id)),
                 color_head: head,
                 color_body: body,
+                skin,
+                team: p.team,
                 snake,
                 alive: p.alive,
                 respawn_left: 0.0,
                 score: p.score as f32,
                 boost_energy: p.boost,
-                magnet_left: 0.0,
-                speedup_left: 0.0,
-                bot_dir: dir,
-                bot_boost_intent: 0.0,
-                bot_hunt_target: None,
-                bot_hunt_left: 0.0,
+                effects: Effects::from_active(p.effects.clone()),
+                kills: p.kills,
+                mass_burn: MassBurn::default(),
+                bot: BotController::new(dir, BotProfile::default()),
             }
         })
         .collect()
 }
 
-fn token_kind_from_str(value: &str) -> Option<TokenKind> {
-    match value {
-        "magnet" => Some(TokenKind::Magnet),
-        "speed" => Some(TokenKind::SpeedUp),
-        "time" => Some(TokenKind::TimeAdd),
-        _ => None,
+fn draw_world_ring<F>(w2s: &F, center: Vec2, radius: f32, thickness: f32, color: Color)
+where
+    F: Fn(Vec2) -> Vec2,
+{
+    let steps = 80;
+    let mut prev: Option<Vec2> = None;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let a = t * std::f32::consts::TAU;
+        let sp = w2s(center + vec2(a.cos(), a.sin()) * radius);
+        if let Some(pp) = prev {
+            draw_line(pp.x, pp.y, sp.x, sp.y, thickness, color);
+        }
+        prev = Some(sp);
     }
 }
 
-fn agent_id_from_name(name: &str) -> u32 {
-    name.strip_prefix('P')
-        .and_then(|s| s.parse::<u32>().ok())
-        .unwrap_or(0)
+// (team, total score, alive count) per team present, best total first.
+fn team_totals(agents: &[crate::game::world::Agent]) -> Vec<(u8, i32, usize)> {
+    let mut rows: Vec<(u8, i32, usize)> = Vec::new();
+    for a in agents.iter().filter(|a| a.team != 0) {
+        let idx = match rows.iter().position(|r| r.0 == a.team) {
+            Some(idx) => idx,
+            None => {
+                rows.push((a.team, 0, 0));
+                rows.len() - 1
+            }
+        };
+        rows[idx].1 += a.score as i32;
+        if a.alive {
+            rows[idx].2 += 1;
+        }
+    }
+    rows.sort_by_key(|r| (std::cmp::Reverse(r.1), r.0));
+    rows
+}
+
+// Kill feed: newest line first, each shown for `KILL_FEED_SEC`.
+const KILL_FEED_MAX: usize = 5;
+const KILL_FEED_SEC: f32 = 4.0;
+// Net match end: the server reports the result on its next heartbeat, so the
+// new global rank is asked for a few times.
+const RANK_POLL_SEC: f32 = 2.0;
+const RANK_POLLS: u32 = 5;
+const EMOTE_BUBBLE_SEC: f32 = 2.5;
+
+fn kill_feed_line(victim: &str, killer: Option<&str>, cause: DeathCause) -> String {
+    match (killer, cause) {
+        (Some(killer), _) => format!("{} killed {}", killer, victim),
+        (None, DeathCause::ArenaEdge) => format!("{} hit the wall", victim),
+        (None, DeathCause::HeadToHead) => format!("{} died head-on", victim),
+        (None, DeathCause::Body) => format!("{} crashed", victim),
+        (None, DeathCause::SelfHit) => format!("{} bit itself", victim),
+        (None, DeathCause::Zone) => format!("{} fell to the zone", victim),
+    }
 }
 
-pub async fn run() {
-    let mut agents = make_initial_agents();
+fn push_kill_feed(feed: &mut Vec<(String, f32)>, line: String) {
+    feed.insert(0, (line, KILL_FEED_SEC));
+    feed.truncate(KILL_FEED_MAX);
+}
+
+// Spectator camera: next (`step` 1) or previous (-1) of the sorted `ids`. With
+// step 0 the current player is kept while listed, else the first one is taken.
+fn cycle_follow(ids: &[u32], current: Option<u32>, step: i32) -> Option<u32> {
+    if ids.is_empty() {
+        return current;
+    }
+    let pos = current.and_then(|c| ids.iter().position(|id| *id == c));
+    let idx = match (pos, step) {
+        (Some(p), 0) => p,
+        (Some(p), s) => (p as i32 + s).rem_euclid(ids.len() as i32) as usize,
+        (None, s) if s < 0 => ids.len() - 1,
+        (None, _) => 0,
+    };
+    Some(ids[idx])
+}
+
+// Name shown for a server player id.
+fn net_player_name(id: u32) -> String {
+    if runtime::local_player_id() == Some(id) {
+        return "YOU".to_owned();
+    }
+    runtime::player_infos()
+        .get(&id)
+        .map(|i| i.name.clone())
+        .unwrap_or_else(|| format!("P{}", id))
+}
+
+// `rules` drives solo games; once connected the room's rules (JoinOk) replace them.
+pub async fn run(rules: GameRules) {
+    let mut rules = rules;
+    let mut agents = make_initial_agents(&rules, crate::client::prefs::get().skin);
     let mut scratch = FrameScratch::new();
 
-    let mut pellets = Pellets::new(PELLET_BUCKET_SIZE, ARENA_RADIUS);
-    pellets.populate_random(PELLET_TARGET_COUNT, PELLET_RADIUS);
+    let mut pellets = Pellets::new(rules.pellet_bucket_size, rules.arena_radius);
+    pellets.populate_random(rules.pellet_target_count, rules.pellet_radius);
 
-    let mut tokens = Tokens::new(ARENA_RADIUS, TOKEN_TARGET_COUNT);
+    let mut tokens = Tokens::new(rules.arena_radius, rules.token_target_count, &rules.tokens);
+    tokens.set_lifetime(rules.token_ttl_sec, rules.token_respawn_delay_sec);
     tokens.populate_random();
 
     let arena_center = vec2(0.0, 0.0);
-    let mut time_left: f32 = MATCH_DURATION_SEC;
+    let mut time_left: f32 = rules.match_duration_sec;
     let mut time_added_total: f32 = 0.0;
     let mut time_add_flash: f32 = 0.0;
     let mut countdown_left: f32 = 0.0;
@@ -136,7 +266,13 @@ pub async fn run() {
     };
     let mut finish_reason: Option<FinishReason> = None;
 
-    let mut best_score: i32 = 0;
+    // Player record, kept between runs in the menu prefs (per match in the demo).
+    let mut best_score: i32 = if spectator_demo { 0 } else { crate::client::prefs::get().best_score };
+    let mut saved_best_score = best_score;
+    // Global rank after a net match (master profile).
+    let mut global_rank: Option<u32> = None;
+    let mut rank_polls_left: Option<u32> = None;
+    let mut rank_poll_in: f32 = 0.0;
 
     let mut finished_winner_idx: Option<usize> = None;
 
@@ -145,8 +281,10 @@ pub async fn run() {
     let mut timeadd_badge_left: f32 = 0.0;
     let mut toast_left: f32 = 0.0;
     let mut toast_text: String = String::new();
-    let mut net_magnet_left: f32 = 0.0;
-    let mut net_speedup_left: f32 = 0.0;
+    let mut kill_feed: Vec<(String, f32)> = Vec::new();
+    let mut net_respawn_left: f32 = 0.0;
+    // Team mode: winner announced by the server at time up (0 = draw).
+    let mut net_team_winner: Option<u8> = None;
 
     let mut joystick_active = false;
     let mut joystick_origin: Vec2;
@@ -159,6 +297,13 @@ pub async fn run() {
 
     let mut spectate_zoom: f32 = 1.0;
     let mut prev_pinch_dist: Option<f32> = None;
+    // Net spectator session: player the camera follows, or a free camera.
+    let mut spectate_follow: Option<u32> = None;
+    let mut spectate_free = false;
+    // Net rooms: chat line being typed (Some while the chat box is open), and
+    // emote bubbles by player id (emote id, seconds left).
+    let mut chat_draft: Option<String> = None;
+    let mut emote_bubbles: HashMap<u32, (u8, f32)> = HashMap::new();
 
     loop {
         if crate::client::lobby_ui::update() {
@@ -166,11 +311,15 @@ pub async fn run() {
             continue;
         }
         runtime::poll();
+        if let Some(server_rules) = runtime::take_rules() {
+            rules = server_rules;
+        }
         let dt = get_frame_time();
         clear_background(Color::from_rgba(12, 14, 20, 255));
 
         let spectator_demo = cfg!(feature = "demo100");
-        let heavy_mode = cfg!(feature = "demo100") || cfg!(feature = "demo_play100");
+        // Many bots: cheaper AI, collision checks and rendering.
+        let heavy_mode = rules.bot_count >= 50;
 
         let input_scale = input_pos_scale();
 
@@ -193,6 +342,20 @@ pub async fn run() {
         let mut zoom_plus_held = false;
         let mut zoom_minus_held = false;
 
+        // Net spectator bar: "<" previous player, name (free camera toggle), ">" next player.
+        let follow_bar = Rect::new(
+            screen_width() * 0.5 - 200.0 * ui_s,
+            screen_height() - 84.0 * ui_s,
+            400.0 * ui_s,
+            56.0 * ui_s,
+        );
+        let follow_prev = Rect::new(follow_bar.x, follow_bar.y, 70.0 * ui_s, follow_bar.h);
+        let follow_next = Rect::new(follow_bar.right() - 70.0 * ui_s, follow_bar.y, 70.0 * ui_s, follow_bar.h);
+        // Net players: one emote button per `EMOTES` entry, down the left edge.
+        let emote_button = |idx: usize| {
+            Rect::new(16.0 * ui_s, screen_height() * 0.42 + idx as f32 * 48.0 * ui_s, 64.0 * ui_s, 40.0 * ui_s)
+        };
+
         let screen_center = vec2(screen_width() * 0.5, screen_height() * 0.56);
 
         let mut stick_delta = vec2(0.0, 0.0);
@@ -200,10 +363,8 @@ pub async fn run() {
 
         if let Some(t) = touches().first() {
             let p = t.position * input_scale;
-            if !joystick_active {
-                if p.x > screen_width() * 0.45 {
-                    joystick_active = true;
-                }
+            if !joystick_active && p.x > screen_width() * 0.45 {
+                joystick_active = true;
             }
             if joystick_active {
                 stick_delta = p - joystick_origin;
@@ -224,8 +385,7 @@ pub async fn run() {
 
         let stick_len = stick_delta.length();
         let stick_dir_screen = if stick_len > 0.001 {
-            let clamped = stick_delta / stick_len * stick_len.min(joystick_radius);
-            clamped
+            stick_delta / stick_len * stick_len.min(joystick_radius)
         } else {
             vec2(0.0, 0.0)
         };
@@ -236,7 +396,9 @@ pub async fn run() {
             desired_dir_world = screen_vec_to_world_dir(stick_dir_screen, camera_scale);
         }
 
-        let wants_boost_keyboard = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        // Shift types capitals while a chat line is open (endless rooms).
+        let wants_boost_keyboard =
+            chat_draft.is_none() && (is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift));
         let wants_boost_mouse = is_mouse_button_down(MouseButton::Right);
         let mut wants_boost_touch = false;
         for t in touches() {
@@ -256,10 +418,35 @@ pub async fn run() {
 
         let mut net_mode = false;
         let mut net_agents: Option<Vec<crate::game::world::Agent>> = None;
+        let net_spectator = runtime::is_spectator();
         {
             let mut players = runtime::latest_players();
+            if net_spectator && !players.is_empty() {
+                let mut step = 0;
+                let click = is_mouse_button_pressed(MouseButton::Left).then(|| {
+                    let (mx, my) = mouse_position();
+                    vec2(mx, my) * input_scale
+                });
+                if is_key_pressed(KeyCode::Right) || is_key_pressed(KeyCode::Tab)
+                    || click.is_some_and(|p| follow_next.contains(p))
+                {
+                    step = 1;
+                } else if is_key_pressed(KeyCode::Left) || click.is_some_and(|p| follow_prev.contains(p)) {
+                    step = -1;
+                } else if is_key_pressed(KeyCode::F) || click.is_some_and(|p| follow_bar.contains(p)) {
+                    spectate_free = !spectate_free;
+                }
+                if step != 0 {
+                    spectate_free = false;
+                }
+                let mut alive: Vec<u32> = players.iter().filter(|p| p.alive).map(|p| p.id).collect();
+                alive.sort_unstable();
+                spectate_follow = cycle_follow(&alive, spectate_follow, step);
+            }
             if !players.is_empty() {
-                if let Some(local_id) = runtime::local_player_id() {
+                // A spectator's followed player takes the local player's place.
+                let local = if net_spectator { spectate_follow } else { runtime::local_player_id() };
+                if let Some(local_id) = local {
                     let mut reordered = Vec::with_capacity(players.len());
                     if let Some(pos) = players.iter().position(|p| p.id == local_id) {
                         reordered.push(players[pos].clone());
@@ -271,11 +458,23 @@ pub async fn run() {
                         players = reordered;
                     }
                 }
-                net_agents = Some(agents_from_players(&players));
+                net_agents = Some(agents_from_players(&players, &runtime::player_infos(), &rules));
                 net_mode = true;
             }
         }
 
+        // Endless rooms: no clock, dead players respawn, all-time best lengths.
+        let endless = net_mode && runtime::game_mode() == Some(GameMode::Endless);
+        // Team rooms: snakes, corpses, leaderboard and minimap use team colors.
+        let teams = net_mode && runtime::game_mode() == Some(GameMode::Teams);
+        // Battle royale rooms: shrinking safe zone (current, next radius).
+        let royale = net_mode && runtime::game_mode() == Some(GameMode::Royale);
+        let (zone_radius, zone_next_radius) = if royale {
+            runtime::latest_zone()
+        } else {
+            (rules.arena_radius, rules.arena_radius)
+        };
+
         if net_mode {
             time_left = runtime::latest_time_left();
             countdown_left = runtime::latest_countdown_left();
@@ -337,11 +536,9 @@ pub async fn run() {
                     prev_pinch_dist = None;
                 } else {
                     let d = p0.distance(p1);
-                    if let Some(prev) = prev_pinch_dist {
-                        if prev > 1.0 {
-                            let ratio = (d / prev).clamp(0.85, 1.18);
-                            spectate_zoom *= ratio;
-                        }
+                    if let Some(prev) = prev_pinch_dist && prev > 1.0 {
+                        let ratio = (d / prev).clamp(0.85, 1.18);
+                        spectate_zoom *= ratio;
                     }
                     prev_pinch_dist = Some(d);
                 }
@@ -383,20 +580,21 @@ pub async fn run() {
                 time_add_flash = (time_add_flash - dt).max(0.0);
 
                 for a in &mut agents {
-                    a.magnet_left = (a.magnet_left - dt).max(0.0);
-                    a.speedup_left = (a.speedup_left - dt).max(0.0);
+                    a.effects.tick(dt);
                 }
 
                 scratch.resize_for_agents(agents.len());
-                for a in &agents {
+                for (i, a) in agents.iter().enumerate() {
                     scratch.agents_snapshot.push(crate::game::world::AgentSnapshot {
+                        id: i as u32,
+                        team: a.team,
                         alive: a.alive,
                         head: a.snake.head_pos(),
                         radius: a.snake.radius,
                     });
                 }
-                let alive_count_snapshot = scratch.agents_snapshot.iter().filter(|s| s.alive).count();
 
+                #[allow(clippy::needless_range_loop)]
                 for idx in 0..agents.len() {
                     if !agents[idx].alive {
                         continue;
@@ -410,195 +608,72 @@ pub async fn run() {
                     let (desired, wants_boost_agent) = if is_player {
                         (desired_dir_world, wants_boost)
                     } else {
-                        let norm = |v: Vec2| {
-                            if v.length_squared() > 0.0001 {
-                                v.normalize()
-                            } else {
-                                vec2(0.0, 0.0)
-                            }
+                        let effects = agents[idx].effects.clone();
+                        let view = BotView {
+                            me: idx,
+                            snakes: &scratch.agents_snapshot,
+                            pellets: &pellets,
+                            tokens: &tokens,
+                            arena_radius: rules.arena_radius,
+                            boost_energy: agents[idx].boost_energy,
+                            effects: &effects,
+                            heavy_mode,
                         };
-
-                    let head = scratch.agents_snapshot[idx].head;
-                    let my_r = scratch.agents_snapshot[idx].radius;
-
-                    let late_game = if heavy_mode {
-                        alive_count_snapshot <= 18
-                    } else {
-                        alive_count_snapshot <= 7
+                        let input = agents[idx].bot.think(&view, dt);
+                        (input.dir, input.boost)
                     };
-                    let big_enough = if heavy_mode { my_r >= 18.0 } else { my_r >= 22.0 };
-
-                    agents[idx].bot_hunt_left = (agents[idx].bot_hunt_left - dt).max(0.0);
-                    let mut hunt: Option<(usize, Vec2, f32)> = None;
-
-                    if agents[idx].bot_hunt_left > 0.0 {
-                        if let Some(ti) = agents[idx].bot_hunt_target {
-                            if ti != idx {
-                                let s = scratch.agents_snapshot[ti];
-                                if s.alive && my_r > s.radius * 1.07 {
-                                    let dist = head.distance(s.head);
-                                    hunt = Some((ti, s.head, dist));
-                                }
-                            }
-                        }
-                        if hunt.is_none() {
-                            agents[idx].bot_hunt_target = None;
-                            agents[idx].bot_hunt_left = 0.0;
-                        }
-                    }
 
-                    if hunt.is_none() && (late_game || big_enough) {
-                        let chase_r = if heavy_mode { 1800.0 } else { 1200.0 };
-                        let mut best: Option<(usize, Vec2, f32, f32)> = None;
-                        for (j, s) in scratch.agents_snapshot.iter().enumerate() {
-                            if !s.alive || j == idx {
-                                continue;
-                            }
-                            if my_r <= s.radius * 1.07 {
-                                continue;
-                            }
-                            let dist = head.distance(s.head);
-                            if dist > chase_r {
-                                continue;
-                            }
-                            let ratio = (my_r / (s.radius).max(0.01)).clamp(1.0, 3.0);
-                            let score = ratio * 900.0 - dist;
-                            if best.as_ref().map(|b| score > b.3).unwrap_or(true) {
-                                best = Some((j, s.head, dist, score));
-                            }
-                        }
-                        if let Some((ti, pos, dist, _score)) = best {
-                            agents[idx].bot_hunt_target = Some(ti);
-                            agents[idx].bot_hunt_left = gen_range(1.2f32, if heavy_mode { 2.8f32 } else { 2.2f32 });
-                            hunt = Some((ti, pos, dist));
-                        }
-                    }
+                let boosting = wants_boost_agent
+                    && agents[idx].boost_energy > 0.01
+                    && rules.boost_allowed(agents[idx].score);
 
-                    let d = head.length();
-                    let inward = if d > ARENA_RADIUS * 0.80 {
-                        norm(-head)
-                    } else {
-                        vec2(0.0, 0.0)
-                    };
-
-                    let mut flee = vec2(0.0, 0.0);
-                    let mut flee_w = 0.0;
-                    let danger_r = 620.0;
-                    for (j, s) in scratch.agents_snapshot.iter().enumerate() {
-                        if !s.alive || j == idx {
-                            continue;
-                        }
-                        if s.radius <= my_r * 1.08 {
-                            continue;
-                        }
-                        let dist = head.distance(s.head);
-                        if dist < danger_r {
-                            let dir = (head - s.head) / dist.max(0.001);
-                            let w = (1.0 - dist / danger_r).clamp(0.0, 1.0);
-                            flee += dir * w;
-                            flee_w += w;
-                        }
-                    }
-
-                    let mut chase: Option<(Vec2, f32)> = None;
-                    let chase_r = if heavy_mode { 720.0 } else { 560.0 };
-                    if flee_w <= 0.01 && hunt.is_none() {
-                        let mut best_d = f32::INFINITY;
-                        for (j, s) in scratch.agents_snapshot.iter().enumerate() {
-                            if !s.alive || j == idx {
-                                continue;
-                            }
-                            if my_r <= s.radius * 1.12 {
-                                continue;
-                            }
-                            let dist = head.distance(s.head);
-                            if dist < chase_r && dist < best_d {
-                                best_d = dist;
-                                chase = Some((s.head, dist));
-                            }
-                        }
-                    }
-
-                    let token_target = tokens.best_target(head, |k| match k {
-                        TokenKind::TimeAdd => Some(1.7),
-                        TokenKind::Magnet => if agents[idx].magnet_left > 0.0 { None } else { Some(2.2) },
-                        TokenKind::SpeedUp => if agents[idx].speedup_left > 0.0 { None } else { Some(2.0) },
-                    });
-
-                    let pellet_target = pellets.best_pellet_target(head, 820.0);
-
-                    let (mut desired, wants_boost_agent) = if flee_w > 0.01 {
-                        (norm(flee), agents[idx].boost_energy > 30.0)
-                    } else if let Some((_ti, tpos, dist)) = hunt {
-                        let wants = agents[idx].boost_energy > 35.0 && dist < (if heavy_mode { 980.0 } else { 760.0 });
-                        (norm(tpos - head), wants)
-                    } else if let Some((tpos, dist)) = chase {
-                        (norm(tpos - head), agents[idx].boost_energy > 45.0 && dist < 360.0)
-                    } else if let Some((tpos, tkind)) = token_target {
-                        let wants = match tkind {
-                            TokenKind::TimeAdd => agents[idx].boost_energy > 65.0 && head.distance(tpos) < 520.0,
-                            _ => agents[idx].boost_energy > 55.0 && head.distance(tpos) < 520.0,
-                        };
-                        (norm(tpos - head), wants)
-                    } else if let Some(ppos) = pellet_target {
-                        (norm(ppos - head), false)
-                    } else {
-                        let jitter = random_unit_dir();
-                        (norm(agents[idx].bot_dir.lerp(jitter, 0.08)), false)
-                    };
-
-                    if desired.length_squared() <= 0.0001 {
-                        desired = agents[idx].bot_dir;
-                    }
-
-                    if inward.length_squared() > 0.0001 {
-                        desired = norm(desired * 0.62 + inward * 1.10);
-                    }
-
-                    let t = (3.4 * dt).clamp(0.0, 1.0);
-                    agents[idx].bot_dir = norm(agents[idx].bot_dir.lerp(desired, 0.55 * t));
-
-                    agents[idx].bot_boost_intent = (agents[idx].bot_boost_intent - dt).max(0.0);
-                    if wants_boost_agent {
-                        if agents[idx].bot_boost_intent <= 0.0 {
-                            agents[idx].bot_boost_intent = gen_range(0.25f32, 0.55f32);
-                        }
-                    }
-                    let wants_boost_agent = agents[idx].bot_boost_intent > 0.0;
-
-                    (agents[idx].bot_dir, wants_boost_agent)
-                };
-
-                let boosting = wants_boost_agent && agents[idx].boost_energy > 0.01;
-
-                let size_t = ((agents[idx].snake.radius - BASE_SNAKE_RADIUS)
-                    / (MAX_SNAKE_RADIUS - BASE_SNAKE_RADIUS))
-                    .clamp(0.0, 1.0);
-                let size_speed_mult = SMALL_SNAKE_SPEED_MULT + (1.0 - SMALL_SNAKE_SPEED_MULT) * size_t;
-                let token_mult = if agents[idx].speedup_left > 0.0 { SPEEDUP_MULT } else { 1.0 };
-                let boost_mult = if boosting { BOOST_SPEED_MULT } else { 1.0 };
-                agents[idx].snake.speed = BASE_SPEED * size_speed_mult * token_mult * boost_mult;
+                let size_speed_mult = rules.size_speed_mult(agents[idx].snake.radius);
+                let token_mult = agents[idx].effects.speed_mult(&rules);
+                let boost_mult = if boosting { rules.boost_speed_mult } else { 1.0 };
+                agents[idx].snake.speed = rules.base_speed * size_speed_mult * token_mult * boost_mult;
 
                 if boosting {
                     agents[idx].boost_energy =
-                        (agents[idx].boost_energy - BOOST_ENERGY_DRAIN_PER_SEC * dt).max(0.0);
+                        (agents[idx].boost_energy - rules.boost_energy_drain_per_sec * dt).max(0.0);
                 } else {
-                    agents[idx].boost_energy =
-                        (agents[idx].boost_energy + BOOST_ENERGY_REGEN_PER_SEC * dt).min(BOOST_ENERGY_MAX);
+                    agents[idx].boost_energy = (agents[idx].boost_energy
+                        + rules.boost_energy_regen_per_sec * dt)
+                        .min(rules.boost_energy_max);
                 }
 
+                let desired = agents[idx].effects.steer(desired);
                 agents[idx].snake.update_dir(dt, desired);
+
+                let a = &mut agents[idx];
+                let (burned, drops) = a.mass_burn.step(&rules, a.score, boosting, dt);
+                if burned > 0 {
+                    a.score = (a.score - burned as f32).max(0.0);
+                    pellets.spawn_boost_drop(a.snake.tail_pos(), drops, a.snake.radius, rules.pellet_radius * 0.8);
+                }
             }
 
             if agents[0].alive {
                 last_player_pos = agents[0].snake.head_pos();
             }
 
-            check_arena_bounds(&agents, &mut scratch);
-            check_head_to_head(&agents, &mut scratch);
-            check_head_to_body(&agents, &mut scratch, heavy_mode);
+            check_arena_bounds(&agents, &mut scratch, rules.arena_radius);
+            check_collisions(&agents, &mut scratch, &rules);
+            for saved in absorb_shields(&mut agents, &mut scratch) {
+                if agents[saved].kind == AgentKind::Player {
+                    toast_text = "SHIELD SAVED YOU".to_owned();
+                    toast_left = 1.4;
+                }
+            }
 
-            if apply_deaths(&mut agents, &mut scratch, &mut pellets) {
+            let deaths = apply_deaths(&mut agents, &mut scratch, &mut pellets, &rules);
+            for (victim, death) in &deaths {
+                let killer = death.killer.map(|k| agents[k as usize].name.as_str());
+                push_kill_feed(
+                    &mut kill_feed,
+                    kill_feed_line(&agents[*victim].name, killer, death.cause),
+                );
+            }
+            if deaths.iter().any(|(i, _)| agents[*i].kind == AgentKind::Player) {
                 state = RunState::Spectating;
                 toast_left = 0.0;
                 timeadd_badge_left = 0.0;
@@ -627,130 +702,179 @@ pub async fn run() {
             }
 
             if state != RunState::Finished {
-                for i in 0..agents.len() {
-                    if !agents[i].alive {
+                let mut confusers: Vec<(usize, Vec2)> = Vec::new();
+                for (idx, a) in agents.iter_mut().enumerate() {
+                    if !a.alive {
                         continue;
                     }
 
                     let collected = tokens.collect_colliding_filtered(
-                        agents[i].snake.head_pos(),
-                        agents[i].snake.radius,
-                        |k| match k {
-                            TokenKind::Magnet => agents[i].magnet_left <= 0.0,
-                            TokenKind::SpeedUp => agents[i].speedup_left <= 0.0,
-                            TokenKind::TimeAdd => true,
+                        a.snake.prev_head(),
+                        a.snake.head_pos(),
+                        a.snake.radius,
+                        |k| match k.effect() {
+                            Some(effect) => a.effects.accepts(effect, rules.tokens.get(k)),
+                            None => true,
                         },
                     );
                     for k in collected {
                         match k {
-                            TokenKind::Magnet => {
-                                agents[i].magnet_left = TOKEN_DURATION_SEC;
-                                if agents[i].kind == AgentKind::Player {
-                                    toast_text = "MAGNET (10s)".to_owned();
-                                    toast_left = 1.2;
-                                }
-                            }
-                            TokenKind::SpeedUp => {
-                                agents[i].speedup_left = TOKEN_DURATION_SEC;
-                                if agents[i].kind == AgentKind::Player {
-                                    toast_text = "SPEED (10s)".to_owned();
+                            TokenKind::Confuse => {
+                                confusers.push((idx, a.snake.head_pos()));
+                                if a.kind == AgentKind::Player {
+                                    toast_text = "CONFUSE!".to_owned();
                                     toast_left = 1.2;
                                 }
                             }
                             TokenKind::TimeAdd => {
-                                time_left += TOKEN_TIME_ADD_SEC;
-                                time_added_total += TOKEN_TIME_ADD_SEC;
-                                if agents[i].kind == AgentKind::Player {
+                                time_left += rules.token_time_add_sec;
+                                time_added_total += rules.token_time_add_sec;
+                                if a.kind == AgentKind::Player {
                                     time_add_flash = 0.9;
                                     timeadd_badge_left = 1.4;
-                                    toast_text = "TIME +10s".to_owned();
+                                    toast_text = format!("TIME +{}s", rules.token_time_add_sec.round());
                                     toast_left = 1.2;
                                 }
                             }
+                            _ => {
+                                if let Some(effect) = k.effect() {
+                                    let rule = rules.tokens.get(k);
+                                    a.effects.grant(effect, rule);
+                                    if a.kind == AgentKind::Player {
+                                        toast_text = format!("{} ({}s)", token_label(k), rule.duration_sec.round());
+                                        toast_left = 1.2;
+                                    }
+                                }
+                            }
                         }
                     }
 
-                    let size_factor = (BASE_SNAKE_RADIUS / agents[i].snake.radius).clamp(0.25, 1.0);
-                    let pickup_bonus = if agents[i].magnet_left > 0.0 {
-                        MAGNET_PICKUP_BONUS_PX * size_factor
+                    let size_factor = (rules.base_snake_radius / a.snake.radius).clamp(0.25, 1.0);
+                    let magnet = a.effects.has(EffectKind::Magnet);
+                    let pickup_bonus = if magnet {
+                        rules.magnet_pickup_bonus_px * size_factor
                     } else {
                         0.0
                     };
 
-                    if agents[i].magnet_left > 0.0 {
-                        let attract_radius = MAGNET_ATTRACT_RADIUS * (0.55 + 0.45 * size_factor);
-                        let attract_speed = MAGNET_ATTRACT_SPEED * (0.75 + 0.25 * size_factor);
-                        let attract_max = ((MAGNET_ATTRACT_MAX_PER_FRAME as f32) * (0.35 + 0.65 * size_factor))
+                    if magnet {
+                        let attract_radius = rules.magnet_attract_radius * (0.55 + 0.45 * size_factor);
+                        let attract_speed = rules.magnet_attract_speed * (0.75 + 0.25 * size_factor);
+                        let attract_max = ((rules.magnet_attract_max_per_frame as f32) * (0.35 + 0.65 * size_factor))
                             .round()
-                            .clamp(40.0, MAGNET_ATTRACT_MAX_PER_FRAME as f32) as usize;
-                        pellets.apply_magnet(agents[i].snake.head_pos(), dt, attract_radius, attract_speed, attract_max);
+                            .max(40.0)
+                            .min(rules.magnet_attract_max_per_frame as f32) as usize;
+                        pellets.apply_magnet(a.snake.head_pos(), dt, attract_radius, attract_speed, attract_max);
                     }
 
-                    let max_eat = if agents[i].magnet_left > 0.0 {
-                        (PELLET_EAT_MAX_PER_FRAME / 2).max(4)
+                    let max_eat = if magnet {
+                        (rules.pellet_eat_max_per_frame / 2).max(4)
                     } else {
-                        PELLET_EAT_MAX_PER_FRAME
+                        rules.pellet_eat_max_per_frame
                     };
                     let gained = pellets.eat_colliding(
-                        agents[i].snake.head_pos(),
-                        agents[i].snake.radius,
+                        a.snake.prev_head(),
+                        a.snake.head_pos(),
+                        a.snake.radius,
                         pickup_bonus,
                         max_eat,
                     );
                     if gained != 0 {
-                        agents[i].score += gained as f32;
+                        a.score += (gained * a.effects.score_mult()) as f32;
                     }
 
-                    best_score = best_score.max(agents[i].score as i32);
+                    if spectator_demo || idx == 0 {
+                        best_score = best_score.max(a.score as i32);
+                    }
 
-                    let extra = ((agents[i].score as i32) / SCORE_PER_SEGMENT).max(0) as usize;
-                    agents[i].snake.target_length = (BASE_SNAKE_LENGTH + extra).clamp(BASE_SNAKE_LENGTH, 900);
+                    a.snake.target_length = rules.target_length(a.score as i32);
 
-                    let s = agents[i].score.max(0.0);
-                    let t = if s <= 0.0 { 0.0 } else { (s / (s + SNAKE_RADIUS_SCORE_HALF)).clamp(0.0, 1.0) };
-                    let target_radius = (BASE_SNAKE_RADIUS
-                        + (MAX_SNAKE_RADIUS - BASE_SNAKE_RADIUS) * t.powf(SNAKE_RADIUS_GROWTH_EXP))
-                        .clamp(BASE_SNAKE_RADIUS, MAX_SNAKE_RADIUS);
+                    let target_radius = rules.target_radius(a.score);
                     let smooth = 1.0 - (-8.0 * dt).exp();
-                    agents[i].snake.radius = agents[i].snake.radius + (target_radius - agents[i].snake.radius) * smooth;
+                    a.snake.radius = a.snake.radius + (target_radius - a.snake.radius) * smooth;
 
-                    let target_spacing = (agents[i].snake.radius * SNAKE_SPACING_MULT)
-                        .max((agents[i].snake.radius * 0.78).max(5.2))
-                        .min(SNAKE_SPACING_MAX);
-                    agents[i].snake.segment_spacing =
-                        agents[i].snake.segment_spacing + (target_spacing - agents[i].snake.segment_spacing) * smooth;
+                    let target_spacing = rules.target_spacing(a.snake.radius);
+                    a.snake.segment_spacing =
+                        a.snake.segment_spacing + (target_spacing - a.snake.segment_spacing) * smooth;
+                }
+
+                // Confuse tokens hit the other snakes (not teammates) around the picker.
+                let r2 = rules.confuse_radius * rules.confuse_radius;
+                for (picker, pos) in confusers {
+                    let picker_team = agents[picker].team;
+                    for (idx, a) in agents.iter_mut().enumerate() {
+                        if idx == picker
+                            || !a.alive
+                            || is_teammate(a.team, picker_team)
+                            || a.snake.head_pos().distance_squared(pos) > r2
+                        {
+                            continue;
+                        }
+                        a.effects.grant(EffectKind::Confused, &rules.tokens.confuse);
+                        if a.kind == AgentKind::Player {
+                            toast_text = "CONFUSED!".to_owned();
+                            toast_left = 1.2;
+                        }
+                    }
                 }
                 }
             }
         }
 
         if !net_mode && state != RunState::Finished {
-            if pellets.total() < PELLET_TARGET_COUNT {
-                pellets.refill_to(PELLET_TARGET_COUNT, PELLET_RADIUS);
+            if pellets.total() < rules.pellet_target_count {
+                pellets.refill_to(rules.pellet_target_count, rules.pellet_radius);
             }
 
-            if tokens.total() < TOKEN_TARGET_COUNT {
+            tokens.tick(dt);
+            if tokens.total() < rules.token_target_count {
                 tokens.refill_to_target();
             }
         }
 
         if let Some(net_agents) = net_agents.take() {
             agents = net_agents;
-            state = RunState::Running;
-            finish_reason = None;
-            if !agents.is_empty() && !agents[0].alive {
-                state = RunState::Spectating;
+            // Timed rooms stay finished at 0 until the room is left.
+            if endless || time_left > 0.0 {
+                state = RunState::Running;
+                finish_reason = None;
+                if net_spectator || (!agents.is_empty() && !agents[0].alive) {
+                    state = RunState::Spectating;
+                }
+            } else {
+                state = RunState::Finished;
+                finish_reason = Some(FinishReason::TimeUp);
             }
         }
 
         for ev in runtime::drain_events() {
             match ev.kind.as_str() {
                 "death" => {
-                    if let Some(local_id) = runtime::local_player_id() {
-                        if ev.id == local_id {
-                            toast_text = "YOU DIED".to_owned();
-                            toast_left = 1.5;
-                        }
+                    if let Some(local_id) = runtime::local_player_id() && ev.id == local_id {
+                        toast_text = "YOU DIED".to_owned();
+                        toast_left = 1.5;
+                        net_respawn_left = rules.respawn_delay_sec;
+                    }
+                    // Kills get their own "kill" event.
+                    if ev.killer.is_none() && let Some(cause) = ev.cause {
+                        push_kill_feed(&mut kill_feed, kill_feed_line(&net_player_name(ev.id), None, cause));
+                    }
+                }
+                "kill" => {
+                    if let Some(killer) = ev.killer {
+                        let line = kill_feed_line(
+                            &net_player_name(ev.id),
+                            Some(&net_player_name(killer)),
+                            ev.cause.unwrap_or(DeathCause::Body),
+                        );
+                        push_kill_feed(&mut kill_feed, line);
+                    }
+                }
+                "respawn" => {
+                    if let Some(local_id) = runtime::local_player_id() && ev.id == local_id {
+                        toast_text = "RESPAWNED".to_owned();
+                        toast_left = 1.2;
+                        net_respawn_left = 0.0;
                     }
                 }
                 "time_add" => {
@@ -760,20 +884,28 @@ pub async fn run() {
                     time_add_flash = 0.9;
                     timeadd_badge_left = 1.4;
                 }
-                "magnet" => {
-                    if let Some(local_id) = runtime::local_player_id() {
-                        if ev.id == local_id {
-                            net_magnet_left = TOKEN_DURATION_SEC;
-                        }
+                "shield_used" => {
+                    if let Some(local_id) = runtime::local_player_id() && ev.id == local_id {
+                        toast_text = "SHIELD SAVED YOU".to_owned();
+                        toast_left = 1.4;
                     }
                 }
-                "speedup" => {
-                    if let Some(local_id) = runtime::local_player_id() {
-                        if ev.id == local_id {
-                            net_speedup_left = TOKEN_DURATION_SEC;
-                        }
+                "confused" => {
+                    if let Some(local_id) = runtime::local_player_id() && ev.id == local_id {
+                        toast_text = "CONFUSED!".to_owned();
+                        toast_left = 1.2;
                     }
                 }
+                "team_win" => {
+                    let team = ev.id as u8;
+                    net_team_winner = Some(team);
+                    toast_text = if team == 0 {
+                        "DRAW".to_owned()
+                    } else {
+                        format!("{} TEAM WINS", team_name(team))
+                    };
+                    toast_left = 3.0;
+                }
                 "time_up" => {
                     state = RunState::Finished;
                     finish_reason = Some(FinishReason::TimeUp);
@@ -783,17 +915,83 @@ pub async fn run() {
         }
 
         if net_mode {
-            net_magnet_left = (net_magnet_left - dt).max(0.0);
-            net_speedup_left = (net_speedup_left - dt).max(0.0);
+            net_respawn_left = (net_respawn_left - dt).max(0.0);
+        }
+
+        // Chat on the countdown and results screens, and all the time in endless
+        // rooms (Enter or T, then Enter to send, Esc to cancel); emotes any time
+        // with 1-6 or the side buttons.
+        let typed: Vec<char> = std::iter::from_fn(get_char_pressed).collect();
+        let net_player = net_mode && !net_spectator;
+        let chat_open = net_player && (endless || countdown_left > 0.0 || state == RunState::Finished);
+        if !chat_open {
+            chat_draft = None;
+        } else if let Some(draft) = chat_draft.as_mut() {
+            for c in typed {
+                if !c.is_control() && draft.chars().count() < crate::state::chat::MAX_CHAT_LEN {
+                    draft.push(c);
+                }
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                draft.pop();
+            }
+            if is_key_pressed(KeyCode::Enter) {
+                let text = draft.trim().to_owned();
+                if !text.is_empty() {
+                    runtime::send_chat(text);
+                }
+                chat_draft = None;
+            } else if is_key_pressed(KeyCode::Escape) {
+                chat_draft = None;
+            }
+        } else if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::T) {
+            chat_draft = Some(String::new());
+        }
+        if net_player && chat_draft.is_none() {
+            let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6];
+            let click = is_mouse_button_pressed(MouseButton::Left).then(|| {
+                let (mx, my) = mouse_position();
+                vec2(mx, my) * input_scale
+            });
+            for (idx, key) in keys.iter().enumerate().take(EMOTES.len()) {
+                if is_key_pressed(*key) || click.is_some_and(|p| emote_button(idx).contains(p)) {
+                    runtime::send_emote(idx as u8);
+                }
+            }
         }
+        for (from, id) in runtime::take_emotes() {
+            emote_bubbles.insert(from, (id, EMOTE_BUBBLE_SEC));
+        }
+        emote_bubbles.retain(|_, bubble| {
+            bubble.1 -= dt;
+            bubble.1 > 0.0
+        });
 
         {
             let segs = agents[0].snake.segments();
             let fit_px = screen_width().min(screen_height()) * CAMERA_FIT_SCREEN_FRACTION;
 
-            if net_mode && !agents.is_empty() {
+            if net_mode && net_spectator {
+                let arena_extent_iso = ISO_SCALE * (2.0f32).sqrt() * rules.arena_radius;
+                let base_scale = fit_px / arena_extent_iso.max(1.0);
+                let target_scale = (base_scale * spectate_zoom).clamp(0.02, 6.0);
+                camera_scale = camera_scale + (target_scale - camera_scale) * (1.0 - (-6.0 * dt).exp());
+                if spectate_free || spectate_follow.is_none() {
+                    let pan_mult = if wants_boost { SPECTATE_PAN_BOOST_MULT } else { 1.0 };
+                    if desired_dir_world.length_squared() > 0.0001 {
+                        camera_center += desired_dir_world.normalize() * (SPECTATE_PAN_SPEED * pan_mult) * dt;
+                    }
+                    let max_r = rules.arena_radius * SPECTATE_CAMERA_CLAMP_MULT;
+                    let d = camera_center.length();
+                    if d > max_r {
+                        camera_center = camera_center / d * max_r;
+                    }
+                } else if !agents.is_empty() {
+                    camera_center = camera_center.lerp(agents[0].snake.head_pos(), 1.0 - (-8.0 * dt).exp());
+                }
+            } else if net_mode && !agents.is_empty() {
                 camera_center = agents[0].snake.head_pos();
-                let arena_extent_iso = ISO_SCALE * (2.0f32).sqrt() * ARENA_RADIUS;
+                let arena_extent_iso = ISO_SCALE * (2.0f32).sqrt() * rules.arena_radius;
                 let target_extent = arena_extent_iso.max(1.0);
                 let target_scale = (fit_px / target_extent).clamp(CAMERA_SCALE_MIN, CAMERA_SCALE_MAX);
                 camera_scale = camera_scale + (target_scale - camera_scale) * (1.0 - (-6.0 * dt).exp());
@@ -826,7 +1024,7 @@ pub async fn run() {
                 };
                 camera_scale = camera_scale + (target_scale - camera_scale) * zoom_smooth;
             } else {
-                let arena_extent_iso = ISO_SCALE * (2.0f32).sqrt() * ARENA_RADIUS;
+                let arena_extent_iso = ISO_SCALE * (2.0f32).sqrt() * rules.arena_radius;
                 let target_extent = arena_extent_iso.max(1.0);
                 let extent_smooth = 1.0 - (-1.8 * dt).exp();
                 camera_extent_smoothed = camera_extent_smoothed + (target_extent - camera_extent_smoothed) * extent_smooth;
@@ -841,7 +1039,7 @@ pub async fn run() {
                     camera_center += desired_dir_world.normalize() * (SPECTATE_PAN_SPEED * pan_mult) * dt;
                 }
 
-                let max_r = ARENA_RADIUS * SPECTATE_CAMERA_CLAMP_MULT;
+                let max_r = rules.arena_radius * SPECTATE_CAMERA_CLAMP_MULT;
                 let d = camera_center.length();
                 if d > max_r {
                     camera_center = camera_center / d * max_r;
@@ -871,19 +1069,12 @@ pub async fn run() {
 
         let w2s = |w: Vec2| world_to_screen(w, camera_center, screen_center, camera_scale);
 
-        {
-            let steps = 80;
-            let mut prev: Option<Vec2> = None;
-            for i in 0..=steps {
-                let t = i as f32 / steps as f32;
-                let a = t * std::f32::consts::TAU;
-                let p = arena_center + vec2(a.cos(), a.sin()) * ARENA_RADIUS;
-                let sp = w2s(p);
-                if let Some(pp) = prev {
-                    draw_line(pp.x, pp.y, sp.x, sp.y, 3.0, Color::from_rgba(255, 90, 90, 110));
-                }
-                prev = Some(sp);
+        draw_world_ring(&w2s, arena_center, rules.arena_radius, 3.0, Color::from_rgba(255, 90, 90, 110));
+        if royale {
+            if zone_next_radius < zone_radius - 1.0 {
+                draw_world_ring(&w2s, arena_center, zone_next_radius, 2.0, Color::from_rgba(255, 255, 255, 90));
             }
+            draw_world_ring(&w2s, arena_center, zone_radius, 5.0, Color::from_rgba(90, 170, 255, 200));
         }
 
         if !net_mode {
@@ -891,12 +1082,19 @@ pub async fn run() {
 
             tokens.draw_visible_aabb(min_w, max_w, w2s);
         } else {
-            for p in runtime::latest_pellets() {
+            let pellet_teams = runtime::latest_pellet_teams();
+            for (i, p) in runtime::latest_pellets().iter().enumerate() {
                 let sp = w2s(vec2(p.x, p.y));
-                draw_circle(sp.x, sp.y, PELLET_RADIUS, Color::from_rgba(255, 220, 180, 220));
+                let col = match pellet_teams.get(i).copied().unwrap_or(0) {
+                    0 => Color::from_rgba(255, 220, 180, 220),
+                    team => team_colors(team).0,
+                };
+                draw_circle(sp.x, sp.y, rules.pellet_radius, col);
             }
             for t in runtime::latest_tokens() {
-                if let Some(kind) = token_kind_from_str(&t.kind) {
+                if let Some(kind) = TokenKind::parse(&t.kind)
+                    && !token_blink_hidden(t.ttl)
+                {
                     let sp = w2s(vec2(t.pos.x, t.pos.y));
                     crate::game::food::draw_token_screen(sp, crate::game::food::token_radius(kind), kind);
                 }
@@ -909,7 +1107,7 @@ pub async fn run() {
             }
             if net_mode {
                 let base = a.color_head;
-                let trail = runtime::trail_for(agent_id_from_name(&a.name));
+                let trail = runtime::trail_for(a.id);
                 if trail.is_empty() {
                     let sp = w2s(a.snake.head_pos());
                     draw_circle(sp.x, sp.y, a.snake.radius * 1.25, Color::new(base.r, base.g, base.b, 0.18));
@@ -917,10 +1115,8 @@ pub async fn run() {
                 } else {
                     for (i, p) in trail.iter().enumerate() {
                         let sp = w2s(vec2(p.x, p.y));
-                        let is_head = i == 0;
-                        let col = if is_head { a.color_head } else { a.color_body };
-                        let r = if is_head { a.snake.radius } else { a.snake.radius * 0.86 };
-                        draw_circle(sp.x, sp.y, r, col);
+                        let r = if i == 0 { a.snake.radius } else { a.snake.radius * 0.86 };
+                        draw_skin_segment(sp, r, a, i, trail.len());
                     }
                 }
                 continue;
@@ -936,10 +1132,44 @@ pub async fn run() {
                     continue;
                 }
                 let sp = w2s(segs[i]);
-                let is_head = i == 0;
-                let base = if is_head { a.color_head } else { a.color_body };
+                let base = if i == 0 { a.color_head } else { a.color_body };
                 draw_circle(sp.x, sp.y, a.snake.radius * 1.35, Color::new(base.r, base.g, base.b, 0.18));
-                draw_circle(sp.x, sp.y, a.snake.radius, base);
+                draw_skin_segment(sp, a.snake.radius, a, i, segs.len());
+            }
+        }
+
+        for a in agents.iter().filter(|a| a.alive) {
+            let head = w2s(a.snake.head_pos());
+            let dir = (w2s(a.snake.head_pos() + a.snake.dir()) - head).normalize_or_zero();
+            draw_effects(head, dir, a.snake.radius, &a.effects);
+
+            let name_size = 16.0;
+            let dims = measure_text(&a.name, None, name_size as u16, 1.0);
+            draw_text(
+                &a.name,
+                head.x - dims.width * 0.5,
+                head.y - a.snake.radius - 10.0,
+                name_size,
+                Color::from_rgba(255, 255, 255, 170),
+            );
+
+            if net_mode && let Some((emote, left)) = emote_bubbles.get(&a.id) {
+                let label = EMOTES.get(*emote as usize).copied().unwrap_or("?");
+                let alpha = (left / 0.5).min(1.0);
+                let size = 22.0;
+                let dims = measure_text(label, None, size as u16, 1.0);
+                let bw = dims.width + 20.0;
+                let bh = 30.0;
+                let bx = head.x - bw * 0.5;
+                let by = head.y - a.snake.radius - 30.0 - bh;
+                draw_rectangle(bx, by, bw, bh, Color::new(1.0, 1.0, 1.0, 0.9 * alpha));
+                draw_triangle(
+                    vec2(head.x - 6.0, by + bh),
+                    vec2(head.x + 6.0, by + bh),
+                    vec2(head.x, by + bh + 8.0),
+                    Color::new(1.0, 1.0, 1.0, 0.9 * alpha),
+                );
+                draw_text(label, bx + 10.0, by + 22.0, size, Color::new(0.1, 0.1, 0.15, alpha));
             }
         }
 
@@ -958,62 +1188,87 @@ pub async fn run() {
         );
         y += 30.0 * ui_s;
 
-        let total_duration = MATCH_DURATION_SEC + time_added_total;
-        let ext_text = if time_added_total > 0.0 {
-            format!(" (+{}s)", time_added_total as i32)
-        } else {
-            String::new()
-        };
-        let time_color = if time_add_flash > 0.0 {
-            Color::from_rgba(255, 180, 220, 240)
+        if endless {
+            draw_text(
+                "ENDLESS ARENA",
+                left_x,
+                y,
+                24.0 * ui_s,
+                Color::from_rgba(255, 255, 255, 230),
+            );
+            y += 26.0 * ui_s;
         } else {
-            Color::from_rgba(255, 255, 255, 230)
-        };
-        draw_text(
-            &format!("Time: {:05.1}s{}", time_left.max(0.0), ext_text),
-            left_x,
-            y,
-            24.0 * ui_s,
-            time_color,
-        );
-        y += 26.0 * ui_s;
+            let total_duration = rules.match_duration_sec + time_added_total;
+            let ext_text = if time_added_total > 0.0 {
+                format!(" (+{}s)", time_added_total as i32)
+            } else {
+                String::new()
+            };
+            let time_color = if time_add_flash > 0.0 {
+                Color::from_rgba(255, 180, 220, 240)
+            } else {
+                Color::from_rgba(255, 255, 255, 230)
+            };
+            draw_text(
+                &format!("Time: {:05.1}s{}", time_left.max(0.0), ext_text),
+                left_x,
+                y,
+                24.0 * ui_s,
+                time_color,
+            );
+            y += 26.0 * ui_s;
 
-        {
-            let bar_x = left_x;
-            let bar_y = y;
-            let bar_w = 360.0 * ui_s;
-            let bar_h = 10.0 * ui_s;
-            let ratio = if total_duration > 0.0 {
-                (time_left / total_duration).clamp(0.0, 1.0)
+            {
+                let bar_x = left_x;
+                let bar_y = y;
+                let bar_w = 360.0 * ui_s;
+                let bar_h = 10.0 * ui_s;
+                let ratio = if total_duration > 0.0 {
+                    (time_left / total_duration).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                draw_rectangle(bar_x, bar_y, bar_w, bar_h, Color::from_rgba(255, 255, 255, 18));
+                draw_rectangle(bar_x, bar_y, bar_w * ratio, bar_h, Color::from_rgba(255, 120, 200, 120));
+                draw_rectangle_lines(bar_x, bar_y, bar_w, bar_h, 1.0 * ui_s, Color::from_rgba(255, 255, 255, 40));
+            }
+            y += (10.0 + 18.0) * ui_s;
+        }
+
+        if royale {
+            let outside = agents[0].alive && (agents[0].snake.head_pos() - arena_center).length() > zone_radius;
+            let (text, col) = if outside {
+                ("OUTSIDE THE ZONE!", Color::from_rgba(255, 80, 80, 240))
+            } else if zone_next_radius < zone_radius - 1.0 {
+                ("ZONE CLOSING", Color::from_rgba(255, 200, 90, 230))
             } else {
-                0.0
+                ("SAFE ZONE", Color::from_rgba(90, 170, 255, 200))
             };
-            draw_rectangle(bar_x, bar_y, bar_w, bar_h, Color::from_rgba(255, 255, 255, 18));
-            draw_rectangle(bar_x, bar_y, bar_w * ratio, bar_h, Color::from_rgba(255, 120, 200, 120));
-            draw_rectangle_lines(bar_x, bar_y, bar_w, bar_h, 1.0 * ui_s, Color::from_rgba(255, 255, 255, 40));
+            draw_text(text, left_x, y + 4.0 * ui_s, 22.0 * ui_s, col);
+            y += 28.0 * ui_s;
         }
-        y += (10.0 + 18.0) * ui_s;
 
         if agents[0].alive {
             {
                 let mut bx = left_x;
-                let by = y;
-                let magnet_left = if net_mode { net_magnet_left } else { agents[0].magnet_left };
-                let speedup_left = if net_mode { net_speedup_left } else { agents[0].speedup_left };
-                if magnet_left > 0.0 {
-                    draw_token_badge(bx, by, TokenKind::Magnet, magnet_left);
-                    bx += 140.0 * ui_s;
+                let mut by = y;
+                let mut badges: Vec<(TokenKind, f32)> =
+                    agents[0].effects.active().iter().map(|e| (e.kind.token(), e.left)).collect();
+                if timeadd_badge_left > 0.0 {
+                    badges.push((TokenKind::TimeAdd, timeadd_badge_left));
                 }
-                if speedup_left > 0.0 {
-                    draw_token_badge(bx, by, TokenKind::SpeedUp, speedup_left);
+                // Three badges per row.
+                for (i, (kind, left)) in badges.iter().enumerate() {
+                    if i > 0 && i % 3 == 0 {
+                        bx = left_x;
+                        by += 42.0 * ui_s;
+                    }
+                    draw_token_badge(bx, by, *kind, *left);
                     bx += 140.0 * ui_s;
                 }
-                if timeadd_badge_left > 0.0 {
-                    draw_token_badge(bx, by, TokenKind::TimeAdd, timeadd_badge_left);
-                }
 
-                if agents[0].magnet_left > 0.0 || agents[0].speedup_left > 0.0 || timeadd_badge_left > 0.0 {
-                    y += 42.0 * ui_s;
+                if !badges.is_empty() {
+                    y = by + 42.0 * ui_s;
                 }
             }
 
@@ -1036,7 +1291,7 @@ pub async fn run() {
                 let bar_y = y;
                 let bar_w = 360.0 * ui_s;
                 let bar_h = 14.0 * ui_s;
-                let energy_ratio = (agents[0].boost_energy / ENERGY_BAR_MAX).clamp(0.0, 1.0);
+                let energy_ratio = (agents[0].boost_energy / rules.boost_energy_max).clamp(0.0, 1.0);
                 draw_rectangle_lines(
                     bar_x,
                     bar_y,
@@ -1072,8 +1327,9 @@ pub async fn run() {
         }
 
         {
+            let team_rows = if teams { team_totals(&agents) } else { Vec::new() };
             let lb_w = 280.0 * ui_s;
-            let lb_h = 200.0 * ui_s;
+            let lb_h = (200.0 + 24.0 * team_rows.len() as f32) * ui_s;
             let lb_x = screen_width() - lb_w - 16.0 * ui_s;
             let lb_y = 16.0 * ui_s;
             draw_rectangle(lb_x, lb_y, lb_w, lb_h, Color::from_rgba(0, 0, 0, 85));
@@ -1087,16 +1343,29 @@ pub async fn run() {
                 .sort_by(|&i, &j| agents[j].score.partial_cmp(&agents[i].score).unwrap());
 
             let mut row_y = lb_y + 56.0 * ui_s;
+            for (team, total, alive) in &team_rows {
+                draw_text(
+                    &format!("{:<6} {:>2} alive {:>6}", team_name(*team), alive, total),
+                    lb_x + 12.0 * ui_s,
+                    row_y,
+                    20.0 * ui_s,
+                    team_colors(*team).0,
+                );
+                row_y += 24.0 * ui_s;
+            }
             for (rank, idx) in scratch.leaderboard_order.iter().copied().take(5).enumerate() {
                 let a = &agents[idx];
-                let name = if idx == 0 { "YOU" } else { &a.name };
-                let color = if a.alive {
-                    Color::from_rgba(255, 255, 255, 220)
-                } else {
-                    Color::from_rgba(255, 255, 255, 120)
+                let name = if idx == 0 && !net_spectator { "YOU" } else { &a.name };
+                let color = match (a.team, a.alive) {
+                    (0, true) => Color::from_rgba(255, 255, 255, 220),
+                    (0, false) => Color::from_rgba(255, 255, 255, 120),
+                    (team, alive) => {
+                        let c = team_colors(team).0;
+                        Color::new(c.r, c.g, c.b, if alive { 0.9 } else { 0.45 })
+                    }
                 };
                 draw_text(
-                    &format!("{:>2}. {:<6} {:>6}", rank + 1, name, a.score as i32),
+                    &format!("{:>2}. {:<6} {:>6} {:>2}K", rank + 1, name, a.score as i32, a.kills),
                     lb_x + 12.0 * ui_s,
                     row_y,
                     20.0 * ui_s,
@@ -1112,6 +1381,50 @@ pub async fn run() {
                 18.0 * ui_s,
                 Color::from_rgba(255, 255, 255, 160),
             );
+
+            for entry in kill_feed.iter_mut() {
+                entry.1 -= dt;
+            }
+            kill_feed.retain(|entry| entry.1 > 0.0);
+            // Right-aligned under the minimap.
+            let mut feed_y = mm_y.max(lb_y + lb_h) + mm_size + 28.0 * ui_s;
+            for (line, left) in &kill_feed {
+                let alpha = (left / 0.6).clamp(0.0, 1.0);
+                let size = 18.0 * ui_s;
+                let text_w = measure_text(line, None, size as u16, 1.0).width;
+                let feed_x = screen_width() - text_w - 16.0 * ui_s;
+                draw_text(line, feed_x, feed_y, size, Color::new(1.0, 0.85, 0.75, 0.9 * alpha));
+                feed_y += 22.0 * ui_s;
+            }
+        }
+
+        if endless {
+            let bt_w = 200.0 * ui_s;
+            let bt_h = mm_size;
+            let bt_x = mm_x - bt_w - 12.0 * ui_s;
+            let bt_y = mm_y;
+            draw_rectangle(bt_x, bt_y, bt_w, bt_h, Color::from_rgba(0, 0, 0, 70));
+            draw_rectangle_lines(bt_x, bt_y, bt_w, bt_h, 2.0 * ui_s, Color::from_rgba(255, 255, 255, 35));
+            draw_text("ALL-TIME LENGTH", bt_x + 10.0 * ui_s, bt_y + 22.0 * ui_s, 18.0 * ui_s, WHITE);
+
+            let local_id = runtime::local_player_id();
+            let mut row_y = bt_y + 46.0 * ui_s;
+            for (rank, entry) in runtime::latest_leaderboard().iter().take(5).enumerate() {
+                let color = if Some(entry.id) == local_id {
+                    Color::from_rgba(255, 220, 120, 230)
+                } else {
+                    Color::from_rgba(255, 255, 255, 200)
+                };
+                let name: String = entry.name.chars().take(8).collect();
+                draw_text(
+                    &format!("{}. {:<8} {:>4}", rank + 1, name, entry.length),
+                    bt_x + 10.0 * ui_s,
+                    row_y,
+                    17.0 * ui_s,
+                    color,
+                );
+                row_y += 22.0 * ui_s;
+            }
         }
 
         {
@@ -1121,6 +1434,12 @@ pub async fn run() {
 
             let r = mm_size * 0.46;
             draw_circle_lines(mm_center.x, mm_center.y, r, 2.0 * ui_s, Color::from_rgba(255, 90, 90, 120));
+            if royale {
+                let zr = r * zone_radius / rules.arena_radius;
+                let nr = r * zone_next_radius / rules.arena_radius;
+                draw_circle_lines(mm_center.x, mm_center.y, nr, 1.0 * ui_s, Color::from_rgba(255, 255, 255, 90));
+                draw_circle_lines(mm_center.x, mm_center.y, zr, 2.0 * ui_s, Color::from_rgba(90, 170, 255, 200));
+            }
 
             {
                 let mm_world_pos = if state == RunState::Spectating {
@@ -1128,7 +1447,7 @@ pub async fn run() {
                 } else {
                     last_player_pos
                 };
-                let rel = (mm_world_pos - arena_center) / ARENA_RADIUS;
+                let rel = (mm_world_pos - arena_center) / rules.arena_radius;
                 let dot = mm_center + rel * r;
                 draw_circle(dot.x, dot.y, 3.5 * ui_s, Color::from_rgba(255, 255, 255, 170));
             }
@@ -1137,10 +1456,13 @@ pub async fn run() {
                 if !a.alive {
                     continue;
                 }
-                let rel = (a.snake.head_pos() - arena_center) / ARENA_RADIUS;
+                let rel = (a.snake.head_pos() - arena_center) / rules.arena_radius;
                 let dot = mm_center + rel * r;
-                let col = if idx == 0 { YELLOW } else { a.color_head };
+                let col = if idx == 0 && a.team == 0 { YELLOW } else { a.color_head };
                 draw_circle(dot.x, dot.y, 4.5 * ui_s, col);
+                if idx == 0 && a.team != 0 {
+                    draw_circle_lines(dot.x, dot.y, 6.5 * ui_s, 1.5 * ui_s, WHITE);
+                }
             }
         }
 
@@ -1152,7 +1474,8 @@ pub async fn run() {
         }
 
         {
-            let c = if agents[0].alive && wants_boost && agents[0].boost_energy > 0.01 {
+            let can_boost = agents[0].boost_energy > 0.01 && rules.boost_allowed(agents[0].score);
+            let c = if agents[0].alive && wants_boost && can_boost {
                 Color::from_rgba(90, 210, 255, 65)
             } else {
                 Color::from_rgba(255, 255, 255, 20)
@@ -1196,7 +1519,34 @@ pub async fn run() {
             draw_text("-", zoom_minus_center.x - 8.0 * ui_s, zoom_minus_center.y + 12.0 * ui_s, 44.0 * ui_s, WHITE);
         }
 
-        if state == RunState::Spectating && !spectator_demo {
+        if state == RunState::Spectating && net_spectator {
+            draw_rectangle(follow_bar.x, follow_bar.y, follow_bar.w, follow_bar.h, Color::from_rgba(0, 0, 0, 110));
+            draw_rectangle_lines(follow_bar.x, follow_bar.y, follow_bar.w, follow_bar.h, 2.0 * ui_s, Color::from_rgba(255, 255, 255, 40));
+            let text_y = follow_bar.y + follow_bar.h * 0.5 + 12.0 * ui_s;
+            draw_text("<", follow_prev.x + 24.0 * ui_s, text_y, 40.0 * ui_s, WHITE);
+            draw_text(">", follow_next.x + 24.0 * ui_s, text_y, 40.0 * ui_s, WHITE);
+            let label = match spectate_follow.filter(|_| !spectate_free) {
+                Some(id) => format!("WATCHING {}", net_player_name(id)),
+                None => "FREE CAMERA".to_owned(),
+            };
+            let ml = measure_text(&label, None, (22.0 * ui_s).round() as u16, 1.0);
+            draw_text(
+                &label,
+                follow_bar.x + (follow_bar.w - ml.width) * 0.5,
+                text_y - 4.0 * ui_s,
+                22.0 * ui_s,
+                Color::from_rgba(255, 255, 255, 220),
+            );
+            let hint = "</>: switch player • F: free camera • +/-: zoom";
+            let mh = measure_text(hint, None, (18.0 * ui_s).round() as u16, 1.0);
+            draw_text(
+                hint,
+                (screen_width() - mh.width) * 0.5,
+                follow_bar.y - 10.0 * ui_s,
+                18.0 * ui_s,
+                Color::from_rgba(255, 255, 255, 150),
+            );
+        } else if state == RunState::Spectating && !spectator_demo {
             let w = 520.0 * ui_s;
             let h = 150.0 * ui_s;
             let x = (screen_width() - w) * 0.5;
@@ -1207,8 +1557,13 @@ pub async fn run() {
             let title = "YOU DIED";
             let mt = measure_text(title, None, (56.0 * ui_s).round() as u16, 1.0);
             draw_text(title, x + (w - mt.width) * 0.5, y + 62.0 * ui_s, 56.0 * ui_s, RED);
+            let status = if endless {
+                format!("RESPAWN IN  {:03.1}s", net_respawn_left)
+            } else {
+                format!("SPECTATING  {:04.1}s", time_left.max(0.0))
+            };
             draw_text(
-                &format!("SPECTATING  {:04.1}s", time_left.max(0.0)),
+                &status,
                 x + 26.0 * ui_s,
                 y + 108.0 * ui_s,
                 28.0 * ui_s,
@@ -1305,6 +1660,44 @@ pub async fn run() {
                         Color::from_rgba(255, 255, 255, 170),
                     );
                 }
+                _ if net_spectator => {
+                    let title = "MATCH OVER";
+                    let mt = measure_text(title, None, (56.0 * ui_s).round() as u16, 1.0);
+                    draw_text(
+                        title,
+                        screen_width() * 0.5 - mt.width * 0.5,
+                        screen_height() * 0.45,
+                        56.0 * ui_s,
+                        WHITE,
+                    );
+                }
+                _ if teams => {
+                    let winner = net_team_winner.unwrap_or(0);
+                    let title = if winner == 0 {
+                        "DRAW"
+                    } else if winner == agents[0].team {
+                        "WINNER"
+                    } else {
+                        "LOSER"
+                    };
+                    let mt = measure_text(title, None, (56.0 * ui_s).round() as u16, 1.0);
+                    draw_text(
+                        title,
+                        screen_width() * 0.5 - mt.width * 0.5,
+                        screen_height() * 0.45,
+                        56.0 * ui_s,
+                        WHITE,
+                    );
+                    let reason = format!("YOUR TEAM: {}", team_name(agents[0].team));
+                    let mr = measure_text(&reason, None, (20.0 * ui_s).round() as u16, 1.0);
+                    draw_text(
+                        &reason,
+                        screen_width() * 0.5 - mr.width * 0.5,
+                        screen_height() * 0.48,
+                        20.0 * ui_s,
+                        team_colors(agents[0].team).0,
+                    );
+                }
                 _ => {
                     let title = if player_is_winner { "WINNER" } else { "LOSER" };
                     let mt = measure_text(title, None, (56.0 * ui_s).round() as u16, 1.0);
@@ -1318,15 +1711,26 @@ pub async fn run() {
                 }
             }
 
+            if !net_spectator {
+                draw_text(
+                    &format!("Your score: {}", player_score),
+                    screen_width() * 0.5 - 112.0 * ui_s,
+                    screen_height() * 0.50,
+                    28.0 * ui_s,
+                    WHITE,
+                );
+            }
+            let winner_line = if teams {
+                let winner = net_team_winner.unwrap_or(0);
+                match team_totals(&agents).iter().find(|r| r.0 == winner) {
+                    Some((team, total, _)) => format!("Winner: {} team  ({})", team_name(*team), total),
+                    None => "Winner: draw".to_owned(),
+                }
+            } else {
+                format!("Winner: {}  ({})", winner_name, winner_score)
+            };
             draw_text(
-                &format!("Your score: {}", player_score),
-                screen_width() * 0.5 - 112.0 * ui_s,
-                screen_height() * 0.50,
-                28.0 * ui_s,
-                WHITE,
-            );
-            draw_text(
-                &format!("Winner: {}  ({})", winner_name, winner_score),
+                &winner_line,
                 screen_width() * 0.5 - 150.0 * ui_s,
                 screen_height() * 0.54,
                 24.0 * ui_s,
@@ -1346,22 +1750,77 @@ pub async fn run() {
                 26.0 * ui_s,
                 WHITE,
             );
+            let rank_line = match (global_rank, rank_polls_left) {
+                (Some(rank), _) => Some(format!("Global rank: #{}", rank)),
+                (None, Some(left)) if left > 0 => Some("Global rank: ...".to_owned()),
+                _ => None,
+            };
+            if let Some(line) = rank_line {
+                draw_text(
+                    &line,
+                    screen_width() * 0.5 - 100.0 * ui_s,
+                    screen_height() * 0.65,
+                    22.0 * ui_s,
+                    Color::from_rgba(90, 210, 255, 220),
+                );
+            }
+        }
+
+        if net_player {
+            for (idx, label) in EMOTES.iter().enumerate() {
+                let r = emote_button(idx);
+                draw_rectangle(r.x, r.y, r.w, r.h, Color::from_rgba(0, 0, 0, 90));
+                draw_rectangle_lines(r.x, r.y, r.w, r.h, 2.0 * ui_s, Color::from_rgba(255, 255, 255, 40));
+                let dims = measure_text(label, None, (18.0 * ui_s).round() as u16, 1.0);
+                draw_text(label, r.x + (r.w - dims.width) * 0.5, r.y + 26.0 * ui_s, 18.0 * ui_s, WHITE);
+            }
+        }
+
+        // Chat box: shown to everyone in the room while chat is open.
+        if net_mode && (endless || countdown_left > 0.0 || state == RunState::Finished) {
+            let lines = runtime::chat_lines();
+            let w = 520.0 * ui_s;
+            let line_h = 24.0 * ui_s;
+            let h = (lines.len().max(1) as f32 + 1.0) * line_h + 16.0 * ui_s;
+            let x = (screen_width() - w) * 0.5;
+            let y = screen_height() * 0.68;
+            draw_rectangle(x, y, w, h, Color::from_rgba(0, 0, 0, 120));
+            draw_rectangle_lines(x, y, w, h, 2.0 * ui_s, Color::from_rgba(255, 255, 255, 40));
+            let mut line_y = y + 24.0 * ui_s;
+            if lines.is_empty() {
+                draw_text("No messages yet.", x + 12.0 * ui_s, line_y, 18.0 * ui_s, Color::from_rgba(255, 255, 255, 120));
+                line_y += line_h;
+            }
+            for (name, text) in &lines {
+                let name_label = format!("{}:", name);
+                let dims = measure_text(&name_label, None, (18.0 * ui_s).round() as u16, 1.0);
+                draw_text(&name_label, x + 12.0 * ui_s, line_y, 18.0 * ui_s, Color::from_rgba(90, 210, 255, 230));
+                draw_text(text, x + 20.0 * ui_s + dims.width, line_y, 18.0 * ui_s, WHITE);
+                line_y += line_h;
+            }
+            let prompt = match &chat_draft {
+                Some(draft) => format!("> {}_", draft),
+                None if chat_open => "Enter: chat".to_owned(),
+                None => String::new(),
+            };
+            draw_text(&prompt, x + 12.0 * ui_s, line_y, 18.0 * ui_s, Color::from_rgba(255, 255, 255, 200));
         }
 
         if spectator_demo && state == RunState::Finished {
             demo_restart_left -= dt;
             if demo_restart_left <= 0.0 {
-                agents = make_initial_agents();
+                agents = make_initial_agents(&rules, crate::client::prefs::get().skin);
                 pellets.clear();
-                pellets.populate_random(PELLET_TARGET_COUNT, PELLET_RADIUS);
+                pellets.populate_random(rules.pellet_target_count, rules.pellet_radius);
                 tokens.clear();
                 tokens.populate_random();
-                time_left = MATCH_DURATION_SEC;
+                time_left = rules.match_duration_sec;
                 time_added_total = 0.0;
                 time_add_flash = 0.0;
                 best_score = 0;
                 toast_left = 0.0;
                 toast_text.clear();
+                kill_feed.clear();
                 state = RunState::Spectating;
                 finish_reason = None;
                 finished_winner_idx = None;
@@ -1377,18 +1836,49 @@ pub async fn run() {
             }
         }
 
-        if is_key_pressed(KeyCode::R) && state == RunState::Finished {
-            agents = make_initial_agents();
+        if state == RunState::Finished && !spectator_demo && best_score > saved_best_score {
+            saved_best_score = best_score;
+            let mut prefs = crate::client::prefs::get();
+            prefs.best_score = best_score;
+            if let Err(err) = crate::client::prefs::set(prefs) {
+                eprintln!("{}", err);
+            }
+        }
+        if net_mode && state == RunState::Finished && !spectator_demo && !net_spectator {
+            let left = *rank_polls_left.get_or_insert_with(|| {
+                rank_poll_in = RANK_POLL_SEC;
+                if crate::client::prefs::get().profile_id.is_empty() { 0 } else { RANK_POLLS }
+            });
+            rank_poll_in -= dt;
+            if left > 0 && rank_poll_in <= 0.0 {
+                rank_poll_in = RANK_POLL_SEC;
+                rank_polls_left = Some(left - 1);
+                master_api::request(MasterRequest::FetchProfile(crate::client::prefs::get().profile_id));
+            }
+            // The menu is closed, so its master replies land here.
+            while let Some(reply) = master_api::poll_reply() {
+                if let MasterReply::Profile(Ok(Some(profile))) = reply {
+                    global_rank = profile.global_rank;
+                }
+            }
+        }
+        if is_key_pressed(KeyCode::R) && state == RunState::Finished && chat_draft.is_none() {
+            global_rank = None;
+            rank_polls_left = None;
+            agents = make_initial_agents(&rules, crate::client::prefs::get().skin);
             pellets.clear();
-            pellets.populate_random(PELLET_TARGET_COUNT, PELLET_RADIUS);
+            pellets.populate_random(rules.pellet_target_count, rules.pellet_radius);
             tokens.clear();
             tokens.populate_random();
-            time_left = MATCH_DURATION_SEC;
+            time_left = rules.match_duration_sec;
             time_added_total = 0.0;
             time_add_flash = 0.0;
-            best_score = 0;
+            if spectator_demo {
+                best_score = 0;
+            }
             toast_left = 0.0;
             toast_text.clear();
+            kill_feed.clear();
             state = if spectator_demo { RunState::Spectating } else { RunState::Running };
             finish_reason = None;
             finished_winner_idx = None;
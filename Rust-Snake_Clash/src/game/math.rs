@@ -32,6 +32,17 @@ pub fn screen_vec_to_world_dir(screen_vec: Vec2, camera_scale: f32) -> Vec2 {
     }
 }
 
+// Squared distance from `p` to the segment `a`-`b` (swept collision tests).
+pub fn dist_sq_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let len2 = ab.length_squared();
+    if len2 <= 0.000001 {
+        return p.distance_squared(a);
+    }
+    let t = ((p - a).dot(ab) / len2).clamp(0.0, 1.0);
+    p.distance_squared(a + ab * t)
+}
+
 pub fn point_in_circle(p: Vec2, center: Vec2, radius: f32) -> bool {
     p.distance_squared(center) <= radius * radius
 }
@@ -1,8 +1,15 @@
 pub mod r#loop;
+pub mod bot;
+pub mod bot_profile;
 pub mod world;
 pub mod snake;
 pub mod snake_sim;
 pub mod food;
 pub mod collision;
+pub mod effects;
+pub mod skin;
+pub mod body_grid;
 pub mod math;
+pub mod mode;
 pub mod sim;
+pub mod zone;
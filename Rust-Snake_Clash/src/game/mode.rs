@@ -0,0 +1,100 @@
+// Room game modes. Mode-specific tuning (respawn delay, team count, ...) lives in GameRules.
+
+use macroquad::prelude::Color;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    // One timed match (countdown, then `match_duration_sec`).
+    #[default]
+    Classic,
+    // Slither-like: never ends, dead players respawn.
+    Endless,
+    // Timed match, players split into `team_count` teams; best team total wins.
+    Teams,
+    // Timed match in a safe zone that shrinks on `zone_phases`.
+    Royale,
+}
+
+pub const MODE_NAMES: &[&str] = &["classic", "endless", "teams", "royale"];
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::Endless,
+        GameMode::Teams,
+        GameMode::Royale,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "classic" => Some(Self::Classic),
+            "endless" | "ffa" => Some(Self::Endless),
+            "teams" | "team" => Some(Self::Teams),
+            "royale" | "battle_royale" | "br" => Some(Self::Royale),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Endless => "endless",
+            Self::Teams => "teams",
+            Self::Royale => "royale",
+        }
+    }
+
+    pub fn is_timed(self) -> bool {
+        matches!(self, Self::Classic | Self::Teams | Self::Royale)
+    }
+
+    pub fn respawns(self) -> bool {
+        self == Self::Endless
+    }
+
+    pub fn has_teams(self) -> bool {
+        self == Self::Teams
+    }
+
+    pub fn has_zone(self) -> bool {
+        self == Self::Royale
+    }
+
+    // Next mode in ALL (menu toggle).
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+// ---- Teams ----
+// Team ids are 1..=MAX_TEAMS; 0 means "no team".
+
+pub const MAX_TEAMS: u8 = 4;
+
+pub fn is_teammate(a: u8, b: u8) -> bool {
+    a != 0 && a == b
+}
+
+pub fn team_name(team: u8) -> &'static str {
+    match team {
+        1 => "RED",
+        2 => "BLUE",
+        3 => "GREEN",
+        4 => "GOLD",
+        _ => "-",
+    }
+}
+
+// (head, body) colors of a team.
+pub fn team_colors(team: u8) -> (Color, Color) {
+    match team {
+        1 => (Color::from_rgba(255, 110, 100, 255), Color::from_rgba(230, 70, 60, 255)),
+        2 => (Color::from_rgba(110, 170, 255, 255), Color::from_rgba(70, 130, 240, 255)),
+        3 => (Color::from_rgba(140, 240, 120, 255), Color::from_rgba(90, 200, 80, 255)),
+        4 => (Color::from_rgba(255, 215, 90, 255), Color::from_rgba(240, 180, 50, 255)),
+        _ => (Color::from_rgba(220, 220, 255, 255), Color::from_rgba(180, 180, 240, 255)),
+    }
+}
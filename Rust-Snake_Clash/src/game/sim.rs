@@ -1,3 +1,7 @@
+use macroquad::prelude::*;
+
+use crate::config::rules::GameRules;
+
 #[derive(Debug, Default)]
 pub struct WorldState {
     pub server_tick: u32,
@@ -8,3 +12,47 @@ impl WorldState {
         self.server_tick = self.server_tick.saturating_add(1);
     }
 }
+
+// What a snake wants this tick (from a client, or a server-side bot).
+#[derive(Clone, Copy)]
+pub struct InputState {
+    pub dir: Vec2,
+    pub boost: bool,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            dir: vec2(0.0, 0.0),
+            boost: false,
+        }
+    }
+}
+
+// Score burned by boosting and size decay (`GameRules::boost_score_cost_per_sec`,
+// `size_decay_per_sec`), carried over between ticks like the zone damage.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MassBurn {
+    debt: f32,
+    drop_debt: f32,
+}
+
+impl MassBurn {
+    // Returns (points lost, value-1 pellets to drop behind the tail) for this tick.
+    pub fn step(&mut self, rules: &GameRules, score: f32, boosting: bool, dt: f32) -> (i32, usize) {
+        let mut rate = 0.0;
+        if boosting {
+            rate += rules.boost_score_cost_per_sec;
+            self.drop_debt += rules.boost_score_cost_per_sec * rules.boost_drop_frac * dt;
+        } else if score > rules.size_decay_min_score as f32 {
+            rate += (score - rules.size_decay_min_score as f32) * rules.size_decay_per_sec;
+        }
+        self.debt += rate * dt;
+
+        let lost = self.debt.floor().min(score.max(0.0));
+        self.debt -= self.debt.floor();
+        let drops = self.drop_debt.floor().min(lost);
+        self.drop_debt -= self.drop_debt.floor();
+        (lost as i32, drops as usize)
+    }
+}
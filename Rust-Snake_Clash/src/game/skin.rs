@@ -0,0 +1,125 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Player look: a palette color and an optional body pattern. Chosen by the
+// client (`JoinReq`), stored by the server and sent to everyone (`PlayerInfo`).
+// Team rooms keep the team colors; only the pattern applies there.
+
+pub const MAX_NAME_LEN: usize = 16;
+
+// (head, body) colors, indexed by `Skin::color`.
+pub const SKIN_PALETTE: [(Color, Color); 8] = [
+    (Color::from_rgba(255, 140, 90, 255), Color::from_rgba(255, 120, 60, 255)),
+    (Color::from_rgba(110, 220, 255, 255), Color::from_rgba(80, 180, 240, 255)),
+    (Color::from_rgba(170, 255, 130, 255), Color::from_rgba(120, 220, 90, 255)),
+    (Color::from_rgba(255, 120, 200, 255), Color::from_rgba(220, 90, 180, 255)),
+    (Color::from_rgba(220, 220, 255, 255), Color::from_rgba(180, 180, 240, 255)),
+    (Color::from_rgba(255, 210, 120, 255), Color::from_rgba(255, 190, 80, 255)),
+    (YELLOW, ORANGE),
+    (Color::from_rgba(190, 140, 255, 255), Color::from_rgba(150, 100, 240, 255)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkinPattern {
+    #[default]
+    Solid,
+    // Every third segment lighter.
+    Stripes,
+    // Dark dot on every other segment.
+    Dots,
+    // Body fades toward the tail.
+    Gradient,
+}
+
+impl SkinPattern {
+    pub const ALL: [SkinPattern; 4] = [
+        SkinPattern::Solid,
+        SkinPattern::Stripes,
+        SkinPattern::Dots,
+        SkinPattern::Gradient,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "solid" | "" => Some(Self::Solid),
+            "stripes" => Some(Self::Stripes),
+            "dots" => Some(Self::Dots),
+            "gradient" => Some(Self::Gradient),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Skin {
+    // Index in `SKIN_PALETTE` (wraps around).
+    pub color: u8,
+    pub pattern: SkinPattern,
+}
+
+impl Skin {
+    // Server and solo bots: color by id, a pattern every few bots.
+    pub fn for_bot(id: u32) -> Self {
+        let id = id as usize;
+        Self {
+            color: (id % SKIN_PALETTE.len()) as u8,
+            pattern: SkinPattern::ALL[(id / 3) % SkinPattern::ALL.len()],
+        }
+    }
+
+    pub fn colors(self) -> (Color, Color) {
+        SKIN_PALETTE[self.color as usize % SKIN_PALETTE.len()]
+    }
+
+    // Color of segment `index` (0 = head) out of `count`, from the resolved
+    // (head, body) colors. The second value asks for a pattern dot.
+    pub fn segment_color(self, head: Color, body: Color, index: usize, count: usize) -> (Color, bool) {
+        if index == 0 {
+            return (head, false);
+        }
+        match self.pattern {
+            SkinPattern::Solid => (body, false),
+            SkinPattern::Stripes if index.is_multiple_of(3) => (lighten(body, 0.35), false),
+            SkinPattern::Stripes => (body, false),
+            SkinPattern::Dots => (body, index.is_multiple_of(2)),
+            SkinPattern::Gradient => {
+                let t = index as f32 / count.max(1) as f32;
+                (lighten(body, -0.45 * t), false)
+            }
+        }
+    }
+}
+
+// Positive `amount` mixes toward white, negative toward black.
+fn lighten(c: Color, amount: f32) -> Color {
+    let target = if amount >= 0.0 { 1.0 } else { 0.0 };
+    let k = amount.abs().min(1.0);
+    Color::new(
+        c.r + (target - c.r) * k,
+        c.g + (target - c.g) * k,
+        c.b + (target - c.b) * k,
+        c.a,
+    )
+}
+
+// Display name from a client: printable characters only, single spaces, at
+// most `MAX_NAME_LEN` characters. Empty names get `fallback`.
+pub fn sanitize_name(raw: &str, fallback: &str) -> String {
+    let mut out = String::new();
+    for c in raw.chars() {
+        if out.chars().count() >= MAX_NAME_LEN {
+            break;
+        }
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+        } else if c.is_alphanumeric() || "-_.!?'#".contains(c) {
+            out.push(c);
+        }
+    }
+    let out = out.trim_end().to_owned();
+    if out.is_empty() { fallback.to_owned() } else { out }
+}
@@ -2,8 +2,12 @@ use std::collections::VecDeque;
 
 use macroquad::prelude::*;
 
+use crate::config::rules::GameRules;
+
 pub struct SnakeSim {
     pub head: Vec2,
+    // Head before the last `update_dir`: collisions sweep from here to `head`.
+    prev_head: Vec2,
     dir: Vec2,
 
     pub speed: f32,
@@ -12,6 +16,7 @@ pub struct SnakeSim {
     pub segment_spacing: f32,
     pub radius: f32,
     pub target_length: usize,
+    pub trail_sample_min_dist: f32,
 
     trail: VecDeque<Vec2>,
     segments: Vec<Vec2>,
@@ -20,19 +25,19 @@ pub struct SnakeSim {
 impl SnakeSim {
     #[allow(dead_code)]
     pub fn new() -> Self {
-        Self::new_at(vec2(0.0, 0.0), vec2(1.0, 0.0))
+        Self::new_at(vec2(0.0, 0.0), vec2(1.0, 0.0), &GameRules::default())
     }
 
-    pub fn new_at(head: Vec2, dir: Vec2) -> Self {
+    pub fn new_at(head: Vec2, dir: Vec2, rules: &GameRules) -> Self {
         let dir = if dir.length_squared() > 0.0001 {
             dir.normalize()
         } else {
             vec2(1.0, 0.0)
         };
 
-        let target_length = crate::config::BASE_SNAKE_LENGTH;
-        let segment_spacing = crate::config::BASE_SNAKE_SPACING;
-        let radius = crate::config::BASE_SNAKE_RADIUS;
+        let target_length = rules.base_snake_length;
+        let segment_spacing = rules.base_snake_spacing;
+        let radius = rules.base_snake_radius;
 
         let mut trail = VecDeque::new();
         trail.push_front(head);
@@ -41,12 +46,14 @@ impl SnakeSim {
 
         Self {
             head,
+            prev_head: head,
             dir,
-            speed: crate::config::BASE_SPEED,
+            speed: rules.base_speed,
             turn_rate: 10.0,
             segment_spacing,
             radius,
             target_length,
+            trail_sample_min_dist: rules.trail_sample_min_dist,
             trail,
             segments,
         }
@@ -57,20 +64,32 @@ impl SnakeSim {
         *self = Self::new();
     }
 
-    #[allow(dead_code)]
+    // Restart the trail at `head`, keeping the current size/tuning.
     pub fn reset_at(&mut self, head: Vec2, dir: Vec2) {
-        *self = Self::new_at(head, dir);
+        self.head = head;
+        self.prev_head = head;
+        self.dir = if dir.length_squared() > 0.0001 {
+            dir.normalize()
+        } else {
+            vec2(1.0, 0.0)
+        };
+        self.trail.clear();
+        self.trail.push_front(head);
+        self.segments = vec![head; self.target_length];
     }
 
     pub fn head_pos(&self) -> Vec2 {
         self.head
     }
 
+    pub fn prev_head(&self) -> Vec2 {
+        self.prev_head
+    }
+
     pub fn dir(&self) -> Vec2 {
         self.dir
     }
 
-    #[allow(dead_code)]
     pub fn tail_pos(&self) -> Vec2 {
         *self.segments.last().unwrap_or(&self.head)
     }
@@ -79,6 +98,19 @@ impl SnakeSim {
         &self.segments
     }
 
+    // Whether the path behind the head is at least `distance` long (a fresh or
+    // reset snake has all its segments stacked on the head).
+    pub fn trail_reaches(&self, distance: f32) -> bool {
+        let mut acc = 0.0;
+        for (a, b) in self.trail.iter().zip(self.trail.iter().skip(1)) {
+            acc += a.distance(*b);
+            if acc >= distance {
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn update_dir(&mut self, dt: f32, desired_dir_world: Vec2) {
         if desired_dir_world.length_squared() > 0.0001 {
             let desired_dir = desired_dir_world.normalize();
@@ -89,9 +121,10 @@ impl SnakeSim {
             }
         }
 
+        self.prev_head = self.head;
         self.head += self.dir * self.speed * dt;
 
-        let min_sample = crate::config::TRAIL_SAMPLE_MIN_DIST;
+        let min_sample = self.trail_sample_min_dist;
         let should_push = self
             .trail
             .front()
@@ -1,7 +1,14 @@
 use macroquad::prelude::*;
 use macroquad::rand::gen_range;
 
-use crate::config::{ARENA_RADIUS, BOOST_ENERGY_MAX, DEMO_BOT_COUNT};
+use crate::config::rules::GameRules;
+use crate::game::body_grid::BodyGrid;
+use crate::game::bot::BotController;
+use crate::game::bot_profile::BotProfile;
+use crate::game::collision::Death;
+use crate::game::effects::Effects;
+use crate::game::skin::Skin;
+use crate::game::sim::MassBurn;
 use crate::game::snake_sim::SnakeSim;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -25,10 +32,16 @@ pub enum AgentKind {
 }
 
 pub struct Agent {
+    // Agent index (solo) or player id (server).
+    pub id: u32,
     pub kind: AgentKind,
     pub name: String,
+    // Colors resolved from the skin (or the team); the skin adds the pattern.
     pub color_head: Color,
     pub color_body: Color,
+    pub skin: Skin,
+    // Team id in team rooms (see game::mode), 0 otherwise.
+    pub team: u8,
 
     pub snake: SnakeSim,
     pub alive: bool,
@@ -38,18 +51,18 @@ pub struct Agent {
     pub score: f32,
     pub boost_energy: f32,
 
-    pub magnet_left: f32,
-    pub speedup_left: f32,
+    pub effects: Effects,
+    pub kills: u32,
+    pub mass_burn: MassBurn,
 
-    // Bot state
-    pub bot_dir: Vec2,
-    pub bot_boost_intent: f32,
-    pub bot_hunt_target: Option<usize>,
-    pub bot_hunt_left: f32,
+    pub bot: BotController,
 }
 
 #[derive(Clone, Copy)]
 pub struct AgentSnapshot {
+    // Agent index (solo) or player id (server).
+    pub id: u32,
+    pub team: u8,
     pub alive: bool,
     pub head: Vec2,
     pub radius: f32,
@@ -57,8 +70,10 @@ pub struct AgentSnapshot {
 
 pub struct FrameScratch {
     pub agents_snapshot: Vec<AgentSnapshot>,
-    pub to_die: Vec<bool>,
+    // Set when the agent dies this frame (first cause found wins).
+    pub to_die: Vec<Option<Death>>,
     pub leaderboard_order: Vec<usize>,
+    pub body_grid: BodyGrid,
 }
 
 impl FrameScratch {
@@ -67,6 +82,7 @@ impl FrameScratch {
             agents_snapshot: Vec::new(),
             to_die: Vec::new(),
             leaderboard_order: Vec::new(),
+            body_grid: BodyGrid::default(),
         }
     }
 
@@ -75,9 +91,9 @@ impl FrameScratch {
         self.agents_snapshot.reserve(agents_len);
 
         if self.to_die.len() != agents_len {
-            self.to_die.resize(agents_len, false);
+            self.to_die.resize(agents_len, None);
         }
-        self.to_die.fill(false);
+        self.to_die.fill(None);
 
         self.leaderboard_order.clear();
         self.leaderboard_order.reserve(agents_len);
@@ -95,80 +111,80 @@ pub fn random_unit_dir() -> Vec2 {
     vec2(a.cos(), a.sin())
 }
 
-fn pick_spawn_pos(agents: &[Agent], arena_radius: f32) -> Vec2 {
+// Rejection sampling: first random point at least `clearance` away from every
+// occupied point (heads, body samples), else a plain random point.
+pub fn pick_safe_spawn(occupied: &[Vec2], arena_radius: f32, clearance: f32) -> Vec2 {
+    let clearance_sq = clearance * clearance;
     for _ in 0..30 {
         let p = random_pos_in_disk(arena_radius * 0.70);
-        let mut ok = true;
-        for a in agents {
-            if a.alive && a.snake.head_pos().distance_squared(p) < 260.0 * 260.0 {
-                ok = false;
-                break;
-            }
-        }
-        if ok {
+        if occupied.iter().all(|o| o.distance_squared(p) >= clearance_sq) {
             return p;
         }
     }
     random_pos_in_disk(arena_radius * 0.60)
 }
 
-pub fn make_initial_agents() -> Vec<Agent> {
+fn pick_spawn_pos(agents: &[Agent], arena_radius: f32) -> Vec2 {
+    let heads: Vec<Vec2> = agents
+        .iter()
+        .filter(|a| a.alive)
+        .map(|a| a.snake.head_pos())
+        .collect();
+    pick_safe_spawn(&heads, arena_radius, 260.0)
+}
+
+pub fn make_initial_agents(rules: &GameRules, player_skin: Skin) -> Vec<Agent> {
     let mut agents: Vec<Agent> = Vec::new();
 
     let spectator_demo = cfg!(feature = "demo100");
-    let heavy_mode = cfg!(feature = "demo100") || cfg!(feature = "demo_play100");
-    let bot_count: usize = if heavy_mode { DEMO_BOT_COUNT } else { 5 };
+    let bot_count = rules.bot_count;
 
     if !spectator_demo {
         let player_spawn = vec2(0.0, 0.0);
+        let (head, body) = player_skin.colors();
         agents.push(Agent {
+            id: 0,
             kind: AgentKind::Player,
             name: "YOU".to_owned(),
-            color_head: YELLOW,
-            color_body: ORANGE,
-            snake: SnakeSim::new_at(player_spawn, vec2(1.0, 0.0)),
+            color_head: head,
+            color_body: body,
+            skin: player_skin,
+            team: 0,
+            snake: SnakeSim::new_at(player_spawn, vec2(1.0, 0.0), rules),
             alive: true,
             respawn_left: 0.0,
             score: 0.0,
-            boost_energy: BOOST_ENERGY_MAX,
-            magnet_left: 0.0,
-            speedup_left: 0.0,
-            bot_dir: vec2(1.0, 0.0),
-            bot_boost_intent: 0.0,
-            bot_hunt_target: None,
-            bot_hunt_left: 0.0,
+            boost_energy: rules.boost_energy_max,
+            effects: Effects::default(),
+            kills: 0,
+            mass_burn: MassBurn::default(),
+            bot: BotController::new(vec2(1.0, 0.0), BotProfile::default()),
         });
     }
 
-    let palette = [
-        (Color::from_rgba(255, 140, 90, 255), Color::from_rgba(255, 120, 60, 255)),
-        (Color::from_rgba(110, 220, 255, 255), Color::from_rgba(80, 180, 240, 255)),
-        (Color::from_rgba(170, 255, 130, 255), Color::from_rgba(120, 220, 90, 255)),
-        (Color::from_rgba(255, 120, 200, 255), Color::from_rgba(220, 90, 180, 255)),
-        (Color::from_rgba(220, 220, 255, 255), Color::from_rgba(180, 180, 240, 255)),
-        (Color::from_rgba(255, 210, 120, 255), Color::from_rgba(255, 190, 80, 255)),
-    ];
-
     for i in 0..bot_count {
-        let spawn = pick_spawn_pos(&agents, ARENA_RADIUS);
+        let spawn = pick_spawn_pos(&agents, rules.arena_radius);
         let dir = random_unit_dir();
-        let (head, body) = palette[i % palette.len()];
+        let id = agents.len() as u32;
+        let skin = Skin::for_bot(i as u32);
+        let (head, body) = skin.colors();
         agents.push(Agent {
+            id,
             kind: AgentKind::Bot,
             name: format!("BOT{}", i + 1),
             color_head: head,
             color_body: body,
-            snake: SnakeSim::new_at(spawn, dir),
+            skin,
+            team: 0,
+            snake: SnakeSim::new_at(spawn, dir, rules),
             alive: true,
             respawn_left: 0.0,
             score: 0.0,
-            boost_energy: BOOST_ENERGY_MAX,
-            magnet_left: 0.0,
-            speedup_left: 0.0,
-            bot_dir: dir,
-            bot_boost_intent: 0.0,
-            bot_hunt_target: None,
-            bot_hunt_left: 0.0,
+            boost_energy: rules.boost_energy_max,
+            effects: Effects::default(),
+            kills: 0,
+            mass_burn: MassBurn::default(),
+            bot: BotController::new(dir, rules.bot_profile(i)),
         });
     }
 
@@ -0,0 +1,54 @@
+// Battle-royale safe zone: starts at the arena radius and shrinks following
+// `GameRules::zone_phases` (wait, then shrink to a fraction of the arena).
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZonePhase {
+    // Seconds the zone holds before this shrink starts.
+    pub wait_sec: f32,
+    pub shrink_sec: f32,
+    // Radius at the end of the shrink, as a fraction of `arena_radius`.
+    pub radius_frac: f32,
+}
+
+pub struct Zone {
+    arena_radius: f32,
+    phases: Vec<ZonePhase>,
+    elapsed: f32,
+}
+
+impl Zone {
+    pub fn new(arena_radius: f32, phases: Vec<ZonePhase>) -> Self {
+        Self {
+            arena_radius,
+            phases,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    // (current radius, radius the zone is heading to)
+    pub fn radii(&self) -> (f32, f32) {
+        let mut t = self.elapsed;
+        let mut from = self.arena_radius;
+        for phase in &self.phases {
+            let to = self.arena_radius * phase.radius_frac.clamp(0.0, 1.0);
+            if t < phase.wait_sec {
+                return (from, to);
+            }
+            t -= phase.wait_sec;
+            if t < phase.shrink_sec {
+                let k = t / phase.shrink_sec;
+                return (from + (to - from) * k, to);
+            }
+            t -= phase.shrink_sec;
+            from = to;
+        }
+        (from, from)
+    }
+}
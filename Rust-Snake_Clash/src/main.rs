@@ -1,5 +1,6 @@
 use macroquad::prelude::*;
 
+mod cli;
 mod config;
 mod client;
 mod master;
@@ -7,9 +8,12 @@ mod net;
 mod state;
 mod util;
 mod game;
+mod tournament;
 
+use config::rules::GameRules;
 use net::dispatcher::DispatcherHandle;
 use net::ws::WsServer;
+use state::chat;
 use state::lobby::Lobby;
 
 fn window_conf() -> Conf {
@@ -23,30 +27,102 @@ fn window_conf() -> Conf {
 }
 
 fn main() {
-    let mut args = std::env::args().skip(1);
-    match args.next().as_deref() {
-        Some("server") => {
-            let lobby = Lobby::new();
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        cli::Command::Server(settings) => {
+            if let Err(err) =
+                GameRules::resolve(&settings.server.rules, settings.server.rules_dir.as_deref())
+            {
+                eprintln!("error: {}", err);
+                std::process::exit(2);
+            }
+            let chat_filter = match chat::load_blocklist(&settings.server.chat_blocklist_path) {
+                Ok(filter) => filter,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    std::process::exit(2);
+                }
+            };
+            let mut lobby = Lobby::new(&settings.server);
+            lobby.set_chat_filter(chat_filter);
             let dispatcher = DispatcherHandle::new(lobby);
             let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
-            let _ = rt.block_on(async {
-                tokio::spawn(async {
-                    let _ = master::serve("0.0.0.0:9100").await;
-                });
-                let _ = WsServer::serve("0.0.0.0:9001", dispatcher).await;
+            rt.block_on(async {
+                if settings.server.embed_master {
+                    let master_settings = settings.master.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = master::serve(master_settings).await {
+                            eprintln!("master: {}", err);
+                        }
+                    });
+                }
+                net::heartbeat::spawn(&settings.server, dispatcher.clone());
+                if let Err(err) =
+                    WsServer::serve(&settings.server.bind, dispatcher, settings.server.tick_rate).await
+                {
+                    eprintln!("server: {}", err);
+                }
             });
         }
-        Some("master") => {
+        cli::Command::Master(settings) => {
             let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
-            let _ = rt.block_on(async {
-                let _ = master::serve("0.0.0.0:9100").await;
+            rt.block_on(async {
+                if let Err(err) = master::serve(settings.master).await {
+                    eprintln!("master: {}", err);
+                }
             });
         }
-        Some("client") | None => {
-            macroquad::Window::from_config(window_conf(), game::r#loop::run());
+        cli::Command::Client(settings) => {
+            let rules = if settings.client.rules.trim().is_empty() {
+                GameRules::build_default()
+            } else {
+                match GameRules::resolve(&settings.client.rules, None) {
+                    Ok(rules) => rules,
+                    Err(err) => {
+                        eprintln!("error: {}", err);
+                        std::process::exit(2);
+                    }
+                }
+            };
+            client::init_config(settings.client);
+            macroquad::Window::from_config(window_conf(), game::r#loop::run(rules));
+        }
+        cli::Command::Bot(settings) => {
+            client::init_config(settings.client);
+            let bot = settings.bot;
+            let server_url = client::master_api::join_room(&bot.room, &bot.name, None)
+                .unwrap_or_else(|| client::config().server_url.clone());
+            println!("bot {} joining {} on {}", bot.name, bot.room, server_url);
+            let mut brain = client::bot_api::PelletBot::default();
+            let mut bot_client = client::bot_api::BotClient::connect(server_url, &bot.room, &bot.name);
+            bot_client.run(|view| brain.decide(view));
+            println!("bot {}: disconnected", bot.name);
         }
-        Some(_) => {
-            macroquad::Window::from_config(window_conf(), game::r#loop::run());
+        cli::Command::Tournament(settings) => {
+            let settings = settings.tournament;
+            let report = tournament::run(&settings).and_then(|r| r.render(settings.format));
+            let written = report.and_then(|text| match &settings.out {
+                Some(path) => std::fs::write(path, text)
+                    .map_err(|e| format!("cannot write {}: {}", path, e)),
+                None => {
+                    print!("{}", text);
+                    Ok(())
+                }
+            });
+            if let Err(err) = written {
+                eprintln!("error: {}", err);
+                std::process::exit(2);
+            }
+        }
+        cli::Command::Help => {
+            println!("{}", cli::USAGE);
         }
     }
-}
\ No newline at end of file
+}
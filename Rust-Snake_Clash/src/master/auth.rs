@@ -1,9 +1,168 @@
 #![allow(dead_code)]
 
-pub fn sign_token(payload: &str) -> String {
-    format!("dev.{}", payload)
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::game::skin;
+
+// Join tickets are `<payload>.<mac>`, signed by the master and checked by the
+// game server with the same `token_secret`. Macs are HMAC-SHA256 over
+// `<domain>\n<payload>`: a value signed for one use (ticket, profile token,
+// match report) is never valid for another.
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const TICKET: &str = "ticket";
+pub const PROFILE: &str = "profile";
+pub const REPORT: &str = "report";
+pub const HEARTBEAT: &str = "heartbeat";
+// Unguessable ids (queue tickets, guest profiles); never verified.
+pub const ID: &str = "id";
+
+fn keyed(secret: &str, domain: &str, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac takes any key length");
+    mac.update(domain.as_bytes());
+    mac.update(b"\n");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+pub fn mac(secret: &str, domain: &str, payload: &str) -> String {
+    keyed(secret, domain, payload)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Constant-time check of a hex `signature` made by `mac`.
+pub fn verify_mac(secret: &str, domain: &str, payload: &str, signature: &str) -> bool {
+    let Some(bytes) = decode_hex(signature) else {
+        return false;
+    };
+    keyed(secret, domain, payload).verify_slice(&bytes).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Header carrying `mac(token_secret, <domain>, body)` of a game server's
+// `POST /matches` (REPORT) and heartbeats (HEARTBEAT).
+pub const SIGNATURE_HEADER: &str = "x-snake-signature";
+
+pub fn sign_token(secret: &str, domain: &str, payload: &str) -> String {
+    format!("{}.{}", payload, mac(secret, domain, payload))
+}
+
+// Payload of a token signed with `secret` for `domain`.
+pub fn verify_token<'a>(secret: &str, domain: &str, token: &'a str) -> Option<&'a str> {
+    let (payload, sig) = token.rsplit_once('.')?;
+    verify_mac(secret, domain, payload, sig).then_some(payload)
 }
 
-pub fn verify_token(_token: &str) -> bool {
-    true
+// Room ids the master signs tickets for: 1-64 of `[A-Za-z0-9_.-]`.
+pub fn valid_room_id(room_id: &str) -> bool {
+    (1..=64).contains(&room_id.len())
+        && room_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+// Ticket for one player of one room, valid until `expires_at` (unix seconds).
+// The name is cleaned like the game server does, so it cannot carry separators.
+pub fn join_ticket(secret: &str, room_id: &str, player_name: &str, expires_at: u64) -> String {
+    let name = skin::sanitize_name(player_name, "");
+    sign_token(secret, TICKET, &format!("{}:{}:{}", room_id, expires_at, name))
+}
+
+// Ok if the ticket is validly signed, unexpired and for `room_id`.
+pub fn check_join_ticket(secret: &str, token: &str, room_id: &str, now: u64) -> Result<(), String> {
+    let payload = verify_token(secret, TICKET, token).ok_or_else(|| "bad ticket signature".to_owned())?;
+    let mut parts = payload.splitn(3, ':');
+    let ticket_room = parts.next().unwrap_or_default();
+    let expires_at: u64 = parts
+        .next()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| "malformed ticket".to_owned())?;
+    if ticket_room != room_id {
+        return Err(format!("ticket is for room {}", ticket_room));
+    }
+    if expires_at < now {
+        return Err(format!("ticket for {} expired", ticket_room));
+    }
+    Ok(())
+}
+
+// Proves a client owns a guest profile (`JoinReq.profile`) until `expires_at`;
+// the client renews it (`POST /players/:player_id/token`) on every launch.
+pub fn profile_token(secret: &str, player_id: &str, expires_at: u64) -> String {
+    sign_token(secret, PROFILE, &format!("{}:{}", player_id, expires_at))
+}
+
+// Profile id of a valid, unexpired profile token.
+pub fn check_profile_token(secret: &str, token: &str, now: u64) -> Option<String> {
+    let (player_id, expires_at) = verify_token(secret, PROFILE, token)?.rsplit_once(':')?;
+    let expires_at: u64 = expires_at.parse().ok()?;
+    (expires_at >= now).then(|| player_id.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    #[test]
+    fn join_ticket_is_for_one_room_until_it_expires() {
+        let ticket = join_ticket(SECRET, "R-1", "Ann", 100);
+        assert_eq!(check_join_ticket(SECRET, &ticket, "R-1", 100), Ok(()));
+        assert!(check_join_ticket(SECRET, &ticket, "R-1", 101).is_err());
+        assert!(check_join_ticket(SECRET, &ticket, "R-2", 50).is_err());
+        assert!(check_join_ticket("other", &ticket, "R-1", 50).is_err());
+    }
+
+    #[test]
+    fn join_ticket_cannot_be_edited() {
+        let ticket = join_ticket(SECRET, "R-1", "Ann", 100);
+        let (_, sig) = ticket.rsplit_once('.').unwrap();
+        let moved = format!("R-2:100:Ann.{}", sig);
+        let extended = format!("R-1:999:Ann.{}", sig);
+        assert!(check_join_ticket(SECRET, &moved, "R-2", 50).is_err());
+        assert!(check_join_ticket(SECRET, &extended, "R-1", 500).is_err());
+    }
+
+    #[test]
+    fn join_ticket_names_carry_no_separators() {
+        let ticket = join_ticket(SECRET, "R-1", "a:9999:b\"}", 100);
+        let (payload, _) = ticket.rsplit_once('.').unwrap();
+        assert_eq!(payload.matches(':').count(), 2);
+        assert!(!payload.contains('"'));
+    }
+
+    #[test]
+    fn profile_token_expires() {
+        let token = profile_token(SECRET, "G1", 100);
+        assert_eq!(check_profile_token(SECRET, &token, 100).as_deref(), Some("G1"));
+        assert_eq!(check_profile_token(SECRET, &token, 101), None);
+        assert_eq!(check_profile_token("other", &token, 50), None);
+    }
+
+    #[test]
+    fn signatures_do_not_cross_domains() {
+        let payload = "G1:100";
+        let token = sign_token(SECRET, TICKET, payload);
+        assert_eq!(check_profile_token(SECRET, &token, 50), None);
+        let sig = mac(SECRET, PROFILE, payload);
+        assert!(verify_mac(SECRET, PROFILE, payload, &sig));
+        assert!(!verify_mac(SECRET, REPORT, payload, &sig));
+        assert!(!verify_mac(SECRET, PROFILE, payload, "zz"));
+    }
 }
@@ -1,5 +1,18 @@
 #![allow(dead_code)]
 
+use crate::master::state;
+
+pub const GC_INTERVAL_SEC: u64 = 10;
+
+// Drop game servers that stopped sending heartbeats, along with their rooms.
 pub fn run_gc() {
-    // placeholder for TTL cleanup
+    let removed = state::prune_servers(state::SERVER_TIMEOUT_SEC * 4);
+    if removed.is_empty() {
+        return;
+    }
+    for room in state::list_rooms() {
+        if removed.contains(&room.server_addr) {
+            state::delete_room(&room.room_id);
+        }
+    }
 }
@@ -4,12 +4,39 @@ pub mod routes;
 pub mod state;
 pub mod auth;
 pub mod gc;
+pub mod queue;
+pub mod store;
+pub mod profiles;
+
+use crate::config::settings::MasterSettings;
 
 pub fn router() -> axum::Router {
     routes::router()
 }
 
-pub async fn serve(addr: &str) -> std::io::Result<()> {
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+pub async fn serve(settings: MasterSettings) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(&settings.bind).await?;
+    if crate::config::settings::insecure_secret(&settings.token_secret) {
+        eprintln!(
+            "WARNING: master token_secret is empty or the public default: match reports are refused \
+             and anyone can forge join tickets and heartbeats. Set SNAKE_TOKEN_SECRET or [master] token_secret."
+        );
+    }
+    store::init(&settings.data_path).map_err(std::io::Error::other)?;
+    state::init(settings);
+    tokio::spawn(async {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(gc::GC_INTERVAL_SEC));
+        loop {
+            ticker.tick().await;
+            gc::run_gc();
+        }
+    });
+    tokio::spawn(async {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(queue::QUEUE_TICK_SEC));
+        loop {
+            ticker.tick().await;
+            queue::run_matching();
+        }
+    });
     axum::serve(listener, router()).await
 }
@@ -0,0 +1,604 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::mode::GameMode;
+use crate::master::auth;
+use crate::master::queue::DEFAULT_RATING;
+use crate::master::state;
+use crate::master::store::{self, StoreData};
+
+// Guest player profiles: Elo rating, lifetime stats and the last matches.
+// Game servers report finished matches (`POST /matches`, signed with the shared
+// `token_secret`); the profile token a client sends in `JoinReq.profile` ties
+// its snake to a profile. Reports also feed the daily, weekly and all-time
+// leaderboards, which can be narrowed to one mode and/or one region.
+
+// Matches kept per profile, newest first.
+pub const HISTORY_LEN: usize = 20;
+pub const ELO_K: f32 = 32.0;
+pub const MAX_LEADERBOARD: usize = 100;
+pub const DEFAULT_LEADERBOARD: usize = 20;
+// Profile tokens last 30 days; clients renew theirs on every launch.
+pub const PROFILE_TOKEN_TTL_SEC: u64 = 30 * DAY_SEC;
+// Reports further than this from the master's clock are refused; recorded
+// match ids are kept this long.
+pub const REPORT_MAX_AGE_SEC: u64 = DAY_SEC;
+const DAY_SEC: u64 = 86_400;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub player_id: String,
+    pub name: String,
+    pub rating: f32,
+    pub games: u32,
+    // First place (or winning team).
+    pub wins: u32,
+    pub kills: u32,
+    pub total_score: i64,
+    pub best_score: i32,
+    pub created_at: u64,
+    pub last_seen: u64,
+    #[serde(default)]
+    pub history: Vec<MatchRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub at: u64,
+    pub room_id: String,
+    pub mode: GameMode,
+    pub players: u8,
+    pub rank: u8,
+    pub score: i32,
+    pub kills: u32,
+    pub rating_delta: f32,
+}
+
+// Final standings of a timed match, sent by the game server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchReport {
+    pub room_id: String,
+    pub mode: GameMode,
+    // Region of the game server.
+    #[serde(default)]
+    pub region: String,
+    // Unix seconds; with `room_id`, identifies the match (`match_id`).
+    pub ended_at: u64,
+    pub players: Vec<MatchPlayer>,
+}
+
+impl MatchReport {
+    pub fn match_id(&self) -> String {
+        format!("{}@{}", self.room_id, self.ended_at)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchPlayer {
+    // Verified profile; None for guests without one and for bots.
+    pub profile_id: Option<String>,
+    pub name: String,
+    pub score: i32,
+    pub kills: u32,
+    // 1 = first; equal scores (or teammates) share a rank.
+    pub rank: u8,
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CreateProfileRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProfileResponse {
+    pub profile: Profile,
+    // Sent back in `JoinReq.profile`; proves the profile is ours.
+    pub token: String,
+    pub expires_at: u64,
+}
+
+// `POST /players/:player_id/token`: a still valid token for a fresh one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenewTokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenewTokenResponse {
+    pub profile: ProfileResponse,
+    pub token: String,
+    pub expires_at: u64,
+}
+
+// `GET /players/:player_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileResponse {
+    #[serde(flatten)]
+    pub profile: Profile,
+    // Rank on the all-time board of every mode and region; None before the first game.
+    pub global_rank: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardPeriod {
+    Daily,
+    Weekly,
+    #[default]
+    AllTime,
+}
+
+impl LeaderboardPeriod {
+    // First second of the current period (UTC; weeks start on Monday).
+    pub fn start(self, now: u64) -> u64 {
+        let day = now - now % DAY_SEC;
+        match self {
+            Self::Daily => day,
+            // 1970-01-01 was a Thursday.
+            Self::Weekly => day.saturating_sub((now / DAY_SEC + 3) % 7 * DAY_SEC),
+            Self::AllTime => 0,
+        }
+    }
+}
+
+// `GET /leaderboard?period=&mode=&region=&limit=`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaderboardQuery {
+    pub period: LeaderboardPeriod,
+    // None = every mode.
+    pub mode: Option<GameMode>,
+    // None or empty = every region.
+    pub region: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardResponse {
+    pub period: LeaderboardPeriod,
+    pub mode: Option<GameMode>,
+    pub region: Option<String>,
+    pub entries: Vec<LeaderboardRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardRow {
+    pub rank: u32,
+    pub player_id: String,
+    pub name: String,
+    pub best_score: i32,
+    pub games: u32,
+    pub wins: u32,
+    pub kills: u32,
+    pub rating: f32,
+}
+
+// One profiled player's result, kept for the daily and weekly boards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub at: u64,
+    pub player_id: String,
+    pub mode: GameMode,
+    pub region: String,
+    pub score: i32,
+    pub kills: u32,
+    pub won: bool,
+}
+
+// Totals of one player in one mode and region (or, merged, across several).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardStats {
+    pub player_id: String,
+    pub mode: GameMode,
+    pub region: String,
+    pub games: u32,
+    pub wins: u32,
+    pub kills: u32,
+    pub best_score: i32,
+}
+
+impl BoardStats {
+    fn add(&mut self, score: i32, kills: u32, won: bool) {
+        self.games += 1;
+        self.wins += won as u32;
+        self.kills += kills;
+        self.best_score = self.best_score.max(score);
+    }
+
+    fn merge(&mut self, other: &BoardStats) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.kills += other.kills;
+        self.best_score = self.best_score.max(other.best_score);
+    }
+}
+
+pub fn board_key(player_id: &str, mode: GameMode, region: &str) -> String {
+    format!("{}|{}|{}", player_id, mode.as_str(), region)
+}
+
+pub fn create_guest(req: CreateProfileRequest) -> Option<CreateProfileResponse> {
+    let now = state::now_secs();
+    let secret = state::settings().token_secret.clone();
+    let name = crate::game::skin::sanitize_name(&req.name, "Guest");
+    store::write(|data| {
+        data.next_guest += 1;
+        let tag = auth::mac(&secret, auth::ID, &format!("guest:{}:{}", data.next_guest, now));
+        let player_id = format!("G{}", &tag[..12]);
+        let profile = Profile {
+            player_id: player_id.clone(),
+            name,
+            rating: DEFAULT_RATING,
+            games: 0,
+            wins: 0,
+            kills: 0,
+            total_score: 0,
+            best_score: 0,
+            created_at: now,
+            last_seen: now,
+            history: Vec::new(),
+        };
+        data.profiles.insert(player_id.clone(), profile.clone());
+        let expires_at = now + PROFILE_TOKEN_TTL_SEC;
+        CreateProfileResponse {
+            token: auth::profile_token(&secret, &player_id, expires_at),
+            profile,
+            expires_at,
+        }
+    })
+}
+
+// None if the token is expired, forged or for another profile, or the profile is unknown.
+pub fn renew_token(player_id: &str, token: &str) -> Option<RenewTokenResponse> {
+    let now = state::now_secs();
+    let secret = &state::settings().token_secret;
+    if auth::check_profile_token(secret, token, now)? != player_id {
+        return None;
+    }
+    let profile = get_profile(player_id)?;
+    let expires_at = now + PROFILE_TOKEN_TTL_SEC;
+    Some(RenewTokenResponse {
+        profile,
+        token: auth::profile_token(secret, player_id, expires_at),
+        expires_at,
+    })
+}
+
+pub fn get_profile(player_id: &str) -> Option<ProfileResponse> {
+    store::read(|data| {
+        let profile = data.profiles.get(player_id)?.clone();
+        let global_rank = board(data, LeaderboardPeriod::AllTime, None, None, state::now_secs())
+            .iter()
+            .position(|stats| stats.player_id == player_id)
+            .map(|idx| idx as u32 + 1);
+        Some(ProfileResponse { profile, global_rank })
+    })
+    .flatten()
+}
+
+// Per-player totals of a period, best score first, then most wins.
+fn board(
+    data: &StoreData,
+    period: LeaderboardPeriod,
+    mode: Option<GameMode>,
+    region: Option<&str>,
+    now: u64,
+) -> Vec<BoardStats> {
+    let matches = |m: GameMode, r: &str| {
+        mode.is_none_or(|mode| mode == m) && region.is_none_or(|region| region.eq_ignore_ascii_case(r))
+    };
+    let mut players: HashMap<&str, BoardStats> = HashMap::new();
+    if period == LeaderboardPeriod::AllTime {
+        for stats in data.all_time.values().filter(|s| matches(s.mode, &s.region)) {
+            players
+                .entry(&stats.player_id)
+                .and_modify(|total| total.merge(stats))
+                .or_insert_with(|| stats.clone());
+        }
+    } else {
+        let since = period.start(now);
+        for entry in data.recent.iter().filter(|e| e.at >= since && matches(e.mode, &e.region)) {
+            players
+                .entry(&entry.player_id)
+                .or_insert_with(|| BoardStats {
+                    player_id: entry.player_id.clone(),
+                    mode: entry.mode,
+                    region: entry.region.clone(),
+                    games: 0,
+                    wins: 0,
+                    kills: 0,
+                    best_score: 0,
+                })
+                .add(entry.score, entry.kills, entry.won);
+        }
+    }
+    let mut rows: Vec<BoardStats> = players.into_values().collect();
+    rows.sort_by(|a, b| {
+        b.best_score
+            .cmp(&a.best_score)
+            .then_with(|| b.wins.cmp(&a.wins))
+            .then_with(|| a.player_id.cmp(&b.player_id))
+    });
+    rows
+}
+
+pub fn leaderboard(query: &LeaderboardQuery) -> LeaderboardResponse {
+    let region = query.region.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD).clamp(1, MAX_LEADERBOARD);
+    let entries = store::read(|data| {
+        board(data, query.period, query.mode, region, state::now_secs())
+            .into_iter()
+            .take(limit)
+            .enumerate()
+            .map(|(idx, stats)| {
+                let profile = data.profiles.get(&stats.player_id);
+                LeaderboardRow {
+                    rank: idx as u32 + 1,
+                    name: profile.map(|p| p.name.clone()).unwrap_or_default(),
+                    rating: profile.map(|p| p.rating).unwrap_or(DEFAULT_RATING),
+                    player_id: stats.player_id,
+                    best_score: stats.best_score,
+                    games: stats.games,
+                    wins: stats.wins,
+                    kills: stats.kills,
+                }
+            })
+            .collect()
+    })
+    .unwrap_or_default();
+    LeaderboardResponse {
+        period: query.period,
+        mode: query.mode,
+        region: region.map(str::to_owned),
+        entries,
+    }
+}
+
+// (index, rank) of the seats that are rated: humans only (server fill bots and
+// bot clients farm easy Elo), and each profile once (a second seat of the same
+// profile is ignored). Ranks are recounted among these seats, so a bot ahead of
+// a human does not cost them the win.
+pub fn rated_seats(players: &[MatchPlayer]) -> Vec<(usize, u8)> {
+    let mut seen: Vec<&str> = Vec::new();
+    let seats: Vec<usize> = players
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.is_bot)
+        .filter(|(_, p)| match p.profile_id.as_deref() {
+            Some(id) if seen.contains(&id) => false,
+            Some(id) => {
+                seen.push(id);
+                true
+            }
+            None => true,
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    seats
+        .iter()
+        .map(|&idx| {
+            let better = seats.iter().filter(|&&other| players[other].rank < players[idx].rank).count();
+            (idx, (better + 1).min(u8::MAX as usize) as u8)
+        })
+        .collect()
+}
+
+// Multiplayer Elo over (rating, rank) seats: every pair is one game, won by the
+// better rank (equal ranks draw). The sum is scaled to one game's `ELO_K`.
+pub fn elo_deltas(seats: &[(f32, u8)]) -> Vec<f32> {
+    if seats.len() < 2 {
+        return vec![0.0; seats.len()];
+    }
+    let others = (seats.len() - 1) as f32;
+    seats
+        .iter()
+        .enumerate()
+        .map(|(i, &(rating, rank))| {
+            let sum: f32 = seats
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, &(other_rating, other_rank))| {
+                    let expected = 1.0 / (1.0 + 10f32.powf((other_rating - rating) / 400.0));
+                    let actual = match rank.cmp(&other_rank) {
+                        std::cmp::Ordering::Less => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.0,
+                    };
+                    actual - expected
+                })
+                .sum();
+            ELO_K * sum / others
+        })
+        .collect()
+}
+
+// Updates the profiles of a finished match. Only `rated_seats` take part:
+// humans without a profile count as `DEFAULT_RATING` opponents and are not
+// updated. Each match is recorded once. Returns how many profiles were updated.
+pub fn apply_report(report: &MatchReport) -> Result<usize, String> {
+    let now = state::now_secs();
+    if now.abs_diff(report.ended_at) > REPORT_MAX_AGE_SEC {
+        return Err(format!("match {} is too old", report.match_id()));
+    }
+    let seats = rated_seats(&report.players);
+    let count = seats.len();
+    store::write(|data| {
+        data.seen_matches.retain(|_, at| now.abs_diff(*at) <= REPORT_MAX_AGE_SEC);
+        if data.seen_matches.insert(report.match_id(), report.ended_at).is_some() {
+            return Err(format!("match {} already recorded", report.match_id()));
+        }
+        let ratings: Vec<(f32, u8)> = seats
+            .iter()
+            .map(|&(idx, rank)| {
+                let p = &report.players[idx];
+                let rating = p
+                    .profile_id
+                    .as_ref()
+                    .and_then(|id| data.profiles.get(id))
+                    .map(|profile| profile.rating)
+                    .unwrap_or(DEFAULT_RATING);
+                (rating, rank)
+            })
+            .collect();
+        let deltas = elo_deltas(&ratings);
+        let mut updated = 0;
+        for (&(idx, rank), delta) in seats.iter().zip(deltas) {
+            let player = &report.players[idx];
+            let Some(profile) = player.profile_id.as_ref().and_then(|id| data.profiles.get_mut(id)) else {
+                continue;
+            };
+            profile.rating += delta;
+            profile.name = player.name.clone();
+            profile.games += 1;
+            if rank == 1 {
+                profile.wins += 1;
+            }
+            profile.kills += player.kills;
+            profile.total_score += player.score as i64;
+            profile.best_score = profile.best_score.max(player.score);
+            profile.last_seen = now;
+            profile.history.insert(
+                0,
+                MatchRecord {
+                    at: now,
+                    room_id: report.room_id.clone(),
+                    mode: report.mode,
+                    players: count.min(u8::MAX as usize) as u8,
+                    rank,
+                    score: player.score,
+                    kills: player.kills,
+                    rating_delta: delta,
+                },
+            );
+            profile.history.truncate(HISTORY_LEN);
+
+            let player_id = profile.player_id.clone();
+            let won = rank == 1;
+            data.recent.push(ScoreEntry {
+                at: now,
+                player_id: player_id.clone(),
+                mode: report.mode,
+                region: report.region.clone(),
+                score: player.score,
+                kills: player.kills,
+                won,
+            });
+            data.all_time
+                .entry(board_key(&player_id, report.mode, &report.region))
+                .or_insert_with(|| BoardStats {
+                    player_id,
+                    mode: report.mode,
+                    region: report.region.clone(),
+                    games: 0,
+                    wins: 0,
+                    kills: 0,
+                    best_score: 0,
+                })
+                .add(player.score, player.kills, won);
+            updated += 1;
+        }
+        // The weekly board is the longest one built from `recent`.
+        let since = LeaderboardPeriod::Weekly.start(now);
+        data.recent.retain(|e| e.at >= since);
+        data.matches_recorded += 1;
+        Ok(updated)
+    })
+    .unwrap_or(Ok(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seat(profile_id: Option<&str>, rank: u8, is_bot: bool) -> MatchPlayer {
+        MatchPlayer {
+            profile_id: profile_id.map(str::to_owned),
+            name: "P".to_owned(),
+            score: 0,
+            kills: 0,
+            rank,
+            is_bot,
+        }
+    }
+
+    #[test]
+    fn elo_winner_gains_what_loser_loses() {
+        let deltas = elo_deltas(&[(DEFAULT_RATING, 1), (DEFAULT_RATING, 2)]);
+        assert!((deltas[0] - ELO_K * 0.5).abs() < 1e-4);
+        assert!((deltas[0] + deltas[1]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn elo_upset_pays_more_than_expected_win() {
+        let upset = elo_deltas(&[(1000.0, 1), (1400.0, 2)])[0];
+        let expected = elo_deltas(&[(1400.0, 1), (1000.0, 2)])[0];
+        assert!(upset > expected);
+        assert!(upset < ELO_K && expected > 0.0);
+    }
+
+    #[test]
+    fn elo_draws_and_lone_seats_do_not_move() {
+        let draw = elo_deltas(&[(1200.0, 1), (1200.0, 1)]);
+        assert!(draw.iter().all(|d| d.abs() < 1e-4));
+        assert_eq!(elo_deltas(&[(1200.0, 1)]), vec![0.0]);
+        assert!(elo_deltas(&[]).is_empty());
+    }
+
+    #[test]
+    fn elo_many_players_sum_to_zero_and_stay_within_k() {
+        let seats = [(1000.0, 1), (1100.0, 2), (1300.0, 2), (900.0, 4)];
+        let deltas = elo_deltas(&seats);
+        assert!(deltas.iter().sum::<f32>().abs() < 1e-3);
+        assert!(deltas.iter().all(|d| d.abs() <= ELO_K));
+        assert!(deltas[0] > 0.0 && deltas[3] < 0.0);
+    }
+
+    #[test]
+    fn rated_seats_skip_bots_and_second_seats_of_a_profile() {
+        let players = [
+            seat(Some("G1"), 1, false),
+            seat(None, 2, true),
+            seat(Some("G1"), 3, false),
+            seat(None, 4, false),
+            seat(Some("G2"), 5, true),
+            seat(Some("G3"), 6, false),
+        ];
+        assert_eq!(rated_seats(&players), vec![(0, 1), (3, 2), (5, 3)]);
+    }
+
+    #[test]
+    fn match_reports_are_recorded_once_and_only_while_fresh() {
+        let report = MatchReport {
+            room_id: "T-REPLAY".to_owned(),
+            mode: GameMode::Classic,
+            region: String::new(),
+            ended_at: state::now_secs(),
+            players: vec![seat(None, 1, false), seat(None, 2, false)],
+        };
+        assert_eq!(apply_report(&report), Ok(0));
+        assert!(apply_report(&report).is_err());
+        let stale = MatchReport {
+            ended_at: report.ended_at - REPORT_MAX_AGE_SEC - 1,
+            ..report
+        };
+        assert!(apply_report(&stale).is_err());
+    }
+
+    #[test]
+    fn rated_seats_rank_humans_ahead_of_bots() {
+        let players = [
+            seat(None, 1, true),
+            seat(Some("G1"), 2, false),
+            seat(Some("G2"), 3, false),
+            seat(None, 3, false),
+        ];
+        assert_eq!(rated_seats(&players), vec![(1, 1), (2, 2), (3, 2)]);
+    }
+}
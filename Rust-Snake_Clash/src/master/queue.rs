@@ -0,0 +1,319 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::mode::GameMode;
+use crate::master::auth;
+use crate::master::state::{self, CreateRoomRequest, RoomInfo};
+
+// Skill-based matchmaking: players enqueue with a rating, `run_matching` groups
+// compatible tickets (same mode, same region, close ratings) into a private
+// room and hands each player a join ticket. The accepted rating gap widens the
+// longer a ticket waits, and after a while regions are mixed.
+
+static QUEUE: OnceLock<Mutex<HashMap<String, Ticket>>> = OnceLock::new();
+static NEXT_TICKET: AtomicU64 = AtomicU64::new(1);
+
+pub const QUEUE_TICK_SEC: u64 = 1;
+pub const DEFAULT_RATING: f32 = 1000.0;
+// Rating gap accepted at enqueue, then widened per second of wait, up to the max.
+pub const BASE_WINDOW: f32 = 100.0;
+pub const WINDOW_PER_SEC: f32 = 10.0;
+pub const MAX_WINDOW: f32 = 600.0;
+// After this long, a match starts with fewer than `queue_match_size` players.
+pub const FILL_AFTER_SEC: u64 = 20;
+pub const MIN_MATCH_PLAYERS: usize = 2;
+// After this long, tickets of other regions are accepted.
+pub const REGION_RELAX_SEC: u64 = 30;
+// Waiting tickets that are not polled for this long are dropped (player left).
+pub const IDLE_TIMEOUT_SEC: u64 = 30;
+// Longest `GET /queue/:ticket_id/wait`.
+pub const MAX_WAIT_SEC: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueRequest {
+    pub player_name: String,
+    pub region: String,
+    pub rating: f32,
+    // None = any mode (Classic if the whole match has no preference).
+    pub mode: Option<GameMode>,
+}
+
+impl Default for QueueRequest {
+    fn default() -> Self {
+        Self {
+            player_name: String::new(),
+            region: String::new(),
+            rating: DEFAULT_RATING,
+            mode: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum QueueStatus {
+    Waiting {
+        waited_sec: u64,
+        // Rating gap currently accepted.
+        window: f32,
+        // Tickets waiting in the queue, this one included.
+        queued: usize,
+    },
+    Matched {
+        room: RoomInfo,
+        // Join ticket for `room` (`JoinReq.ticket`).
+        token: String,
+        expires_at: u64,
+    },
+    // Unknown, cancelled or dropped ticket.
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueTicketResponse {
+    pub ticket_id: String,
+    pub status: QueueStatus,
+}
+
+#[derive(Debug, Clone)]
+struct Ticket {
+    req: QueueRequest,
+    enqueued_at: u64,
+    last_poll: u64,
+    // None while waiting.
+    matched: Option<QueueStatus>,
+}
+
+impl Ticket {
+    fn waited(&self, now: u64) -> u64 {
+        now.saturating_sub(self.enqueued_at)
+    }
+
+    fn window(&self, now: u64) -> f32 {
+        (BASE_WINDOW + WINDOW_PER_SEC * self.waited(now) as f32).min(MAX_WINDOW)
+    }
+}
+
+fn queue() -> &'static Mutex<HashMap<String, Ticket>> {
+    QUEUE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn enqueue(mut req: QueueRequest) -> QueueTicketResponse {
+    let now = state::now_secs();
+    if !req.rating.is_finite() {
+        req.rating = DEFAULT_RATING;
+    }
+    // Ticket ids are unguessable so nobody polls or cancels someone else's ticket.
+    let n = NEXT_TICKET.fetch_add(1, Ordering::Relaxed);
+    let ticket_id = auth::mac(&state::settings().token_secret, auth::ID, &format!("queue:{}:{}", n, now));
+    if let Ok(mut guard) = queue().lock() {
+        guard.insert(
+            ticket_id.clone(),
+            Ticket {
+                req,
+                enqueued_at: now,
+                last_poll: now,
+                matched: None,
+            },
+        );
+    }
+    let status = status(&ticket_id);
+    QueueTicketResponse { ticket_id, status }
+}
+
+// Also keeps a waiting ticket alive.
+pub fn status(ticket_id: &str) -> QueueStatus {
+    let now = state::now_secs();
+    let Ok(mut guard) = queue().lock() else {
+        return QueueStatus::Expired;
+    };
+    let queued = guard.values().filter(|t| t.matched.is_none()).count();
+    let Some(ticket) = guard.get_mut(ticket_id) else {
+        return QueueStatus::Expired;
+    };
+    ticket.last_poll = now;
+    match &ticket.matched {
+        Some(matched) => matched.clone(),
+        None => QueueStatus::Waiting {
+            waited_sec: ticket.waited(now),
+            window: ticket.window(now),
+            queued,
+        },
+    }
+}
+
+pub fn cancel(ticket_id: &str) -> bool {
+    queue()
+        .lock()
+        .map(|mut guard| guard.remove(ticket_id).is_some())
+        .unwrap_or(false)
+}
+
+fn compatible(a: &Ticket, b: &Ticket, now: u64) -> bool {
+    let same_mode = match (a.req.mode, b.req.mode) {
+        (Some(ma), Some(mb)) => ma == mb,
+        _ => true,
+    };
+    let regions = a.req.region.eq_ignore_ascii_case(&b.req.region)
+        || (a.waited(now) >= REGION_RELAX_SEC && b.waited(now) >= REGION_RELAX_SEC);
+    let gap = (a.req.rating - b.req.rating).abs();
+    same_mode && regions && gap <= a.window(now).min(b.window(now))
+}
+
+// Drops stale tickets and turns compatible waiting tickets into matches.
+// Called every `QUEUE_TICK_SEC` by `master::serve`.
+pub fn run_matching() {
+    let now = state::now_secs();
+    let match_size = (state::settings().queue_match_size as usize).max(MIN_MATCH_PLAYERS);
+    let groups: Vec<Vec<(String, QueueRequest)>> = {
+        let Ok(mut guard) = queue().lock() else {
+            return;
+        };
+        guard.retain(|_, t| match t.matched {
+            None => now.saturating_sub(t.last_poll) <= IDLE_TIMEOUT_SEC,
+            Some(_) => now.saturating_sub(t.enqueued_at) <= IDLE_TIMEOUT_SEC + state::JOIN_TOKEN_TTL_SEC * 2,
+        });
+
+        // Oldest tickets first so nobody starves.
+        let mut waiting: Vec<(&String, &Ticket)> = guard.iter().filter(|(_, t)| t.matched.is_none()).collect();
+        waiting.sort_by(|a, b| a.1.enqueued_at.cmp(&b.1.enqueued_at).then_with(|| a.0.cmp(b.0)));
+
+        let mut taken: HashSet<&String> = HashSet::new();
+        let mut groups = Vec::new();
+        for (anchor_id, anchor) in &waiting {
+            if taken.contains(anchor_id) {
+                continue;
+            }
+            let mut candidates: Vec<(&String, &Ticket)> = waiting
+                .iter()
+                .filter(|(id, t)| id != anchor_id && !taken.contains(id) && compatible(anchor, t, now))
+                .copied()
+                .collect();
+            candidates.sort_by(|a, b| {
+                let da = (a.1.req.rating - anchor.req.rating).abs();
+                let db = (b.1.req.rating - anchor.req.rating).abs();
+                da.total_cmp(&db)
+            });
+            let mut group: Vec<(&String, &Ticket)> = vec![(anchor_id, anchor)];
+            for cand in candidates {
+                if group.len() >= match_size {
+                    break;
+                }
+                if group.iter().all(|(_, t)| compatible(t, cand.1, now)) {
+                    group.push(cand);
+                }
+            }
+            let ready = group.len() >= match_size
+                || (anchor.waited(now) >= FILL_AFTER_SEC && group.len() >= MIN_MATCH_PLAYERS);
+            if ready {
+                taken.extend(group.iter().map(|(id, _)| *id));
+                groups.push(group.iter().map(|(id, t)| ((*id).clone(), t.req.clone())).collect());
+            }
+        }
+        groups
+    };
+
+    for group in groups {
+        let matched = allocate(&group, match_size, now);
+        if let Ok(mut guard) = queue().lock() {
+            for ((ticket_id, _), status) in group.iter().zip(matched) {
+                if let Some(ticket) = guard.get_mut(ticket_id) {
+                    ticket.matched = Some(status);
+                }
+            }
+        }
+    }
+}
+
+// Private room on a server with spare capacity, one ticket per player.
+fn allocate(group: &[(String, QueueRequest)], match_size: usize, now: u64) -> Vec<QueueStatus> {
+    let anchor = &group[0].1;
+    let mode = group.iter().find_map(|(_, r)| r.mode).unwrap_or_default();
+    let room = state::create_room(CreateRoomRequest {
+        name: "Ranked Match".to_owned(),
+        region: anchor.region.clone(),
+        max_players: match_size.min(u8::MAX as usize) as u8,
+        is_private: true,
+        access_code: None,
+        rules: String::new(),
+        mode,
+    });
+    let room = RoomInfo {
+        ticket_only: true,
+        players: group.len().min(u8::MAX as usize) as u8,
+        ..room
+    };
+    state::upsert_room(room.clone());
+    let expires_at = now + state::JOIN_TOKEN_TTL_SEC;
+    let secret = &state::settings().token_secret;
+    group
+        .iter()
+        .map(|(_, req)| QueueStatus::Matched {
+            room: room.clone(),
+            token: auth::join_ticket(secret, &room.room_id, &req.player_name, expires_at),
+            expires_at,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(region: &str, rating: f32, mode: Option<GameMode>, enqueued_at: u64) -> Ticket {
+        Ticket {
+            req: QueueRequest {
+                player_name: "P".to_owned(),
+                region: region.to_owned(),
+                rating,
+                mode,
+            },
+            enqueued_at,
+            last_poll: enqueued_at,
+            matched: None,
+        }
+    }
+
+    #[test]
+    fn window_widens_with_wait_up_to_max() {
+        let t = ticket("EU", DEFAULT_RATING, None, 0);
+        assert_eq!(t.window(0), BASE_WINDOW);
+        assert_eq!(t.window(5), BASE_WINDOW + 5.0 * WINDOW_PER_SEC);
+        assert_eq!(t.window(10_000), MAX_WINDOW);
+    }
+
+    #[test]
+    fn rating_gap_must_fit_both_windows() {
+        let a = ticket("EU", 1000.0, None, 0);
+        let b = ticket("EU", 1150.0, None, 0);
+        assert!(!compatible(&a, &b, 0));
+        assert!(compatible(&a, &b, 5));
+        // A fresh ticket keeps its narrow window even next to an old one.
+        let fresh = ticket("EU", 1150.0, None, 5);
+        assert!(!compatible(&a, &fresh, 5));
+    }
+
+    #[test]
+    fn modes_must_agree_unless_one_is_open() {
+        let classic = ticket("EU", 1000.0, Some(GameMode::Classic), 0);
+        let royale = ticket("EU", 1000.0, Some(GameMode::Royale), 0);
+        let any = ticket("EU", 1000.0, None, 0);
+        assert!(!compatible(&classic, &royale, 0));
+        assert!(compatible(&classic, &any, 0));
+    }
+
+    #[test]
+    fn regions_mix_once_both_waited_long_enough() {
+        let eu = ticket("EU", 1000.0, None, 0);
+        let us = ticket("us", 1000.0, None, 10);
+        assert!(!compatible(&eu, &us, REGION_RELAX_SEC));
+        assert!(compatible(&eu, &us, REGION_RELAX_SEC + 10));
+        assert!(compatible(&eu, &ticket("eu", 1000.0, None, 0), 0));
+    }
+}
@@ -1,12 +1,21 @@
 #![allow(dead_code)]
 
-use axum::{routing::{delete, get, post}, extract::Path, Json, Router};
+use axum::{routing::{delete, get, post}, extract::{Path, Query}, http::{HeaderMap, StatusCode}, Json, Router};
 
 use crate::master::state::{
-    CreateRoomRequest, CreateRoomResponse, JoinRoomRequest, JoinRoomResponse, RoomsResponse, RoomInfo, RoomStatus,
-    HeartbeatRequest,
+    CreateRoomRequest, CreateRoomResponse, JoinRoomRequest, JoinRoomResponse, MatchmakeRequest, MatchmakeResponse,
+    RoomQuery, RoomsResponse, RoomInfo, RoomStatus,
+    HeartbeatRequest, ServerHeartbeatRequest, ServerHeartbeatResponse,
 };
-use crate::master::state;
+use crate::master::profiles::{
+    self, CreateProfileRequest, CreateProfileResponse, LeaderboardQuery, LeaderboardResponse, MatchReport, ProfileResponse,
+    RenewTokenRequest, RenewTokenResponse,
+};
+use crate::master::queue::{self, QueueRequest, QueueStatus, QueueTicketResponse};
+use crate::master::{auth, state};
+use crate::config::settings;
+use crate::game::mode::GameMode;
+use serde::Deserialize;
 
 pub fn router() -> Router {
     Router::new()
@@ -15,10 +24,20 @@ pub fn router() -> Router {
     .route("/rooms/:room_id", delete(delete_room))
         .route("/rooms/join", post(join_room))
         .route("/rooms/heartbeat", post(heartbeat))
+        .route("/matchmake", post(matchmake))
+        .route("/queue", post(enqueue))
+        .route("/queue/:ticket_id", get(queue_status).delete(cancel_ticket))
+        .route("/queue/:ticket_id/wait", get(wait_ticket))
+        .route("/players", post(create_profile))
+        .route("/players/:player_id", get(get_profile))
+        .route("/players/:player_id/token", post(renew_token))
+        .route("/leaderboard", get(leaderboard))
+        .route("/matches", post(report_match))
+        .route("/servers/heartbeat", post(server_heartbeat))
 }
 
-async fn list_rooms() -> Json<RoomsResponse> {
-    Json(RoomsResponse { rooms: state::list_rooms() })
+async fn list_rooms(Query(query): Query<RoomQuery>) -> Json<RoomsResponse> {
+    Json(state::query_rooms(&query))
 }
 
 async fn create_room(Json(req): Json<CreateRoomRequest>) -> Json<CreateRoomResponse> {
@@ -34,18 +53,124 @@ async fn delete_room(Path(room_id): Path<String>) -> Json<()> {
     Json(())
 }
 
-async fn join_room(Json(_req): Json<JoinRoomRequest>) -> Json<JoinRoomResponse> {
-    Json(JoinRoomResponse {
-        token: "dev-token".to_owned(),
-        server_addr: "ws://127.0.0.1:9001".to_owned(),
-        expires_at: 0,
-    })
+// Tickets only for rooms the master lists (404 otherwise), and never for queue
+// rooms, which only take the tickets of their matched players.
+async fn join_room(Json(req): Json<JoinRoomRequest>) -> Result<Json<JoinRoomResponse>, StatusCode> {
+    if !auth::valid_room_id(&req.room_id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let room = state::get_room(&req.room_id).ok_or(StatusCode::NOT_FOUND)?;
+    let server_addr = room.server_addr.clone();
+    let expires_at = state::now_secs() + state::JOIN_TOKEN_TTL_SEC;
+    let token = if room.ticket_only {
+        String::new()
+    } else {
+        auth::join_ticket(&state::settings().token_secret, &req.room_id, &req.player_name, expires_at)
+    };
+    Ok(Json(JoinRoomResponse {
+        token,
+        server_addr,
+        expires_at,
+    }))
+}
+
+async fn matchmake(Json(req): Json<MatchmakeRequest>) -> Json<MatchmakeResponse> {
+    Json(state::matchmake(req))
+}
+
+async fn enqueue(Json(req): Json<QueueRequest>) -> Json<QueueTicketResponse> {
+    Json(queue::enqueue(req))
+}
+
+async fn queue_status(Path(ticket_id): Path<String>) -> Json<QueueStatus> {
+    Json(queue::status(&ticket_id))
 }
 
-async fn heartbeat(Json(_req): Json<HeartbeatRequest>) -> Json<()> {
+async fn cancel_ticket(Path(ticket_id): Path<String>) -> Json<()> {
+    let _ = queue::cancel(&ticket_id);
     Json(())
 }
 
+#[derive(Debug, Deserialize)]
+struct WaitQuery {
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+// Long poll: answers as soon as the ticket leaves the waiting state, or with
+// the waiting status after `timeout` seconds (max `queue::MAX_WAIT_SEC`).
+async fn wait_ticket(Path(ticket_id): Path<String>, Query(query): Query<WaitQuery>) -> Json<QueueStatus> {
+    let timeout = query.timeout.unwrap_or(queue::MAX_WAIT_SEC).min(queue::MAX_WAIT_SEC);
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(timeout);
+    loop {
+        let status = queue::status(&ticket_id);
+        if !matches!(status, QueueStatus::Waiting { .. }) || tokio::time::Instant::now() >= deadline {
+            return Json(status);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    }
+}
+
+async fn create_profile(Json(req): Json<CreateProfileRequest>) -> Result<Json<CreateProfileResponse>, StatusCode> {
+    profiles::create_guest(req).map(Json).ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_profile(Path(player_id): Path<String>) -> Result<Json<ProfileResponse>, StatusCode> {
+    profiles::get_profile(&player_id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn renew_token(
+    Path(player_id): Path<String>,
+    Json(req): Json<RenewTokenRequest>,
+) -> Result<Json<RenewTokenResponse>, StatusCode> {
+    profiles::renew_token(&player_id, &req.token).map(Json).ok_or(StatusCode::UNAUTHORIZED)
+}
+
+async fn leaderboard(Query(query): Query<LeaderboardQuery>) -> Json<LeaderboardResponse> {
+    Json(profiles::leaderboard(&query))
+}
+
+// Game server -> master, when a timed match ends. The body is signed with the
+// shared `token_secret` (`auth::SIGNATURE_HEADER`) so clients cannot post results.
+// Refused while the secret is empty or the public default.
+async fn report_match(headers: HeaderMap, body: String) -> Result<Json<()>, StatusCode> {
+    let secret = &state::settings().token_secret;
+    if settings::insecure_secret(secret) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let report: MatchReport = signed_body(&headers, &body, auth::REPORT)?;
+    if !state::get_room(&report.room_id).is_some_and(|room| room.allocated) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    profiles::apply_report(&report).map_err(|_| StatusCode::CONFLICT)?;
+    Ok(Json(()))
+}
+
+// Heartbeats are signed like match reports: only game servers sharing
+// `token_secret` can register, or point a room at their address.
+async fn heartbeat(headers: HeaderMap, body: String) -> Result<Json<()>, StatusCode> {
+    let req: HeartbeatRequest = signed_body(&headers, &body, auth::HEARTBEAT)?;
+    state::room_heartbeat(req);
+    Ok(Json(()))
+}
+
+async fn server_heartbeat(headers: HeaderMap, body: String) -> Result<Json<ServerHeartbeatResponse>, StatusCode> {
+    let req: ServerHeartbeatRequest = signed_body(&headers, &body, auth::HEARTBEAT)?;
+    Ok(Json(state::server_heartbeat(req)))
+}
+
+// JSON body signed for `domain` in `auth::SIGNATURE_HEADER` (401 if not, 400 if unreadable).
+fn signed_body<T: serde::de::DeserializeOwned>(headers: &HeaderMap, body: &str, domain: &str) -> Result<T, StatusCode> {
+    let signature = headers
+        .get(auth::SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !auth::verify_mac(&state::settings().token_secret, domain, body, signature) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    serde_json::from_str(body).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
 #[allow(dead_code)]
 fn demo_room() -> RoomInfo {
     RoomInfo {
@@ -54,9 +179,15 @@ fn demo_room() -> RoomInfo {
         server_addr: "wss://game-01.example.com:9001".to_owned(),
         region: "EU".to_owned(),
         players: 2,
+        bots: 0,
+        spectators: 0,
         max_players: 4,
         is_private: false,
         status: RoomStatus::Waiting,
         ping_ms: Some(32),
+        rules: "classic".to_owned(),
+        mode: GameMode::Classic,
+        ticket_only: false,
+        allocated: false,
     }
 }
@@ -6,12 +6,84 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 use serde::{Deserialize, Serialize};
 
+use crate::config::settings::MasterSettings;
+use crate::master::auth;
+use crate::game::mode::GameMode;
+
 static ROOM_STATE: OnceLock<Mutex<HashMap<String, RoomInfo>>> = OnceLock::new();
+static SERVER_STATE: OnceLock<Mutex<HashMap<String, ServerInfo>>> = OnceLock::new();
+static SETTINGS: OnceLock<MasterSettings> = OnceLock::new();
 static NEXT_ROOM_ID: AtomicU64 = AtomicU64::new(1);
 
+// A game server that missed heartbeats for this long is no longer allocated rooms.
+pub const SERVER_TIMEOUT_SEC: u64 = 15;
+
+// Room list page size bounds (`GET /rooms?per_page=`).
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+// Lifetime of the join token handed out by `POST /matchmake`.
+pub const JOIN_TOKEN_TTL_SEC: u64 = 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoomsResponse {
     pub rooms: Vec<RoomInfo>,
+    // Rooms matching the filters, over all pages.
+    #[serde(default)]
+    pub total: usize,
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default)]
+    pub per_page: usize,
+}
+
+// `GET /rooms` query string; every filter is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomQuery {
+    // Case-insensitive region match.
+    pub region: Option<String>,
+    // waiting, starting, running or finished.
+    pub status: Option<String>,
+    pub has_space: bool,
+    pub hide_private: bool,
+    pub mode: Option<GameMode>,
+    pub sort: RoomSort,
+    // 0-based.
+    pub page: usize,
+    // 0 = `DEFAULT_PAGE_SIZE`.
+    pub per_page: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSort {
+    // Room id, so pages stay stable between calls.
+    #[default]
+    Id,
+    // Most players first.
+    Players,
+    // Lowest ping first, unknown last.
+    Ping,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchmakeRequest {
+    pub player_name: String,
+    // Preferred region; other regions are used when it has no room.
+    pub region: String,
+    // Only rooms of this mode/rules; None = any (Classic when a room is created).
+    pub mode: Option<GameMode>,
+    pub rules: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchmakeResponse {
+    pub room: RoomInfo,
+    // True when no room fit and a new one was allocated.
+    pub created: bool,
+    pub token: String,
+    pub expires_at: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,13 +93,31 @@ pub struct RoomInfo {
     pub server_addr: String,
     pub region: String,
     pub players: u8,
+    // How many of `players` are external bot clients.
+    #[serde(default)]
+    pub bots: u8,
+    // Watching sessions; not counted in `players` nor against `max_players`.
+    #[serde(default)]
+    pub spectators: u8,
     pub max_players: u8,
     pub is_private: bool,
     pub status: RoomStatus,
     pub ping_ms: Option<u16>,
+    // GameRules preset or rule file name the room runs with.
+    #[serde(default = "default_rules")]
+    pub rules: String,
+    #[serde(default)]
+    pub mode: GameMode,
+    // Allocated by the matchmaking queue: joining needs a master ticket.
+    #[serde(default)]
+    pub ticket_only: bool,
+    // Created by the master (not a room joined directly on a game server):
+    // only these report match results.
+    #[serde(skip)]
+    pub allocated: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RoomStatus {
     Waiting,
     Starting,
@@ -35,6 +125,18 @@ pub enum RoomStatus {
     Finished,
 }
 
+impl RoomStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "waiting" => Some(Self::Waiting),
+            "starting" => Some(Self::Starting),
+            "running" => Some(Self::Running),
+            "finished" => Some(Self::Finished),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateRoomRequest {
     pub name: String,
@@ -42,6 +144,10 @@ pub struct CreateRoomRequest {
     pub max_players: u8,
     pub is_private: bool,
     pub access_code: Option<String>,
+    #[serde(default = "default_rules")]
+    pub rules: String,
+    #[serde(default)]
+    pub mode: GameMode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,15 +170,84 @@ pub struct JoinRoomResponse {
     pub expires_at: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub server_addr: String,
+    pub region: String,
+    pub max_rooms: u16,
+    pub rooms: u16,
+    pub players: u16,
+    pub last_seen: u64,
+}
+
+// Sent periodically by every game server; the response lists the rooms the
+// master allocated to it so the server can prepare them before anyone joins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerHeartbeatRequest {
+    pub server_addr: String,
+    pub region: String,
+    pub max_rooms: u16,
+    pub rooms: Vec<HeartbeatRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerHeartbeatResponse {
+    pub rooms: Vec<RoomSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomSpec {
+    pub room_id: String,
+    pub name: String,
+    pub max_players: u8,
+    #[serde(default = "default_rules")]
+    pub rules: String,
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub ticket_only: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HeartbeatRequest {
     pub server_addr: String,
     pub room_id: String,
     pub players: u8,
+    #[serde(default)]
+    pub bots: u8,
+    #[serde(default)]
+    pub spectators: u8,
     pub max_players: u8,
     pub status: RoomStatus,
     pub region: String,
     pub updated_at: u64,
+    #[serde(default = "default_rules")]
+    pub rules: String,
+    #[serde(default)]
+    pub mode: GameMode,
+}
+
+fn default_rules() -> String {
+    "classic".to_owned()
+}
+
+pub fn init(settings: MasterSettings) {
+    let _ = SETTINGS.set(settings);
+}
+
+pub fn settings() -> &'static MasterSettings {
+    SETTINGS.get_or_init(MasterSettings::default)
+}
+
+pub fn default_server_addr() -> String {
+    settings().default_server_addr.clone()
+}
+
+pub fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 pub fn upsert_room(room: RoomInfo) {
@@ -84,17 +259,40 @@ pub fn upsert_room(room: RoomInfo) {
 
 pub fn create_room(req: CreateRoomRequest) -> RoomInfo {
     let id = NEXT_ROOM_ID.fetch_add(1, Ordering::Relaxed);
-    let room_id = format!("LOCAL-{}", id);
+    let server = pick_server(&req.region);
+    if let Some(s) = &server {
+        // Count the allocation now so back-to-back creates spread across servers.
+        let map = SERVER_STATE.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Ok(mut guard) = map.lock()
+            && let Some(entry) = guard.get_mut(&s.server_addr)
+        {
+            entry.rooms = entry.rooms.saturating_add(1);
+        }
+    }
+    let region = match &server {
+        Some(s) if req.region.trim().is_empty() => s.region.clone(),
+        _ => req.region,
+    };
+    let prefix = if region.trim().is_empty() { "LOCAL".to_owned() } else { region.to_uppercase() };
+    let room_id = format!("{}-{}", prefix, id);
     let room = RoomInfo {
         room_id: room_id.clone(),
         name: if req.name.trim().is_empty() { "Local Room".to_owned() } else { req.name },
-        server_addr: "ws://127.0.0.1:9001".to_owned(),
-        region: req.region,
+        server_addr: server
+            .map(|s| s.server_addr)
+            .unwrap_or_else(default_server_addr),
+        region,
         players: 0,
-        max_players: req.max_players.max(2).min(8),
+        bots: 0,
+        spectators: 0,
+        max_players: req.max_players.clamp(2, 8),
         is_private: req.is_private,
         status: RoomStatus::Waiting,
         ping_ms: Some(1),
+        rules: if req.rules.trim().is_empty() { default_rules() } else { req.rules },
+        mode: req.mode,
+        ticket_only: false,
+        allocated: true,
     };
     upsert_room(room.clone());
     room
@@ -116,6 +314,84 @@ pub fn list_rooms() -> Vec<RoomInfo> {
     Vec::new()
 }
 
+// Filtered, sorted page of the room list. An unknown `status` matches nothing.
+pub fn query_rooms(query: &RoomQuery) -> RoomsResponse {
+    let status = query.status.as_deref().filter(|s| !s.trim().is_empty()).map(RoomStatus::parse);
+    let region = query.region.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let mut rooms: Vec<RoomInfo> = list_rooms()
+        .into_iter()
+        .filter(|r| region.is_none_or(|reg| r.region.eq_ignore_ascii_case(reg)))
+        .filter(|r| status.is_none_or(|st| st == Some(r.status)))
+        .filter(|r| !query.has_space || r.players < r.max_players)
+        .filter(|r| !query.hide_private || !r.is_private)
+        .filter(|r| query.mode.is_none_or(|m| m == r.mode))
+        .collect();
+    rooms.sort_by(|a, b| a.room_id.cmp(&b.room_id));
+    match query.sort {
+        RoomSort::Id => {}
+        RoomSort::Players => rooms.sort_by_key(|r| std::cmp::Reverse(r.players)),
+        RoomSort::Ping => rooms.sort_by_key(|r| r.ping_ms.unwrap_or(u16::MAX)),
+    }
+    let per_page = if query.per_page == 0 { DEFAULT_PAGE_SIZE } else { query.per_page.min(MAX_PAGE_SIZE) };
+    let total = rooms.len();
+    RoomsResponse {
+        rooms: rooms.into_iter().skip(query.page.saturating_mul(per_page)).take(per_page).collect(),
+        total,
+        page: query.page,
+        per_page,
+    }
+}
+
+// Quick play: the fullest public room with a free slot (preferred region first,
+// then lowest ping), else a new room. The slot is counted right away so
+// concurrent calls do not overfill a room; the next heartbeat corrects it.
+pub fn matchmake(req: MatchmakeRequest) -> MatchmakeResponse {
+    let region = req.region.trim();
+    let rules = req.rules.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let best = list_rooms()
+        .into_iter()
+        .filter(|r| !r.is_private && !r.ticket_only && r.players < r.max_players)
+        .filter(|r| matches!(r.status, RoomStatus::Waiting | RoomStatus::Starting | RoomStatus::Running))
+        .filter(|r| req.mode.is_none_or(|m| m == r.mode))
+        .filter(|r| rules.is_none_or(|rules| rules == r.rules))
+        .max_by_key(|r| {
+            (
+                region.is_empty() || r.region.eq_ignore_ascii_case(region),
+                r.players,
+                std::cmp::Reverse(r.ping_ms.unwrap_or(u16::MAX)),
+                std::cmp::Reverse(r.room_id.clone()),
+            )
+        });
+    let (room, created) = match best {
+        Some(room) => (room, false),
+        None => {
+            let room = create_room(CreateRoomRequest {
+                name: "Quick Play".to_owned(),
+                region: region.to_owned(),
+                max_players: settings().quick_play_max_players,
+                is_private: false,
+                access_code: None,
+                rules: rules.map(str::to_owned).unwrap_or_else(default_rules),
+                mode: req.mode.unwrap_or_default(),
+            });
+            (room, true)
+        }
+    };
+    let room = RoomInfo {
+        players: room.players.saturating_add(1),
+        ..room
+    };
+    upsert_room(room.clone());
+    let expires_at = now_secs() + JOIN_TOKEN_TTL_SEC;
+    let token = auth::join_ticket(&settings().token_secret, &room.room_id, &req.player_name, expires_at);
+    MatchmakeResponse {
+        room,
+        created,
+        token,
+        expires_at,
+    }
+}
+
 pub fn get_room(room_id: &str) -> Option<RoomInfo> {
     let map = ROOM_STATE.get_or_init(|| Mutex::new(HashMap::new()));
     if let Ok(guard) = map.lock() {
@@ -123,3 +399,114 @@ pub fn get_room(room_id: &str) -> Option<RoomInfo> {
     }
     None
 }
+
+// Least loaded live server with spare capacity, preferring the requested region.
+fn pick_server(region: &str) -> Option<ServerInfo> {
+    let now = now_secs();
+    let servers = list_servers();
+    let live = servers.into_iter().filter(|s| {
+        now.saturating_sub(s.last_seen) <= SERVER_TIMEOUT_SEC && s.rooms < s.max_rooms
+    });
+    let load = |s: &ServerInfo| s.rooms as f32 / s.max_rooms.max(1) as f32;
+    let (same_region, other): (Vec<ServerInfo>, Vec<ServerInfo>) =
+        live.partition(|s| s.region.eq_ignore_ascii_case(region));
+    let pool = if same_region.is_empty() { other } else { same_region };
+    pool.into_iter()
+        .min_by(|a, b| load(a).partial_cmp(&load(b)).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+pub fn list_servers() -> Vec<ServerInfo> {
+    let map = SERVER_STATE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(guard) = map.lock() {
+        return guard.values().cloned().collect();
+    }
+    Vec::new()
+}
+
+pub fn server_heartbeat(req: ServerHeartbeatRequest) -> ServerHeartbeatResponse {
+    for room in &req.rooms {
+        room_heartbeat(room.clone());
+    }
+
+    let assigned: Vec<RoomInfo> = list_rooms()
+        .into_iter()
+        .filter(|r| r.server_addr == req.server_addr)
+        .collect();
+
+    let map = SERVER_STATE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(mut guard) = map.lock() {
+        guard.insert(
+            req.server_addr.clone(),
+            ServerInfo {
+                server_addr: req.server_addr.clone(),
+                region: req.region.clone(),
+                max_rooms: req.max_rooms,
+                rooms: assigned.len().min(u16::MAX as usize) as u16,
+                players: req.rooms.iter().map(|r| r.players as u16).sum(),
+                last_seen: now_secs(),
+            },
+        );
+    }
+
+    ServerHeartbeatResponse {
+        rooms: assigned
+            .into_iter()
+            .map(|r| RoomSpec {
+                room_id: r.room_id,
+                name: r.name,
+                max_players: r.max_players,
+                rules: r.rules,
+                mode: r.mode,
+                ticket_only: r.ticket_only,
+            })
+            .collect(),
+    }
+}
+
+// Live stats reported by a game server. Rooms joined directly (never created
+// through the master) get a default listing entry.
+pub fn room_heartbeat(req: HeartbeatRequest) {
+    let base = get_room(&req.room_id).unwrap_or(RoomInfo {
+        room_id: req.room_id.clone(),
+        name: "Local Room".to_owned(),
+        server_addr: req.server_addr.clone(),
+        region: req.region.clone(),
+        players: 0,
+        bots: 0,
+        spectators: 0,
+        max_players: req.max_players,
+        is_private: false,
+        status: RoomStatus::Waiting,
+        ping_ms: Some(1),
+        rules: req.rules.clone(),
+        mode: req.mode,
+        ticket_only: false,
+        allocated: false,
+    });
+    upsert_room(RoomInfo {
+        server_addr: req.server_addr,
+        players: req.players,
+        bots: req.bots,
+        spectators: req.spectators,
+        status: req.status,
+        rules: req.rules,
+        mode: req.mode,
+        ..base
+    });
+}
+
+pub fn prune_servers(max_age_sec: u64) -> Vec<String> {
+    let now = now_secs();
+    let map = SERVER_STATE.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut removed = Vec::new();
+    if let Ok(mut guard) = map.lock() {
+        guard.retain(|addr, s| {
+            let keep = now.saturating_sub(s.last_seen) <= max_age_sec;
+            if !keep {
+                removed.push(addr.clone());
+            }
+            keep
+        });
+    }
+    removed
+}
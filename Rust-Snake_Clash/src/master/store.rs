@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::master::profiles::{BoardStats, Profile, ScoreEntry};
+
+// Master data that survives restarts, kept in memory and rewritten as JSON to
+// `[master] data_path` after every change (temp file + rename, so a crash
+// never leaves half a file). Rooms are not stored: game servers re-register
+// them with their heartbeats.
+
+static STORE: OnceLock<Mutex<StoreData>> = OnceLock::new();
+static STORE_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreData {
+    pub profiles: HashMap<String, Profile>,
+    pub next_guest: u64,
+    pub matches_recorded: u64,
+    // Results of the last days, for the daily and weekly boards.
+    pub recent: Vec<ScoreEntry>,
+    // All-time totals, keyed by `profiles::board_key`.
+    pub all_time: HashMap<String, BoardStats>,
+    // `MatchReport::match_id` -> end time of the matches recorded in the last
+    // `REPORT_MAX_AGE_SEC`, so a report cannot be replayed.
+    pub seen_matches: HashMap<String, u64>,
+}
+
+// Loads `path` (missing file = empty store). Empty path = memory only.
+pub fn init(path: &str) -> Result<(), String> {
+    let path = (!path.trim().is_empty()).then(|| PathBuf::from(path));
+    let data = match &path {
+        Some(p) => load(p)?,
+        None => StoreData::default(),
+    };
+    let _ = STORE_PATH.set(path);
+    let _ = STORE.set(Mutex::new(data));
+    Ok(())
+}
+
+fn load(path: &Path) -> Result<StoreData, String> {
+    if !path.exists() {
+        return Ok(StoreData::default());
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("invalid store {}: {}", path.display(), e))
+}
+
+fn store() -> &'static Mutex<StoreData> {
+    STORE.get_or_init(|| Mutex::new(StoreData::default()))
+}
+
+pub fn read<R>(f: impl FnOnce(&StoreData) -> R) -> Option<R> {
+    store().lock().ok().map(|guard| f(&guard))
+}
+
+// Runs `f` and saves the store.
+pub fn write<R>(f: impl FnOnce(&mut StoreData) -> R) -> Option<R> {
+    let mut guard = store().lock().ok()?;
+    let out = f(&mut guard);
+    if let Some(Some(path)) = STORE_PATH.get()
+        && let Err(err) = save(path, &guard)
+    {
+        eprintln!("master store: {}", err);
+    }
+    Some(out)
+}
+
+fn save(path: &Path, data: &StoreData) -> Result<(), String> {
+    let text = serde_json::to_string(data).map_err(|e| format!("cannot encode store: {}", e))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, text).map_err(|e| format!("cannot write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("cannot replace {}: {}", path.display(), e))
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex, Notify};
 
+use crate::master::profiles::MatchReport;
+use crate::master::state::{HeartbeatRequest, RoomSpec};
 use crate::net::messages::ServerMessage;
 use crate::net::session::{InboundMessage, OutboundMessage, SessionHandle};
 use crate::state::lobby::Lobby;
@@ -10,12 +12,15 @@ use crate::state::lobby::Lobby;
 #[derive(Clone)]
 pub struct DispatcherHandle {
     inner: Arc<Mutex<Dispatcher>>,
+    // Woken when a join waits for a room spec from the master.
+    specs_wanted: Arc<Notify>,
 }
 
 impl DispatcherHandle {
     pub fn new(lobby: Lobby) -> Self {
         Self {
             inner: Arc::new(Mutex::new(Dispatcher::new(lobby))),
+            specs_wanted: Arc::new(Notify::new()),
         }
     }
 
@@ -38,7 +43,15 @@ impl DispatcherHandle {
 
     pub async fn handle_inbound(&self, inbound: InboundMessage) -> Vec<OutboundMessage> {
         let mut guard = self.inner.lock().await;
-        guard.lobby.handle_message(inbound.session_id, inbound.message)
+        let outbound = guard.lobby.handle_message(inbound.session_id, inbound.message);
+        if guard.lobby.take_specs_wanted() {
+            self.specs_wanted.notify_one();
+        }
+        outbound
+    }
+
+    pub async fn specs_wanted(&self) {
+        self.specs_wanted.notified().await;
     }
 
     pub async fn send_outbound(&self, outbound: OutboundMessage) {
@@ -52,6 +65,37 @@ impl DispatcherHandle {
         let mut guard = self.inner.lock().await;
         guard.lobby.tick()
     }
+
+    // (heartbeat number, room stats) of the heartbeat about to be sent.
+    pub async fn room_heartbeats(&self) -> (u64, Vec<HeartbeatRequest>) {
+        let mut guard = self.inner.lock().await;
+        let seq = guard.lobby.begin_heartbeat();
+        (seq, guard.lobby.room_heartbeats())
+    }
+
+    pub async fn take_match_reports(&self) -> Vec<MatchReport> {
+        let mut guard = self.inner.lock().await;
+        guard.lobby.take_match_reports()
+    }
+
+    pub async fn requeue_match_report(&self, report: MatchReport) {
+        let mut guard = self.inner.lock().await;
+        guard.lobby.requeue_match_report(report);
+    }
+
+    // `specs` None: heartbeat `seq` got no answer. Sends the joins it settles.
+    pub async fn apply_room_specs(&self, seq: u64, specs: Option<Vec<RoomSpec>>) {
+        let outbound = {
+            let mut guard = self.inner.lock().await;
+            match specs {
+                Some(specs) => guard.lobby.apply_room_specs(seq, specs),
+                None => guard.lobby.master_unreachable(seq),
+            }
+        };
+        for msg in outbound {
+            self.send_outbound(msg).await;
+        }
+    }
 }
 
 struct Dispatcher {
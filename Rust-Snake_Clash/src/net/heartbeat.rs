@@ -0,0 +1,95 @@
+use tokio::time::{interval, Duration};
+
+use crate::config::settings::ServerSettings;
+use crate::master::auth;
+use crate::master::state::{ServerHeartbeatRequest, ServerHeartbeatResponse};
+use crate::net::dispatcher::DispatcherHandle;
+
+pub const HEARTBEAT_INTERVAL_SEC: u64 = 2;
+
+// Registers this game server with the master, keeps its room list fresh and
+// uploads the results of finished matches.
+// Failures are silent: the master may start after us or restart at any time.
+pub fn spawn(settings: &ServerSettings, dispatcher: DispatcherHandle) {
+    let url = format!("{}/servers/heartbeat", settings.master_url.trim_end_matches('/'));
+    let matches_url = format!("{}/matches", settings.master_url.trim_end_matches('/'));
+    let server_addr = settings.public_addr.clone();
+    let region = settings.region.clone();
+    let max_rooms = settings.max_rooms;
+    let token_secret = settings.token_secret.clone();
+    // The master refuses reports signed with a public secret: they are dropped here.
+    let send_reports = !crate::config::settings::insecure_secret(&token_secret);
+    if !send_reports {
+        eprintln!(
+            "WARNING: server token_secret is empty or the public default: match results are not \
+             reported and join tickets can be forged. Set SNAKE_TOKEN_SECRET or [server] token_secret."
+        );
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(HEARTBEAT_INTERVAL_SEC));
+        loop {
+            // A join waiting for its room spec asks for a beat right away.
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = dispatcher.specs_wanted() => {}
+            }
+            let (seq, rooms) = dispatcher.room_heartbeats().await;
+            let req = ServerHeartbeatRequest {
+                server_addr: server_addr.clone(),
+                region: region.clone(),
+                max_rooms,
+                rooms,
+            };
+            let Ok(body) = serde_json::to_string(&req) else {
+                continue;
+            };
+            let url = url.clone();
+            let signature = auth::mac(&token_secret, auth::HEARTBEAT, &body);
+            let resp = tokio::task::spawn_blocking(move || {
+                ureq::post(&url)
+                    .timeout(std::time::Duration::from_secs(3))
+                    .set("content-type", "application/json")
+                    .set(auth::SIGNATURE_HEADER, &signature)
+                    .send_string(&body)
+                    .ok()?
+                    .into_json::<ServerHeartbeatResponse>()
+                    .ok()
+            })
+            .await;
+            let specs = resp.ok().flatten().map(|resp| resp.rooms);
+            dispatcher.apply_room_specs(seq, specs).await;
+
+            // Results of finished matches, signed with the shared secret. One the
+            // master cannot be reached for is retried next beat; one it refuses is dropped.
+            let reports = dispatcher.take_match_reports().await;
+            for report in reports.into_iter().filter(|_| send_reports) {
+                let Ok(body) = serde_json::to_string(&report) else {
+                    continue;
+                };
+                let url = matches_url.clone();
+                let signature = auth::mac(&token_secret, auth::REPORT, &body);
+                let done = tokio::task::spawn_blocking(move || {
+                    match ureq::post(&url)
+                        .timeout(std::time::Duration::from_secs(3))
+                        .set("content-type", "application/json")
+                        .set(auth::SIGNATURE_HEADER, &signature)
+                        .send_string(&body)
+                    {
+                        Ok(_) => true,
+                        Err(ureq::Error::Status(code, _)) => {
+                            eprintln!("master refused match report: HTTP {}", code);
+                            true
+                        }
+                        Err(_) => false,
+                    }
+                })
+                .await
+                .unwrap_or(false);
+                if !done {
+                    dispatcher.requeue_match_report(report).await;
+                }
+            }
+        }
+    });
+}
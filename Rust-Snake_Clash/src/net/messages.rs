@@ -1,7 +1,19 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config::rules::GameRules;
+use crate::game::collision::DeathCause;
+use crate::game::effects::ActiveEffect;
+use crate::game::mode::GameMode;
+use crate::game::skin::Skin;
+
 pub const PROTOCOL_VERSION: u8 = 1;
 
+// `JoinReq.device` of scripted clients; the server flags their snake `is_bot`.
+pub const BOT_DEVICE: &str = "bot";
+
+// Quick emotes; `Emote.id` indexes this list.
+pub const EMOTES: &[&str] = &["GG", "HI", "LOL", "WOW", "OOPS", "GRR"];
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Vec2f {
     pub x: f32,
@@ -30,6 +42,17 @@ pub enum ClientMessage {
         name: String,
         device: String,
         client_time: f32,
+        #[serde(default)]
+        skin: Skin,
+        // Master join ticket (`/matchmake`, `/queue`, `/rooms/join`); required by queue rooms.
+        #[serde(default)]
+        ticket: Option<String>,
+        // Master profile token (`POST /players`); the match result updates that profile.
+        #[serde(default)]
+        profile: Option<String>,
+        // Watch the room without a snake: no capacity used, inputs ignored.
+        #[serde(default)]
+        spectator: bool,
     },
     Input {
         seq: u32,
@@ -42,6 +65,14 @@ pub enum ClientMessage {
     Ping {
         client_time: f32,
     },
+    // Room chat; accepted during the countdown and once the match is over.
+    Chat {
+        text: String,
+    },
+    // Bubble over our snake (`EMOTES` index); accepted any time.
+    Emote {
+        id: u8,
+    },
     Leave,
 }
 
@@ -49,37 +80,90 @@ pub enum ClientMessage {
 #[serde(tag = "t", content = "data", rename_all = "snake_case")]
 pub enum ServerMessage {
     JoinOk {
+        // 0 for spectators.
         player_id: u32,
         tick_rate: u16,
         server_tick: u32,
         arena: ArenaInfo,
+        // Tuning of the joined room; the client renders/predicts with it.
+        rules: Box<GameRules>,
+        mode: GameMode,
+        // Everyone in the room, this player included.
+        #[serde(default)]
+        players: Vec<PlayerInfo>,
+        #[serde(default)]
+        spectator: bool,
     },
     Snapshot {
         server_tick: u32,
         players: Vec<PlayerState>,
         pellets: Vec<Vec2f>,
+        // Team of each pellet (same order as `pellets`); empty outside team mode.
+        #[serde(default)]
+        pellet_teams: Vec<u8>,
         tokens: Vec<TokenState>,
         events: Vec<Event>,
         time_left: f32,
         countdown_left: f32,
+        // Battle royale safe zone (current / target radius); the arena radius otherwise.
+        #[serde(default)]
+        zone_radius: f32,
+        #[serde(default)]
+        zone_next_radius: f32,
     },
     SnapshotDelta {
         server_tick: u32,
         base_tick: u32,
         players: Vec<PlayerDelta>,
         pellets: Vec<Vec2f>,
+        // Team of each pellet (same order as `pellets`); empty outside team mode.
+        #[serde(default)]
+        pellet_teams: Vec<u8>,
         tokens: Vec<TokenState>,
         events: Vec<Event>,
         time_left: f32,
         countdown_left: f32,
+        // Battle royale safe zone (current / target radius); the arena radius otherwise.
+        #[serde(default)]
+        zone_radius: f32,
+        #[serde(default)]
+        zone_next_radius: f32,
     },
     Pong {
         server_time: f32,
         client_time: f32,
     },
+    // Sent to the room when a player or a server bot joins.
+    PlayerJoined {
+        player: PlayerInfo,
+    },
     PlayerLeft {
         id: u32,
     },
+    // Endless rooms: best lengths since the room opened (sent on change and on join).
+    Leaderboard {
+        entries: Vec<LeaderboardEntry>,
+    },
+    // Chat line of a player of the room (sender included), already filtered.
+    Chat {
+        from: u32,
+        name: String,
+        text: String,
+    },
+    Emote {
+        from: u32,
+        id: u8,
+    },
+}
+
+// Fixed per player: sent once (`JoinOk`, `PlayerJoined`) instead of every snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub id: u32,
+    // Sanitized by the server (`game::skin::sanitize_name`).
+    pub name: String,
+    #[serde(default)]
+    pub skin: Skin,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,6 +181,17 @@ pub struct PlayerState {
     pub radius: f32,
     pub score: i32,
     pub boost: f32,
+    // 1..=4 in team mode, 0 otherwise.
+    #[serde(default)]
+    pub team: u8,
+    // Server-side bot filling the room, or an external bot client.
+    #[serde(default)]
+    pub is_bot: bool,
+    #[serde(default)]
+    pub kills: u32,
+    // Running power-ups; clients count them down between updates.
+    #[serde(default)]
+    pub effects: Vec<ActiveEffect>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,6 +204,14 @@ pub struct PlayerDelta {
     pub radius: Option<f32>,
     pub score: Option<i32>,
     pub boost: Option<f32>,
+    #[serde(default)]
+    pub team: Option<u8>,
+    #[serde(default)]
+    pub is_bot: Option<bool>,
+    #[serde(default)]
+    pub kills: Option<u32>,
+    #[serde(default)]
+    pub effects: Option<Vec<ActiveEffect>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,8 +222,20 @@ pub struct TokenState {
     pub ttl: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub id: u32,
+    pub name: String,
+    pub length: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Event {
     pub kind: String,
     pub id: u32,
+    // `death` / `kill` (id = victim): player credited with the kill, and why.
+    #[serde(default)]
+    pub killer: Option<u32>,
+    #[serde(default)]
+    pub cause: Option<DeathCause>,
 }
@@ -3,3 +3,4 @@ pub mod messages;
 pub mod codec;
 pub mod session;
 pub mod dispatcher;
+pub mod heartbeat;
@@ -14,13 +14,13 @@ use crate::net::session::{InboundMessage, SessionHandle};
 pub struct WsServer;
 
 impl WsServer {
-    pub async fn serve(addr: &str, dispatcher: DispatcherHandle) -> tokio::io::Result<()> {
+    pub async fn serve(addr: &str, dispatcher: DispatcherHandle, tick_rate: u16) -> tokio::io::Result<()> {
         let listener = TcpListener::bind(addr).await?;
         let mut next_id: u64 = 1;
 
         let tick_dispatcher = dispatcher.clone();
         tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_millis(50));
+            let mut ticker = interval(Duration::from_secs_f32(1.0 / tick_rate.max(1) as f32));
             loop {
                 ticker.tick().await;
                 let outbound = tick_dispatcher.tick().await;
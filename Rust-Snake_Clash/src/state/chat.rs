@@ -0,0 +1,212 @@
+use std::time::Instant;
+
+// In-room chat and emotes: message cleanup, per-session rate limits and the
+// word filter `Lobby` runs on every chat line (swappable with `set_chat_filter`).
+
+pub const MAX_CHAT_LEN: usize = 120;
+// Token buckets per session: a short burst, then one message per refill interval.
+pub const CHAT_BURST: f32 = 3.0;
+pub const CHAT_REFILL_SEC: f32 = 2.0;
+pub const EMOTE_BURST: f32 = 3.0;
+pub const EMOTE_REFILL_SEC: f32 = 1.0;
+
+pub trait ChatFilter: Send {
+    // Text to relay (words may be masked), or None to drop the message.
+    fn filter(&self, text: &str) -> Option<String>;
+}
+
+pub struct NoFilter;
+
+impl ChatFilter for NoFilter {
+    fn filter(&self, text: &str) -> Option<String> {
+        Some(text.to_owned())
+    }
+}
+
+// Masks whole words equal to a blocked word, ignoring case and punctuation:
+// "Darn!", "D.A.R.N" and "oh,darn" are caught, "darning" is not.
+pub struct WordMask {
+    words: Vec<String>,
+}
+
+impl WordMask {
+    pub fn new(words: &[String]) -> Self {
+        Self {
+            words: words.iter().map(|w| plain(w)).filter(|w| !w.is_empty()).collect(),
+        }
+    }
+
+    fn blocked(&self, word: &str) -> bool {
+        let lower = word.to_lowercase();
+        self.words.contains(&plain(&lower))
+            || lower
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|part| self.words.iter().any(|w| w == part))
+    }
+}
+
+fn plain(word: &str) -> String {
+    word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+impl ChatFilter for WordMask {
+    fn filter(&self, text: &str) -> Option<String> {
+        let words: Vec<String> = text
+            .split(' ')
+            .map(|word| {
+                if self.blocked(word) {
+                    word.chars().map(|c| if c.is_alphanumeric() { '*' } else { c }).collect()
+                } else {
+                    word.to_owned()
+                }
+            })
+            .collect();
+        Some(words.join(" "))
+    }
+}
+
+pub fn filter_from_blocklist(words: &[String]) -> Box<dyn ChatFilter> {
+    if words.iter().all(|w| w.trim().is_empty()) {
+        Box::new(NoFilter)
+    } else {
+        Box::new(WordMask::new(words))
+    }
+}
+
+// Blocklist file: one word per line, `#` starts a comment.
+pub fn parse_blocklist(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|word| !word.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+// Filter for `[server] chat_blocklist_path`; empty path = no filter.
+pub fn load_blocklist(path: &str) -> Result<Box<dyn ChatFilter>, String> {
+    if path.trim().is_empty() {
+        return Ok(Box::new(NoFilter));
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read chat blocklist {}: {}", path, e))?;
+    Ok(filter_from_blocklist(&parse_blocklist(&text)))
+}
+
+// Printable characters with single spaces, at most `MAX_CHAT_LEN`; None if nothing is left.
+pub fn sanitize_chat(raw: &str) -> Option<String> {
+    let mut out = String::new();
+    for c in raw.chars() {
+        if out.chars().count() >= MAX_CHAT_LEN {
+            break;
+        }
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') {
+                out.push(' ');
+            }
+        } else if !c.is_control() {
+            out.push(c);
+        }
+    }
+    let out = out.trim_end().to_owned();
+    (!out.is_empty()).then_some(out)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f32,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(burst: f32, now: Instant) -> Self {
+        Self { tokens: burst, last: now }
+    }
+
+    fn take(&mut self, burst: f32, refill_sec: f32, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f32();
+        self.tokens = (self.tokens + elapsed / refill_sec).min(burst);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Chat and emote allowance of one session.
+#[derive(Debug, Clone, Copy)]
+pub struct ChatBudget {
+    chat: Bucket,
+    emote: Bucket,
+}
+
+impl ChatBudget {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            chat: Bucket::new(CHAT_BURST, now),
+            emote: Bucket::new(EMOTE_BURST, now),
+        }
+    }
+
+    pub fn allow_chat(&mut self, now: Instant) -> bool {
+        self.chat.take(CHAT_BURST, CHAT_REFILL_SEC, now)
+    }
+
+    pub fn allow_emote(&mut self, now: Instant) -> bool {
+        self.emote.take(EMOTE_BURST, EMOTE_REFILL_SEC, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn mask(words: &[&str]) -> WordMask {
+        WordMask::new(&words.iter().map(|w| (*w).to_owned()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn sanitize_chat_keeps_printable_text_on_one_line() {
+        assert_eq!(sanitize_chat("  hi\t\tthere \n you\u{7}  ").as_deref(), Some("hi there you"));
+        assert_eq!(sanitize_chat(" \n\t "), None);
+        assert_eq!(sanitize_chat("\u{1b}"), None);
+        let long = sanitize_chat(&"x".repeat(MAX_CHAT_LEN + 10)).unwrap();
+        assert_eq!(long.chars().count(), MAX_CHAT_LEN);
+    }
+
+    #[test]
+    fn chat_budget_allows_a_burst_then_refills() {
+        let start = Instant::now();
+        let mut budget = ChatBudget::new(start);
+        for _ in 0..CHAT_BURST as usize {
+            assert!(budget.allow_chat(start));
+        }
+        assert!(!budget.allow_chat(start));
+        assert!(budget.allow_emote(start));
+        let later = start + Duration::from_secs_f32(CHAT_REFILL_SEC);
+        assert!(budget.allow_chat(later));
+        assert!(!budget.allow_chat(later));
+        let idle = start + Duration::from_secs(60);
+        for _ in 0..CHAT_BURST as usize {
+            assert!(budget.allow_chat(idle));
+        }
+        assert!(!budget.allow_chat(idle));
+    }
+
+    #[test]
+    fn word_mask_matches_whole_words_only() {
+        let filter = mask(&["darn", "Heck"]);
+        assert_eq!(filter.filter("Darn! that heck").as_deref(), Some("****! that ****"));
+        assert_eq!(filter.filter("D.A.R.N oh,heck").as_deref(), Some("*.*.*.* **,****"));
+        assert_eq!(filter.filter("darning hecking sundarn").as_deref(), Some("darning hecking sundarn"));
+    }
+
+    #[test]
+    fn blocklist_files_skip_comments_and_blank_lines() {
+        assert_eq!(parse_blocklist("# words\ndarn\n\n  heck  # mild\n"), vec!["darn", "heck"]);
+        let none = filter_from_blocklist(&parse_blocklist("# none\n"));
+        assert_eq!(none.filter("darn").as_deref(), Some("darn"));
+    }
+}
@@ -1,15 +1,22 @@
-use crate::config::ARENA_RADIUS;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
 
 use macroquad::prelude::*;
 
 use crate::net::messages::{
     ArenaInfo, ClientMessage, PlayerDelta, PlayerState, ServerMessage, TokenState, Vec2f,
+    BOT_DEVICE, EMOTES,
 };
-use crate::game::food::TokenKind;
-use crate::state::room::InputState;
-use crate::master::state::{RoomInfo, RoomStatus};
+use crate::game::effects::ActiveEffect;
+use crate::game::sim::InputState;
+use crate::config::rules::GameRules;
+use crate::config::settings::ServerSettings;
+use crate::game::mode::GameMode;
+use crate::master::auth;
+use crate::master::profiles::MatchReport;
+use crate::master::state::{self, HeartbeatRequest, RoomSpec, RoomStatus};
 use crate::net::session::OutboundMessage;
+use crate::state::chat::{self, ChatBudget, ChatFilter};
 use crate::state::room::Room;
 
 // Rooms / matchmaking (minimal, functional skeleton).
@@ -19,56 +26,188 @@ pub struct Lobby {
     last_snapshot_ack: HashMap<u64, u32>,
     last_snapshot: HashMap<u64, SnapshotCache>,
     session_rooms: HashMap<u64, String>,
+    // Sessions watching a room without a snake (also in `session_rooms`).
+    spectators: HashSet<u64>,
+    // Rooms the master allocated to this server (from heartbeat responses).
+    room_specs: HashMap<String, RoomSpec>,
+    tick_rate: u16,
+    public_addr: String,
+    region: String,
+    // Rules for rooms the master did not specify (and for direct joins).
+    default_rules: String,
+    rules_dir: Option<String>,
+    default_mode: GameMode,
+    // Server-side bots fill each room up to this many snakes (0 = off).
+    bot_fill: u8,
+    // Verifies join tickets and profile tokens; empty = neither is checked.
+    token_secret: String,
+    // Finished matches, until the heartbeat task sends them to the master.
+    match_reports: Vec<MatchReport>,
+    chat_filter: Box<dyn ChatFilter>,
+    chat_budgets: HashMap<u64, ChatBudget>,
+    // Joins for unknown rooms, until the master says whether it allocated them.
+    pending_joins: Vec<PendingJoin>,
+    // Heartbeats started so far; a pending join is settled by the answer (or
+    // failure) of one started after it arrived.
+    heartbeat_seq: u64,
+    // Last heartbeat got an answer. While the master is down, joins without a
+    // ticket open rooms on the server defaults right away.
+    master_up: bool,
+    // A pending join asks for a heartbeat before the next interval.
+    specs_wanted: bool,
 }
 
+struct PendingJoin {
+    session_id: u64,
+    msg: ClientMessage,
+    after: u64,
+}
+
+const DEFAULT_MAX_PLAYERS: u8 = 4;
+const MAX_PENDING_REPORTS: usize = 64;
+
 impl Lobby {
-    pub fn new() -> Self {
+    pub fn new(settings: &ServerSettings) -> Self {
         Self {
             rooms: HashMap::new(),
             next_room_id: 1,
             last_snapshot_ack: HashMap::new(),
             last_snapshot: HashMap::new(),
             session_rooms: HashMap::new(),
+            spectators: HashSet::new(),
+            room_specs: HashMap::new(),
+            tick_rate: settings.tick_rate,
+            public_addr: settings.public_addr.clone(),
+            region: settings.region.clone(),
+            default_rules: settings.rules.clone(),
+            rules_dir: settings.rules_dir.clone(),
+            default_mode: settings.mode,
+            bot_fill: settings.bot_fill,
+            token_secret: settings.token_secret.clone(),
+            match_reports: Vec::new(),
+            chat_filter: Box::new(chat::NoFilter),
+            chat_budgets: HashMap::new(),
+            pending_joins: Vec::new(),
+            heartbeat_seq: 0,
+            master_up: true,
+            specs_wanted: false,
+        }
+    }
+
+    // Chat filter: the `[server] chat_blocklist_path` word mask, or an embedder's own.
+    pub fn set_chat_filter(&mut self, filter: Box<dyn ChatFilter>) {
+        self.chat_filter = filter;
+    }
+
+    // Tickets are checked whenever one is sent; queue rooms refuse joins without one.
+    fn check_ticket(&self, room_id: &str, ticket: Option<&str>) -> Result<(), String> {
+        if self.token_secret.is_empty() {
+            return Ok(());
+        }
+        let required = self.room_specs.get(room_id).is_some_and(|s| s.ticket_only);
+        match ticket.filter(|t| !t.is_empty()) {
+            Some(ticket) => {
+                auth::check_join_ticket(&self.token_secret, ticket, room_id, state::now_secs())
+            }
+            None if required => Err("join ticket required".to_owned()),
+            None => Ok(()),
+        }
+    }
+
+    // Number of the heartbeat about to be sent (see `apply_room_specs`).
+    pub fn begin_heartbeat(&mut self) -> u64 {
+        self.specs_wanted = false;
+        self.heartbeat_seq += 1;
+        self.heartbeat_seq
+    }
+
+    pub fn take_specs_wanted(&mut self) -> bool {
+        std::mem::take(&mut self.specs_wanted)
+    }
+
+    // Answer to heartbeat `seq`: the rooms the master allocated to this server.
+    pub fn apply_room_specs(&mut self, seq: u64, specs: Vec<RoomSpec>) -> Vec<OutboundMessage> {
+        self.room_specs = specs
+            .into_iter()
+            .map(|spec| (spec.room_id.clone(), spec))
+            .collect();
+        self.master_up = true;
+        self.settle_pending_joins(seq)
+    }
+
+    // Heartbeat `seq` got no answer.
+    pub fn master_unreachable(&mut self, seq: u64) -> Vec<OutboundMessage> {
+        self.master_up = false;
+        self.settle_pending_joins(seq)
+    }
+
+    fn settle_pending_joins(&mut self, seq: u64) -> Vec<OutboundMessage> {
+        let (settled, waiting): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.pending_joins).into_iter().partition(|p| p.after < seq);
+        self.pending_joins = waiting;
+        settled
+            .into_iter()
+            .flat_map(|p| self.handle_join(p.session_id, p.msg, true))
+            .collect()
+    }
+
+    pub fn take_match_reports(&mut self) -> Vec<MatchReport> {
+        std::mem::take(&mut self.match_reports)
+    }
+
+    // Master unreachable: kept for the next heartbeat (bounded, oldest dropped).
+    pub fn requeue_match_report(&mut self, report: MatchReport) {
+        self.match_reports.push(report);
+        if self.match_reports.len() > MAX_PENDING_REPORTS {
+            self.match_reports.remove(0);
+        }
+    }
+
+    pub fn room_heartbeats(&self) -> Vec<HeartbeatRequest> {
+        // (players, spectators) per room.
+        let mut counts: HashMap<&str, (u8, u8)> = HashMap::new();
+        for (session_id, room_id) in &self.session_rooms {
+            let entry = counts.entry(room_id.as_str()).or_insert((0, 0));
+            if self.spectators.contains(session_id) {
+                entry.1 = entry.1.saturating_add(1);
+            } else {
+                entry.0 = entry.0.saturating_add(1);
+            }
         }
+
+        let updated_at = crate::master::state::now_secs();
+        self.rooms
+            .iter()
+            .map(|(room_id, room)| {
+                let (players, spectators) = counts.get(room_id.as_str()).copied().unwrap_or((0, 0));
+                HeartbeatRequest {
+                    server_addr: self.public_addr.clone(),
+                    room_id: room_id.clone(),
+                    players,
+                    bots: room.bot_session_count().min(u8::MAX as usize) as u8,
+                    spectators,
+                    max_players: room.max_players,
+                    status: if room.finished() {
+                        RoomStatus::Finished
+                    } else if players == 0 {
+                        RoomStatus::Waiting
+                    } else if room.countdown_left() > 0.0 {
+                        RoomStatus::Starting
+                    } else {
+                        RoomStatus::Running
+                    },
+                    region: self.region.clone(),
+                    updated_at,
+                    rules: room.rules.name.clone(),
+                    mode: room.mode,
+                }
+            })
+            .collect()
     }
 
     pub fn handle_message(&mut self, session_id: u64, msg: ClientMessage) -> Vec<OutboundMessage> {
         match msg {
-            ClientMessage::JoinReq { room_id, .. } => {
-                let room = self
-                    .rooms
-                    .entry(room_id.clone())
-                    .or_insert_with(|| {
-                        let id = self.next_room_id;
-                        self.next_room_id = self.next_room_id.saturating_add(1);
-                        Room::new(id, 20)
-                    });
-                let player_id = room.add_player(session_id);
-                if player_id == 0 {
-                    return Vec::new();
-                }
-                self.session_rooms.insert(session_id, room_id);
-                self.last_snapshot_ack.insert(session_id, 0);
-                self.last_snapshot.insert(
-                    session_id,
-                    SnapshotCache {
-                        tick: 0,
-                        players: Vec::new(),
-                    },
-                );
-                vec![OutboundMessage {
-                    session_id,
-                    message: ServerMessage::JoinOk {
-                        player_id,
-                        tick_rate: room.tick_rate,
-                        server_tick: 0,
-                        arena: ArenaInfo {
-                            radius: ARENA_RADIUS,
-                            seed: 42,
-                        },
-                    },
-                }]
-            }
+            msg @ ClientMessage::JoinReq { .. } => self.handle_join(session_id, msg, false),
             ClientMessage::Ping { client_time } => vec![OutboundMessage {
                 session_id,
                 message: ServerMessage::Pong {
@@ -77,6 +216,8 @@ impl Lobby {
                 },
             }],
             ClientMessage::Leave => self.handle_disconnect(session_id),
+            ClientMessage::Chat { text } => self.handle_chat(session_id, &text),
+            ClientMessage::Emote { id } => self.handle_emote(session_id, id),
             ClientMessage::Input {
                 dir,
                 boost,
@@ -86,36 +227,217 @@ impl Lobby {
                 if let Some(ack) = last_snapshot_ack {
                     self.last_snapshot_ack.insert(session_id, ack);
                 }
-                if let Some(room_id) = self.session_rooms.get(&session_id).cloned() {
-                    if let Some(room) = self.rooms.get_mut(&room_id) {
-                        room.set_input(
-                            session_id,
-                            InputState {
-                                dir: vec2(dir.x, dir.y),
-                                boost,
-                            },
-                        );
-                    }
+                if let Some(room_id) = self.session_rooms.get(&session_id).cloned()
+                    && let Some(room) = self.rooms.get_mut(&room_id)
+                {
+                    room.set_input(
+                        session_id,
+                        InputState {
+                            dir: vec2(dir.x, dir.y),
+                            boost,
+                        },
+                    );
                 }
                 Vec::new()
             }
         }
     }
 
+    // Players only: spectators read the chat but do not post.
+    // A join for a room this server does not run yet waits (`settled` false)
+    // until the master has answered a heartbeat started after it arrived.
+    fn handle_join(&mut self, session_id: u64, msg: ClientMessage, settled: bool) -> Vec<OutboundMessage> {
+        let ClientMessage::JoinReq { room_id, name, device, client_time, skin, ticket, profile, spectator } = msg else {
+            return Vec::new();
+        };
+        // Only players open rooms: a spectator of an unknown room would
+        // start an empty room (bots included) that ticks for nobody.
+        if spectator && !self.rooms.contains_key(&room_id) {
+            eprintln!("room {}: join refused: no such room to watch", room_id);
+            return Vec::new();
+        }
+        let has_ticket = ticket.as_deref().is_some_and(|t| !t.is_empty());
+        if !self.rooms.contains_key(&room_id) && !self.room_specs.contains_key(&room_id) {
+            // The master may have just allocated this room: its spec comes with
+            // the next heartbeat answer, asked for right away. Rooms it created
+            // never start on the server defaults.
+            if !settled && (self.master_up || has_ticket) {
+                self.pending_joins.push(PendingJoin {
+                    session_id,
+                    msg: ClientMessage::JoinReq { room_id, name, device, client_time, skin, ticket, profile, spectator },
+                    after: self.heartbeat_seq,
+                });
+                self.specs_wanted = true;
+                return Vec::new();
+            }
+            if has_ticket {
+                eprintln!("room {}: join refused: room unknown to the master", room_id);
+                return Vec::new();
+            }
+        }
+        if let Err(err) = self.check_ticket(&room_id, ticket.as_deref()) {
+            eprintln!("room {}: join refused: {}", room_id, err);
+            return Vec::new();
+        }
+        if !self.rooms.contains_key(&room_id) {
+            let spec = self.room_specs.get(&room_id);
+            let max_players = spec.map(|s| s.max_players).unwrap_or(DEFAULT_MAX_PLAYERS);
+            let mode = spec.map(|s| s.mode).unwrap_or(self.default_mode);
+            let rules_dir = self.rules_dir.as_deref();
+            let rules = match spec {
+                Some(spec) => GameRules::resolve_named(&spec.rules, rules_dir),
+                None => GameRules::resolve(&self.default_rules, rules_dir),
+            }
+            .unwrap_or_else(|err| {
+                    eprintln!("room {}: {}, using classic rules", room_id, err);
+                    GameRules::classic()
+                });
+            let id = self.next_room_id;
+            self.next_room_id = self.next_room_id.saturating_add(1);
+            let mut room = Room::new(id, self.tick_rate, max_players, rules, mode);
+            room.set_bot_fill(self.bot_fill);
+            self.rooms.insert(room_id.clone(), room);
+        }
+        let Some(room) = self.rooms.get_mut(&room_id) else {
+            return Vec::new();
+        };
+        let player_id = if spectator {
+            self.spectators.insert(session_id);
+            0
+        } else {
+            let player_id = room.add_player(session_id, &name, skin, device == BOT_DEVICE);
+            if player_id == 0 {
+                return Vec::new();
+            }
+            player_id
+        };
+        if !spectator && !self.token_secret.is_empty() {
+            let profile_id = profile
+                .as_deref()
+                .and_then(|token| auth::check_profile_token(&self.token_secret, token, state::now_secs()));
+            room.set_profile_id(session_id, profile_id);
+        }
+        self.session_rooms.insert(session_id, room_id);
+        self.last_snapshot_ack.insert(session_id, 0);
+        self.last_snapshot.insert(
+            session_id,
+            SnapshotCache {
+                tick: 0,
+                players: Vec::new(),
+            },
+        );
+        let mut outbound = vec![OutboundMessage {
+            session_id,
+            message: ServerMessage::JoinOk {
+                player_id,
+                tick_rate: room.tick_rate,
+                server_tick: 0,
+                arena: ArenaInfo {
+                    radius: room.rules.arena_radius,
+                    seed: 42,
+                },
+                rules: Box::new(room.rules.clone()),
+                mode: room.mode,
+                players: room.player_infos(),
+                spectator,
+            },
+        }];
+        if room.mode.respawns() {
+            outbound.push(OutboundMessage {
+                session_id,
+                message: ServerMessage::Leaderboard {
+                    entries: room.leaderboard().to_vec(),
+                },
+            });
+        }
+        outbound
+    }
+
+    fn handle_chat(&mut self, session_id: u64, text: &str) -> Vec<OutboundMessage> {
+        let Some(room_id) = self.session_rooms.get(&session_id) else {
+            return Vec::new();
+        };
+        let Some(room) = self.rooms.get(room_id) else {
+            return Vec::new();
+        };
+        let Some(player) = room.player_by_session(session_id) else {
+            return Vec::new();
+        };
+        if !room.chat_open() {
+            return Vec::new();
+        }
+        let budget = self
+            .chat_budgets
+            .entry(session_id)
+            .or_insert_with(|| ChatBudget::new(Instant::now()));
+        if !budget.allow_chat(Instant::now()) {
+            return Vec::new();
+        }
+        let Some(text) = chat::sanitize_chat(text).and_then(|t| self.chat_filter.filter(&t)) else {
+            return Vec::new();
+        };
+        let message = ServerMessage::Chat {
+            from: player.id,
+            name: player.name.clone(),
+            text,
+        };
+        self.room_broadcast(room_id, message)
+    }
+
+    fn handle_emote(&mut self, session_id: u64, id: u8) -> Vec<OutboundMessage> {
+        if id as usize >= EMOTES.len() {
+            return Vec::new();
+        }
+        let Some(room_id) = self.session_rooms.get(&session_id) else {
+            return Vec::new();
+        };
+        let Some(player) = self.rooms.get(room_id).and_then(|room| room.player_by_session(session_id)) else {
+            return Vec::new();
+        };
+        let budget = self
+            .chat_budgets
+            .entry(session_id)
+            .or_insert_with(|| ChatBudget::new(Instant::now()));
+        if !budget.allow_emote(Instant::now()) {
+            return Vec::new();
+        }
+        self.room_broadcast(room_id, ServerMessage::Emote { from: player.id, id })
+    }
+
+    // Every session in the room, players and spectators.
+    fn room_broadcast(&self, room_id: &str, message: ServerMessage) -> Vec<OutboundMessage> {
+        self.session_rooms
+            .iter()
+            .filter(|(_, r)| r.as_str() == room_id)
+            .map(|(session_id, _)| OutboundMessage {
+                session_id: *session_id,
+                message: message.clone(),
+            })
+            .collect()
+    }
+
     pub fn handle_disconnect(&mut self, session_id: u64) -> Vec<OutboundMessage> {
         let mut outbound = Vec::new();
-        let room_id = self.session_rooms.remove(&session_id);
-        if let Some(room_id) = room_id {
-            if let Some(room) = self.rooms.get_mut(&room_id) {
-                if let Some(player_id) = room.remove_player(session_id) {
-                    self.last_snapshot_ack.remove(&session_id);
-                    self.last_snapshot.remove(&session_id);
-                    for other in room.session_ids() {
-                        outbound.push(OutboundMessage {
-                            session_id: other,
-                            message: ServerMessage::PlayerLeft { id: player_id },
-                        });
-                    }
+        self.chat_budgets.remove(&session_id);
+        self.pending_joins.retain(|p| p.session_id != session_id);
+        let Some(room_id) = self.session_rooms.remove(&session_id) else {
+            return outbound;
+        };
+        self.last_snapshot_ack.remove(&session_id);
+        self.last_snapshot.remove(&session_id);
+        if self.spectators.remove(&session_id) {
+            return outbound;
+        }
+        if let Some(room) = self.rooms.get_mut(&room_id)
+            && let Some(player_id) = room.remove_player(session_id)
+        {
+            // Players and spectators still in the room.
+            for (other, other_room) in &self.session_rooms {
+                if *other_room == room_id {
+                    outbound.push(OutboundMessage {
+                        session_id: *other,
+                        message: ServerMessage::PlayerLeft { id: player_id },
+                    });
                 }
             }
         }
@@ -141,46 +463,88 @@ impl Lobby {
                 let players = room.player_states();
                 let server_tick = room.world.server_tick;
                 let pellets = build_pellets(room);
+                let pellet_teams = build_pellet_teams(room);
                 let tokens = build_tokens(room);
                 let events = room.take_events();
+                let (joined, left) = room.take_roster_changes();
+                for session_id in &sessions {
+                    for player in &joined {
+                        outbound.push(OutboundMessage {
+                            session_id: *session_id,
+                            message: ServerMessage::PlayerJoined { player: player.clone() },
+                        });
+                    }
+                    for id in &left {
+                        outbound.push(OutboundMessage {
+                            session_id: *session_id,
+                            message: ServerMessage::PlayerLeft { id: *id },
+                        });
+                    }
+                }
+                if let Some(players) = room.take_final_standings() {
+                    self.match_reports.push(MatchReport {
+                        room_id: room_id.clone(),
+                        mode: room.mode,
+                        region: self.region.clone(),
+                        ended_at: crate::master::state::now_secs(),
+                        players,
+                    });
+                }
+                if let Some(entries) = room.take_leaderboard_update() {
+                    for session_id in &sessions {
+                        outbound.push(OutboundMessage {
+                            session_id: *session_id,
+                            message: ServerMessage::Leaderboard {
+                                entries: entries.clone(),
+                            },
+                        });
+                    }
+                }
                 let time_left = room.time_left();
                 let countdown_left = room.countdown_left();
+                let (zone_radius, zone_next_radius) = room.zone_radii();
 
                 for session_id in sessions {
                     let last_ack = self.last_snapshot_ack.get(&session_id).copied().unwrap_or(0);
-                    if let Some(cache) = self.last_snapshot.get(&session_id) {
-                        if last_ack == cache.tick {
-                            let delta_players = build_player_deltas(&cache.players, &players);
-                            let message = ServerMessage::SnapshotDelta {
-                                server_tick,
-                                base_tick: cache.tick,
-                                players: delta_players,
-                                pellets: pellets.clone(),
-                                tokens: tokens.clone(),
-                                events: events.clone(),
-                                time_left,
-                                countdown_left,
-                            };
-                            outbound.push(OutboundMessage { session_id, message });
-                            self.last_snapshot.insert(
-                                session_id,
-                                SnapshotCache {
-                                    tick: server_tick,
-                                    players: players.clone(),
-                                },
-                            );
-                            continue;
-                        }
+                    if let Some(cache) = self.last_snapshot.get(&session_id)
+                        && last_ack == cache.tick
+                    {
+                        let delta_players = build_player_deltas(&cache.players, &players);
+                        let message = ServerMessage::SnapshotDelta {
+                            server_tick,
+                            base_tick: cache.tick,
+                            players: delta_players,
+                            pellets: pellets.clone(),
+                            pellet_teams: pellet_teams.clone(),
+                            tokens: tokens.clone(),
+                            events: events.clone(),
+                            time_left,
+                            countdown_left,
+                            zone_radius,
+                            zone_next_radius,
+                        };
+                        outbound.push(OutboundMessage { session_id, message });
+                        self.last_snapshot.insert(
+                            session_id,
+                            SnapshotCache {
+                                tick: server_tick,
+                                players: players.clone(),
+                            },
+                        );
+                        continue;
                     }
 
                     let message = ServerMessage::Snapshot {
                         server_tick,
                         players: players.clone(),
                         pellets: pellets.clone(),
+                        pellet_teams: pellet_teams.clone(),
                         tokens: tokens.clone(),
                         events: events.clone(),
                         time_left,
                         countdown_left,
+                        zone_radius,
+                        zone_next_radius,
                     };
                     outbound.push(OutboundMessage { session_id, message });
                     self.last_snapshot.insert(
@@ -194,8 +558,6 @@ impl Lobby {
             }
         }
 
-        publish_room_stats(&self.session_rooms);
-
         outbound
     }
 }
@@ -212,6 +574,20 @@ const MASK_DIR: u16 = 1 << 2;
 const MASK_RADIUS: u16 = 1 << 3;
 const MASK_SCORE: u16 = 1 << 4;
 const MASK_BOOST: u16 = 1 << 5;
+const MASK_TEAM: u16 = 1 << 6;
+const MASK_IS_BOT: u16 = 1 << 7;
+const MASK_KILLS: u16 = 1 << 8;
+const MASK_EFFECTS: u16 = 1 << 9;
+
+// Clients count effect timers down themselves: only a new, ended or renewed
+// effect is sent.
+fn effects_changed(prev: &[ActiveEffect], next: &[ActiveEffect]) -> bool {
+    prev.len() != next.len()
+        || prev
+            .iter()
+            .zip(next)
+            .any(|(a, b)| a.kind != b.kind || b.left > a.left)
+}
 
 fn build_player_deltas(prev: &[PlayerState], next: &[PlayerState]) -> Vec<PlayerDelta> {
     let mut prev_map: HashMap<u32, &PlayerState> = HashMap::new();
@@ -231,6 +607,10 @@ fn build_player_deltas(prev: &[PlayerState], next: &[PlayerState]) -> Vec<Player
             radius: None,
             score: None,
             boost: None,
+            team: None,
+            is_bot: None,
+            kills: None,
+            effects: None,
         };
 
         if let Some(prev_p) = prev_map.get(&p.id) {
@@ -258,14 +638,43 @@ fn build_player_deltas(prev: &[PlayerState], next: &[PlayerState]) -> Vec<Player
                 mask |= MASK_BOOST;
                 delta.boost = Some(p.boost);
             }
+            if prev_p.team != p.team {
+                mask |= MASK_TEAM;
+                delta.team = Some(p.team);
+            }
+            if prev_p.is_bot != p.is_bot {
+                mask |= MASK_IS_BOT;
+                delta.is_bot = Some(p.is_bot);
+            }
+            if prev_p.kills != p.kills {
+                mask |= MASK_KILLS;
+                delta.kills = Some(p.kills);
+            }
+            if effects_changed(&prev_p.effects, &p.effects) {
+                mask |= MASK_EFFECTS;
+                delta.effects = Some(p.effects.clone());
+            }
         } else {
-            mask |= MASK_ALIVE | MASK_HEAD | MASK_DIR | MASK_RADIUS | MASK_SCORE | MASK_BOOST;
+            mask |= MASK_ALIVE
+                | MASK_HEAD
+                | MASK_DIR
+                | MASK_RADIUS
+                | MASK_SCORE
+                | MASK_BOOST
+                | MASK_TEAM
+                | MASK_IS_BOT
+                | MASK_KILLS
+                | MASK_EFFECTS;
             delta.alive = Some(p.alive);
             delta.head = Some(p.head);
             delta.dir = Some(p.dir);
             delta.radius = Some(p.radius);
             delta.score = Some(p.score);
             delta.boost = Some(p.boost);
+            delta.team = Some(p.team);
+            delta.is_bot = Some(p.is_bot);
+            delta.kills = Some(p.kills);
+            delta.effects = Some(p.effects.clone());
         }
 
         if mask != 0 {
@@ -277,14 +686,6 @@ fn build_player_deltas(prev: &[PlayerState], next: &[PlayerState]) -> Vec<Player
     deltas
 }
 
-fn token_kind_to_string(kind: TokenKind) -> String {
-    match kind {
-        TokenKind::Magnet => "magnet".to_owned(),
-        TokenKind::SpeedUp => "speed".to_owned(),
-        TokenKind::TimeAdd => "time".to_owned(),
-    }
-}
-
 fn build_pellets(room: &Room) -> Vec<Vec2f> {
     room.pellets
         .positions()
@@ -293,45 +694,22 @@ fn build_pellets(room: &Room) -> Vec<Vec2f> {
         .collect()
 }
 
+fn build_pellet_teams(room: &Room) -> Vec<u8> {
+    if !room.mode.has_teams() {
+        return Vec::new();
+    }
+    room.pellets.teams()
+}
+
 fn build_tokens(room: &Room) -> Vec<TokenState> {
     room.tokens
         .items()
         .iter()
-        .enumerate()
-        .map(|(idx, t)| TokenState {
-            id: idx as u32,
-            kind: token_kind_to_string(t.kind),
+        .map(|t| TokenState {
+            id: t.id,
+            kind: t.kind.as_str().to_owned(),
             pos: Vec2f { x: t.pos.x, y: t.pos.y },
-            ttl: 0.0,
+            ttl: t.ttl,
         })
         .collect()
 }
-
-fn publish_room_stats(session_rooms: &HashMap<u64, String>) {
-    let mut counts: HashMap<String, u8> = HashMap::new();
-    for room_id in session_rooms.values() {
-        let entry = counts.entry(room_id.clone()).or_insert(0);
-        *entry = entry.saturating_add(1);
-    }
-
-    for (room_id, players) in counts {
-        let existing = crate::master::state::get_room(&room_id);
-        let base = existing.unwrap_or(RoomInfo {
-            room_id: room_id.clone(),
-            name: "Local Room".to_owned(),
-            server_addr: "ws://127.0.0.1:9001".to_owned(),
-            region: "LOCAL".to_owned(),
-            players: 0,
-            max_players: 4,
-            is_private: false,
-            status: RoomStatus::Waiting,
-            ping_ms: Some(1),
-        });
-
-        crate::master::state::upsert_room(RoomInfo {
-            players,
-            status: if players > 0 { RoomStatus::Running } else { RoomStatus::Waiting },
-            ..base
-        });
-    }
-}
@@ -1,88 +1,286 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use macroquad::prelude::*;
-use macroquad::rand::gen_range;
 
-use crate::game::sim::WorldState;
+use crate::game::bot::{BotController, BotView};
+use crate::game::bot_profile::BotProfile;
+use crate::game::body_grid::BodyGrid;
+use crate::game::collision::{find_collisions, CollisionBody, Death, DeathCause};
+use crate::game::sim::{InputState, MassBurn, WorldState};
+use crate::game::effects::{EffectKind, Effects};
 use crate::game::food::{Pellets, TokenKind, Tokens};
+use crate::game::mode::{is_teammate, GameMode};
+use crate::game::skin::{sanitize_name, Skin};
 use crate::game::snake_sim::SnakeSim;
-use crate::config::{
-    ARENA_RADIUS, BASE_SNAKE_LENGTH, BASE_SNAKE_RADIUS, BASE_SPEED, BOOST_ENERGY_DRAIN_PER_SEC,
-    BOOST_ENERGY_MAX, BOOST_ENERGY_REGEN_PER_SEC, BOOST_SPEED_MULT, MAGNET_ATTRACT_MAX_PER_FRAME,
-    MAGNET_ATTRACT_RADIUS, MAGNET_ATTRACT_SPEED, MAGNET_PICKUP_BONUS_PX, PELLET_BUCKET_SIZE,
-    PELLET_EAT_MAX_PER_FRAME, PELLET_RADIUS, PELLET_TARGET_COUNT, SCORE_PER_SEGMENT,
-    SNAKE_RADIUS_GROWTH_EXP, SNAKE_RADIUS_SCORE_HALF, SNAKE_SPACING_MAX, SNAKE_SPACING_MULT,
-    SMALL_SNAKE_SPEED_MULT, SPEEDUP_MULT, TOKEN_DURATION_SEC, TOKEN_TARGET_COUNT,
-    TOKEN_TIME_ADD_SEC, MAX_SNAKE_RADIUS, MATCH_DURATION_SEC, MATCH_START_COUNTDOWN_SEC,
-    CORPSE_DROP_MAX_PELLETS, CORPSE_DROP_SPREAD_PX,
-};
-use crate::net::messages::{Event, PlayerState, Vec2f};
+use crate::game::world::{pick_safe_spawn, random_unit_dir, AgentSnapshot};
+use crate::game::zone::Zone;
+use crate::config::rules::GameRules;
+use crate::master::profiles::MatchPlayer;
+use crate::net::messages::{Event, LeaderboardEntry, PlayerInfo, PlayerState, Vec2f};
+
+// Min distance from other snakes when (re)spawning.
+const SPAWN_CLEARANCE: f32 = 260.0;
+// Keys of server-side bots in `players` (never a real session id).
+const BOT_KEY_BASE: u64 = 1 << 62;
 
 pub struct Room {
     #[allow(dead_code)]
     pub id: u32,
     pub tick_rate: u16,
+    pub max_players: u8,
+    pub rules: GameRules,
+    pub mode: GameMode,
     players: HashMap<u64, PlayerEntity>,
     next_player_id: u32,
     inputs: HashMap<u64, InputState>,
     pub world: WorldState,
     pub pellets: Pellets,
     pub tokens: Tokens,
+    // Broadphase for body collisions, rebuilt every tick.
+    body_grid: BodyGrid,
     events: Vec<Event>,
     time_left: f32,
     countdown_left: f32,
     finished: bool,
+    // Final standings already handed to the lobby.
+    result_taken: bool,
+    // Endless: best lengths since the room opened, longest first.
+    leaderboard: Vec<LeaderboardEntry>,
+    leaderboard_dirty: bool,
+    // Battle royale only.
+    zone: Option<Zone>,
+    // Server-side bots, keyed like `players`; they fill the room up to `bot_fill` snakes.
+    bots: HashMap<u64, BotController>,
+    bot_fill: u8,
+    next_bot_key: u64,
+    // Roster changes for the lobby to announce (`PlayerJoined` / `PlayerLeft`).
+    joined: Vec<PlayerInfo>,
+    left: Vec<u32>,
 }
 
 impl Room {
-    pub fn new(id: u32, tick_rate: u16) -> Self {
+    pub fn new(id: u32, tick_rate: u16, max_players: u8, rules: GameRules, mode: GameMode) -> Self {
+        let mut tokens = Tokens::new(rules.arena_radius, rules.token_target_count, &rules.tokens);
+        tokens.set_time_add_enabled(mode.is_timed());
+        tokens.set_lifetime(rules.token_ttl_sec, rules.token_respawn_delay_sec);
         Self {
             id,
             tick_rate,
+            max_players,
             players: HashMap::new(),
             next_player_id: 1,
             inputs: HashMap::new(),
             world: WorldState::default(),
-            pellets: Pellets::new(PELLET_BUCKET_SIZE, ARENA_RADIUS),
-            tokens: Tokens::new(ARENA_RADIUS, TOKEN_TARGET_COUNT),
+            pellets: Pellets::new(rules.pellet_bucket_size, rules.arena_radius),
+            tokens,
+            body_grid: BodyGrid::default(),
             events: Vec::new(),
-            time_left: MATCH_DURATION_SEC,
-            countdown_left: MATCH_START_COUNTDOWN_SEC,
+            time_left: rules.match_duration_sec,
+            countdown_left: if mode.is_timed() { rules.match_start_countdown_sec } else { 0.0 },
             finished: false,
+            result_taken: false,
+            leaderboard: Vec::new(),
+            leaderboard_dirty: false,
+            zone: mode
+                .has_zone()
+                .then(|| Zone::new(rules.arena_radius, rules.zone_phases.clone())),
+            bots: HashMap::new(),
+            bot_fill: 0,
+            next_bot_key: BOT_KEY_BASE,
+            joined: Vec::new(),
+            left: Vec::new(),
+            rules,
+            mode,
         }
     }
 
-    pub fn add_player(&mut self, session_id: u64) -> u32 {
-        if self.players.len() >= 4 {
+    // `is_bot`: external bot client (JoinReq device "bot"); takes a seat like a human.
+    pub fn add_player(&mut self, session_id: u64, name: &str, skin: Skin, is_bot: bool) -> u32 {
+        if self.human_count() >= self.max_players as usize {
             return 0;
         }
+        let player_id = self.spawn_player(session_id, name, skin, is_bot, None);
+        let team = self.players.get(&session_id).map(|p| p.team).unwrap_or(0);
+        self.rebalance_bots(team);
+        player_id
+    }
+
+    // `brain`: server bot profile; external bot sessions send their own inputs.
+    fn spawn_player(
+        &mut self,
+        key: u64,
+        name: &str,
+        skin: Skin,
+        is_bot: bool,
+        brain: Option<BotProfile>,
+    ) -> u32 {
         let player_id = self.next_player_id;
         self.next_player_id = self.next_player_id.saturating_add(1);
-        let pos = random_pos_in_disk(ARENA_RADIUS * 0.6);
+        let name = sanitize_name(name, &format!("P{}", player_id));
+        self.joined.push(PlayerInfo {
+            id: player_id,
+            name: name.clone(),
+            skin,
+        });
+        let team = self.pick_team();
+        let pos = pick_safe_spawn(&self.occupied_points(), self.zone_radii().0, SPAWN_CLEARANCE);
         let dir = random_unit_dir();
         self.players.insert(
-            session_id,
+            key,
             PlayerEntity {
                 id: player_id,
+                name,
+                skin,
+                is_bot,
+                team,
                 alive: true,
-                snake: SnakeSim::new_at(pos, dir),
+                respawn_left: 0.0,
+                snake: SnakeSim::new_at(pos, dir, &self.rules),
                 score: 0,
-                boost_energy: BOOST_ENERGY_MAX,
-                magnet_left: 0.0,
-                speedup_left: 0.0,
+                boost_energy: self.rules.boost_energy_max,
+                effects: Effects::default(),
+                zone_debt: 0.0,
+                mass_burn: MassBurn::default(),
+                kills: 0,
+                profile_id: None,
             },
         );
-        self.inputs.insert(session_id, InputState::default());
+        self.inputs.insert(key, InputState::default());
+        if let Some(profile) = brain {
+            self.bots.insert(key, BotController::new(dir, profile));
+        }
         player_id
     }
 
+    // Server bot outside of `bot_fill` (headless matches with no humans).
+    pub fn add_bot(&mut self, profile: BotProfile) -> u32 {
+        let key = self.next_bot_key;
+        self.next_bot_key += 1;
+        let id = self.next_player_id;
+        self.spawn_player(key, &format!("BOT{}", id), Skin::for_bot(id), true, Some(profile))
+    }
+
+    // Keep `bot_fill` snakes in the room while humans are in it.
+    pub fn set_bot_fill(&mut self, fill: u8) {
+        self.bot_fill = fill;
+        self.rebalance_bots(0);
+    }
+
+    fn human_count(&self) -> usize {
+        self.players.len() - self.bots.len()
+    }
+
+    // Sessions driven by external bot clients.
+    pub fn bot_session_count(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|(key, p)| p.is_bot && !self.bots.contains_key(key))
+            .count()
+    }
+
+    // Adds or removes bots; removed bots come from `prefer_team` first (team balance).
+    fn rebalance_bots(&mut self, prefer_team: u8) {
+        let humans = self.human_count();
+        let wanted = if humans == 0 {
+            0
+        } else {
+            (self.bot_fill as usize).saturating_sub(humans)
+        };
+        while self.bots.len() > wanted {
+            let key = self
+                .bots
+                .keys()
+                .copied()
+                .max_by_key(|key| {
+                    let team = self.players.get(key).map(|p| p.team).unwrap_or(0);
+                    (team == prefer_team, *key)
+                })
+                .unwrap_or(BOT_KEY_BASE);
+            self.bots.remove(&key);
+            self.inputs.remove(&key);
+            if let Some(p) = self.players.remove(&key) {
+                self.left.push(p.id);
+            }
+        }
+        while self.bots.len() < wanted {
+            let key = self.next_bot_key;
+            self.next_bot_key += 1;
+            let id = self.next_player_id;
+            let profile = self.rules.bot_profile((key - BOT_KEY_BASE) as usize);
+            self.spawn_player(key, &format!("BOT{}", id), Skin::for_bot(id), true, Some(profile));
+        }
+    }
+
+    // Team mode: smallest team first (lowest id on ties). 0 outside team mode.
+    fn pick_team(&self) -> u8 {
+        if !self.mode.has_teams() {
+            return 0;
+        }
+        (1..=self.rules.team_count)
+            .min_by_key(|team| self.players.values().filter(|p| p.team == *team).count())
+            .unwrap_or(1)
+    }
+
     pub fn remove_player(&mut self, session_id: u64) -> Option<u32> {
+        if self.bots.contains_key(&session_id) {
+            return None;
+        }
         self.inputs.remove(&session_id);
-        self.players.remove(&session_id).map(|p| p.id)
+        let removed = self.players.remove(&session_id).map(|p| p.id);
+        self.rebalance_bots(0);
+        removed
+    }
+
+    // Human sessions only.
+    pub fn set_profile_id(&mut self, session_id: u64, profile_id: Option<String>) {
+        if let Some(player) = self.players.get_mut(&session_id) {
+            player.profile_id = profile_id;
+        }
     }
 
-    pub fn session_ids(&self) -> Vec<u64> {
-        self.players.keys().copied().collect()
+    // Standings once a timed match is over (only the first call returns them).
+    // Ranked by score, or by team total in team mode; ties share a rank.
+    pub fn take_final_standings(&mut self) -> Option<Vec<MatchPlayer>> {
+        if !self.finished || self.result_taken {
+            return None;
+        }
+        self.result_taken = true;
+        let totals = self.team_totals();
+        let key = |p: &PlayerEntity| -> i32 {
+            if self.mode.has_teams() && p.team != 0 {
+                totals.get(p.team as usize - 1).copied().unwrap_or(0)
+            } else {
+                p.score
+            }
+        };
+        let mut rows: Vec<(i32, MatchPlayer)> = self
+            .players
+            .values()
+            .map(|p| {
+                (
+                    key(p),
+                    MatchPlayer {
+                        profile_id: p.profile_id.clone(),
+                        name: p.name.clone(),
+                        score: p.score,
+                        kills: p.kills,
+                        rank: 0,
+                        is_bot: p.is_bot,
+                    },
+                )
+            })
+            .collect();
+        rows.sort_by_key(|(k, _)| std::cmp::Reverse(*k));
+        let keys: Vec<i32> = rows.iter().map(|(k, _)| *k).collect();
+        Some(
+            rows.into_iter()
+                .map(|(k, mut row)| {
+                    let better = keys.iter().filter(|other| **other > k).count();
+                    row.rank = (better + 1).min(u8::MAX as usize) as u8;
+                    row
+                })
+                .collect(),
+        )
     }
 
     pub fn set_input(&mut self, session_id: u64, input: InputState) {
@@ -96,31 +294,22 @@ impl Room {
         self.world.step();
         self.events.clear();
 
-        if self.countdown_left > 0.0 {
-            self.countdown_left = (self.countdown_left - dt).max(0.0);
-            if self.countdown_left <= 0.0 {
-                self.events.push(Event {
-                    kind: "match_start".to_owned(),
-                    id: 0,
-                });
-            } else {
-                return;
-            }
+        if self.mode.is_timed() && !self.step_match_clock(dt) {
+            return;
         }
-
-        if !self.finished {
-            self.time_left -= dt;
-            if self.time_left <= 0.0 {
-                self.time_left = 0.0;
-                self.finished = true;
-                self.events.push(Event {
-                    kind: "time_up".to_owned(),
-                    id: 0,
-                });
-            }
+        if self.mode.respawns() {
+            self.respawn_dead(dt);
         }
+        if let Some(zone) = &mut self.zone {
+            zone.step(dt);
+            let (radius, _) = zone.radii();
+            self.pellets.shrink_to(radius);
+            self.tokens.shrink_to(radius);
+        }
+        self.think_bots(dt);
 
         {
+            let rules = &self.rules;
             let (pellets, tokens, events, inputs, players, time_left) = (
                 &mut self.pellets,
                 &mut self.tokens,
@@ -129,66 +318,70 @@ impl Room {
                 &mut self.players,
                 &mut self.time_left,
             );
+            let mut confusers: Vec<(u32, Vec2)> = Vec::new();
             for (session_id, player) in players.iter_mut() {
                 if !player.alive {
                     continue;
                 }
                 let input = inputs.get(session_id).cloned().unwrap_or_default();
                 let desired_dir = if input.dir.length_squared() > 0.0001 {
-                    input.dir.normalize()
+                    player.effects.steer(input.dir.normalize())
                 } else {
                     player.snake.dir()
                 };
-                let size_t = ((player.snake.radius - BASE_SNAKE_RADIUS)
-                    / (MAX_SNAKE_RADIUS - BASE_SNAKE_RADIUS))
-                    .clamp(0.0, 1.0);
-                let size_speed_mult =
-                    SMALL_SNAKE_SPEED_MULT + (1.0 - SMALL_SNAKE_SPEED_MULT) * size_t;
-                let token_mult = if player.speedup_left > 0.0 { SPEEDUP_MULT } else { 1.0 };
-                let boost_mult = if input.boost && player.boost_energy > 0.01 {
-                    BOOST_SPEED_MULT
-                } else {
-                    1.0
-                };
-                player.snake.speed = BASE_SPEED * size_speed_mult * token_mult * boost_mult;
+                let size_speed_mult = rules.size_speed_mult(player.snake.radius);
+                let token_mult = player.effects.speed_mult(rules);
+                let boosting = input.boost
+                    && player.boost_energy > 0.01
+                    && rules.boost_allowed(player.score as f32);
+                let boost_mult = if boosting { rules.boost_speed_mult } else { 1.0 };
+                player.snake.speed = rules.base_speed * size_speed_mult * token_mult * boost_mult;
                 player.snake.update_dir(dt, desired_dir);
 
-                let max_r = (ARENA_RADIUS - player.snake.radius).max(0.0);
+                let max_r = (rules.arena_radius - player.snake.radius).max(0.0);
                 let head = player.snake.head_pos();
                 let d = head.length();
                 if d > max_r {
                     let clamped = head / d * max_r;
-                    let radius = player.snake.radius;
-                    let spacing = player.snake.segment_spacing;
-                    let target_length = player.snake.target_length;
                     player.snake.reset_at(clamped, desired_dir);
-                    player.snake.radius = radius;
-                    player.snake.segment_spacing = spacing;
-                    player.snake.target_length = target_length;
                 }
 
-                if input.boost && player.boost_energy > 0.01 {
+                if boosting {
                     player.boost_energy =
-                        (player.boost_energy - BOOST_ENERGY_DRAIN_PER_SEC * dt).max(0.0);
+                        (player.boost_energy - rules.boost_energy_drain_per_sec * dt).max(0.0);
                 } else {
-                    player.boost_energy =
-                        (player.boost_energy + BOOST_ENERGY_REGEN_PER_SEC * dt).min(BOOST_ENERGY_MAX);
+                    player.boost_energy = (player.boost_energy
+                        + rules.boost_energy_regen_per_sec * dt)
+                        .min(rules.boost_energy_max);
+                }
+
+                let (burned, drops) = player.mass_burn.step(rules, player.score as f32, boosting, dt);
+                if burned > 0 {
+                    player.score = (player.score - burned).max(0);
+                    pellets.spawn_boost_drop(
+                        player.snake.tail_pos(),
+                        drops,
+                        player.snake.radius,
+                        rules.pellet_radius * 0.8,
+                    );
                 }
 
-                let size_factor = (BASE_SNAKE_RADIUS / player.snake.radius).clamp(0.25, 1.0);
-                let pickup_bonus = if player.magnet_left > 0.0 {
-                    MAGNET_PICKUP_BONUS_PX * size_factor
+                let size_factor = (rules.base_snake_radius / player.snake.radius).clamp(0.25, 1.0);
+                let magnet = player.effects.has(EffectKind::Magnet);
+                let pickup_bonus = if magnet {
+                    rules.magnet_pickup_bonus_px * size_factor
                 } else {
                     0.0
                 };
 
-                if player.magnet_left > 0.0 {
-                    let attract_radius = MAGNET_ATTRACT_RADIUS * (0.55 + 0.45 * size_factor);
-                    let attract_speed = MAGNET_ATTRACT_SPEED * (0.75 + 0.25 * size_factor);
+                if magnet {
+                    let attract_radius = rules.magnet_attract_radius * (0.55 + 0.45 * size_factor);
+                    let attract_speed = rules.magnet_attract_speed * (0.75 + 0.25 * size_factor);
                     let attract_max =
-                        ((MAGNET_ATTRACT_MAX_PER_FRAME as f32) * (0.35 + 0.65 * size_factor))
+                        ((rules.magnet_attract_max_per_frame as f32) * (0.35 + 0.65 * size_factor))
                             .round()
-                            .clamp(40.0, MAGNET_ATTRACT_MAX_PER_FRAME as f32)
+                            .max(40.0)
+                            .min(rules.magnet_attract_max_per_frame as f32)
                             as usize;
                     pellets.apply_magnet(
                         player.snake.head_pos(),
@@ -199,172 +392,388 @@ impl Room {
                     );
                 }
 
-                let max_eat = if player.magnet_left > 0.0 {
-                    (PELLET_EAT_MAX_PER_FRAME / 2).max(4)
+                let max_eat = if magnet {
+                    (rules.pellet_eat_max_per_frame / 2).max(4)
                 } else {
-                    PELLET_EAT_MAX_PER_FRAME
+                    rules.pellet_eat_max_per_frame
                 };
 
                 let gained = pellets.eat_colliding(
+                    player.snake.prev_head(),
                     player.snake.head_pos(),
                     player.snake.radius,
                     pickup_bonus,
                     max_eat,
                 );
                 if gained != 0 {
-                    player.score += gained;
+                    player.score += gained * player.effects.score_mult();
                 }
 
-                let extra = (player.score / SCORE_PER_SEGMENT).max(0) as usize;
-                player.snake.target_length =
-                    (BASE_SNAKE_LENGTH + extra).clamp(BASE_SNAKE_LENGTH, 900);
+                player.snake.target_length = rules.target_length(player.score);
 
-                let s = (player.score as f32).max(0.0);
-                let t = if s <= 0.0 {
-                    0.0
-                } else {
-                    (s / (s + SNAKE_RADIUS_SCORE_HALF)).clamp(0.0, 1.0)
-                };
-                let target_radius = (BASE_SNAKE_RADIUS
-                    + (MAX_SNAKE_RADIUS - BASE_SNAKE_RADIUS) * t.powf(SNAKE_RADIUS_GROWTH_EXP))
-                    .clamp(BASE_SNAKE_RADIUS, MAX_SNAKE_RADIUS);
+                let target_radius = rules.target_radius(player.score as f32);
                 let smooth = 1.0 - (-8.0 * dt).exp();
                 player.snake.radius =
                     player.snake.radius + (target_radius - player.snake.radius) * smooth;
-                let target_spacing = (player.snake.radius * SNAKE_SPACING_MULT)
-                    .max((player.snake.radius * 0.78).max(5.2))
-                    .min(SNAKE_SPACING_MAX);
+                let target_spacing = rules.target_spacing(player.snake.radius);
                 player.snake.segment_spacing = player.snake.segment_spacing
                     + (target_spacing - player.snake.segment_spacing) * smooth;
 
                 let collected = tokens.collect_colliding_filtered(
+                    player.snake.prev_head(),
                     player.snake.head_pos(),
                     player.snake.radius,
-                    |_| true,
+                    |k| match k.effect() {
+                        Some(effect) => player.effects.accepts(effect, rules.tokens.get(k)),
+                        None => true,
+                    },
                 );
                 for k in collected {
                     match k {
-                        TokenKind::Magnet => {
-                            player.magnet_left = TOKEN_DURATION_SEC;
-                            events.push(Event {
-                                kind: "magnet".to_owned(),
-                                id: player.id,
-                            });
-                        }
-                        TokenKind::SpeedUp => {
-                            player.speedup_left = TOKEN_DURATION_SEC;
-                            events.push(Event {
-                                kind: "speedup".to_owned(),
-                                id: player.id,
-                            });
-                        }
+                        TokenKind::Confuse => confusers.push((player.id, player.snake.head_pos())),
                         TokenKind::TimeAdd => {
-                            *time_left += TOKEN_TIME_ADD_SEC;
+                            *time_left += rules.token_time_add_sec;
                             events.push(Event {
                                 kind: "time_add".to_owned(),
-                                id: TOKEN_TIME_ADD_SEC as u32,
+                                id: rules.token_time_add_sec as u32,
+                                ..Default::default()
                             });
                         }
+                        _ => {
+                            if let Some(effect) = k.effect() {
+                                player.effects.grant(effect, rules.tokens.get(k));
+                                events.push(Event {
+                                    kind: effect.as_str().to_owned(),
+                                    id: player.id,
+                                    ..Default::default()
+                                });
+                            }
+                        }
                     }
                 }
 
-                player.magnet_left = (player.magnet_left - dt).max(0.0);
-                player.speedup_left = (player.speedup_left - dt).max(0.0);
+                player.effects.tick(dt);
+            }
+
+            // Confuse tokens hit the other snakes (not teammates) around the picker.
+            for (picker, pos) in confusers {
+                let picker_team = players.values().find(|p| p.id == picker).map(|p| p.team).unwrap_or(0);
+                let r2 = rules.confuse_radius * rules.confuse_radius;
+                for player in players.values_mut() {
+                    if player.id == picker
+                        || !player.alive
+                        || is_teammate(player.team, picker_team)
+                        || player.snake.head_pos().distance_squared(pos) > r2
+                    {
+                        continue;
+                    }
+                    player.effects.grant(EffectKind::Confused, &rules.tokens.confuse);
+                    events.push(Event {
+                        kind: EffectKind::Confused.as_str().to_owned(),
+                        id: player.id,
+                        ..Default::default()
+                    });
+                }
             }
         }
 
-        // Arena bounds (authoritative): mark if outside arena
-        let mut dead_ids: HashSet<u32> = HashSet::new();
+        // Dead this tick -> cause and killer (first cause found wins).
+        let mut deaths: HashMap<u32, Death> = HashMap::new();
         for player in self.players.values() {
             if !player.alive {
                 continue;
             }
-            if player.snake.head_pos().length() > (ARENA_RADIUS - player.snake.radius).max(0.0) {
-                dead_ids.insert(player.id);
+            if player.snake.head_pos().length() > (self.rules.arena_radius - player.snake.radius).max(0.0) {
+                deaths.insert(player.id, Death::new(DeathCause::ArenaEdge, None));
             }
         }
 
-        // Head-to-head collisions (simple)
-        let snapshots: Vec<(u32, Vec2, f32, bool, i32)> = self
-            .players
-            .values()
-            .map(|p| (p.id, p.snake.head_pos(), p.snake.radius, p.alive, p.score))
-            .collect();
-        let mut to_kill: HashSet<u32> = HashSet::new();
-        for i in 0..snapshots.len() {
-            for j in (i + 1)..snapshots.len() {
-                let (id_a, pos_a, r_a, alive_a, score_a) = snapshots[i];
-                let (id_b, pos_b, r_b, alive_b, score_b) = snapshots[j];
-                if !alive_a || !alive_b {
+        // Battle royale: outside the safe zone, snakes lose score (and length) until
+        // nothing is left, then die.
+        if let Some((zone_r, _)) = self.zone.as_ref().map(Zone::radii) {
+            let rules = &self.rules;
+            for player in self.players.values_mut().filter(|p| p.alive) {
+                if player.snake.head_pos().length() <= zone_r {
+                    player.zone_debt = 0.0;
                     continue;
                 }
-                let r = r_a + r_b;
-                if pos_a.distance_squared(pos_b) <= r * r {
-                    if score_a == score_b {
-                        to_kill.insert(id_a);
-                        to_kill.insert(id_b);
-                    } else if score_a > score_b {
-                        to_kill.insert(id_b);
-                    } else {
-                        to_kill.insert(id_a);
-                    }
+                player.zone_debt += (rules.zone_damage_per_sec
+                    + player.score.max(0) as f32 * rules.zone_damage_frac_per_sec)
+                    * dt;
+                let lost = player.zone_debt.floor();
+                if lost < 1.0 {
+                    continue;
+                }
+                player.zone_debt -= lost;
+                if player.score <= 0 {
+                    deaths.entry(player.id).or_insert(Death::new(DeathCause::Zone, None));
+                } else {
+                    player.score = (player.score - lost as i32).max(0);
                 }
             }
         }
 
-        // Head-to-body collisions
-        let head_snapshots: Vec<(u32, Vec2, f32, bool)> = self
-            .players
-            .values()
-            .map(|p| (p.id, p.snake.head_pos(), p.snake.radius, p.alive))
-            .collect();
-        let body_snapshots: Vec<(u32, bool, f32, Vec<Vec2>)> = self
-            .players
-            .values()
-            .map(|p| (p.id, p.alive, p.snake.radius, p.snake.segments().to_vec()))
-            .collect();
+        // Snake collisions: same rules and code as the solo game (game::collision).
+        let mut collided: Vec<(u32, Death)> = Vec::new();
+        {
+            let players: Vec<&PlayerEntity> = self.players.values().collect();
+            let bodies: Vec<CollisionBody> = players
+                .iter()
+                .map(|p| CollisionBody {
+                    snake: &p.snake,
+                    team: p.team,
+                    alive: p.alive,
+                    score: p.score as f32,
+                    ghost: p.effects.ghost(),
+                })
+                .collect();
+            let mut marks: Vec<Option<Death>> = players.iter().map(|p| deaths.get(&p.id).copied()).collect();
+            find_collisions(&bodies, &self.rules, &mut self.body_grid, &mut marks);
+            for (player, mark) in players.iter().zip(marks) {
+                if let Some(death) = mark
+                    && !deaths.contains_key(&player.id)
+                {
+                    let killer = death.killer.map(|k| players[k as usize].id);
+                    collided.push((player.id, Death::new(death.cause, killer)));
+                }
+            }
+        }
+        for (id, death) in collided {
+            let shielded = self
+                .players
+                .values_mut()
+                .find(|p| p.id == id)
+                .is_some_and(|p| p.effects.absorbs(&death));
+            if shielded {
+                self.events.push(Event {
+                    kind: "shield_used".to_owned(),
+                    id,
+                    ..Default::default()
+                });
+            } else {
+                deaths.insert(id, death);
+            }
+        }
 
-        for (attacker_id, head, head_r, alive) in head_snapshots {
-            if !alive {
-                continue;
+        if !deaths.is_empty() {
+            let (rules, pellets, events, players) =
+                (&self.rules, &mut self.pellets, &mut self.events, &mut self.players);
+            for (id, death) in &deaths {
+                if let Some(player) = players.values_mut().find(|p| p.id == *id) {
+                    kill_player_in_place(rules, pellets, events, player, *death);
+                }
             }
-            for (victim_id, victim_alive, victim_r, segments) in &body_snapshots {
-                if !victim_alive || *victim_id == attacker_id {
-                    continue;
+            for killer in deaths.values().filter_map(|d| d.killer) {
+                if let Some(player) = players.values_mut().find(|p| p.id == killer) {
+                    player.kills += 1;
                 }
-                for (idx, seg) in segments.iter().enumerate() {
-                    if idx == 0 {
-                        continue;
-                    }
-                    let r = head_r + *victim_r;
-                    if head.distance_squared(*seg) <= r * r {
-                        to_kill.insert(attacker_id);
-                        break;
-                    }
+            }
+        }
+
+        if self.mode.respawns() {
+            self.update_leaderboard();
+        }
+
+        // Same pellet density when the safe zone shrinks.
+        let zone_area = (self.zone_radii().0 / self.rules.arena_radius).powi(2);
+        let pellet_target = (self.rules.pellet_target_count as f32 * zone_area).ceil() as usize;
+        if self.pellets.total() < pellet_target {
+            self.pellets.refill_to(pellet_target, self.rules.pellet_radius);
+        }
+        self.tokens.tick(dt);
+        if self.tokens.total() < self.rules.token_target_count {
+            self.tokens.refill_to_target();
+        }
+    }
+
+    // Countdown then match timer. Returns false while the countdown is running.
+    fn step_match_clock(&mut self, dt: f32) -> bool {
+        if self.countdown_left > 0.0 {
+            self.countdown_left = (self.countdown_left - dt).max(0.0);
+            if self.countdown_left <= 0.0 {
+                self.events.push(Event {
+                    kind: "match_start".to_owned(),
+                    id: 0,
+                    ..Default::default()
+                });
+            } else {
+                return false;
+            }
+        }
+
+        if !self.finished {
+            self.time_left -= dt;
+            if self.time_left <= 0.0 {
+                self.time_left = 0.0;
+                self.finished = true;
+                if self.mode.has_teams() {
+                    self.events.push(Event {
+                        kind: "team_win".to_owned(),
+                        id: self.winning_team() as u32,
+                        ..Default::default()
+                    });
                 }
+                self.events.push(Event {
+                    kind: "time_up".to_owned(),
+                    id: 0,
+                    ..Default::default()
+                });
             }
         }
+        true
+    }
 
-        dead_ids.extend(to_kill.iter().copied());
+    // Score of every team (dead players included), indexed by team id - 1.
+    pub fn team_totals(&self) -> Vec<i32> {
+        let mut totals = vec![0; self.rules.team_count as usize];
+        for player in self.players.values().filter(|p| p.team != 0) {
+            if let Some(total) = totals.get_mut(player.team as usize - 1) {
+                *total += player.score;
+            }
+        }
+        totals
+    }
 
-        if !dead_ids.is_empty() {
-            let ids: Vec<u32> = dead_ids.iter().copied().collect();
-            let (pellets, events, players) = (&mut self.pellets, &mut self.events, &mut self.players);
-            for id in ids {
-                if let Some(player) = players.values_mut().find(|p| p.id == id) {
-                    kill_player_in_place(pellets, events, player);
+    // Team with the best total, 0 on a tie.
+    pub fn winning_team(&self) -> u8 {
+        let totals = self.team_totals();
+        let Some(best) = totals.iter().copied().max() else {
+            return 0;
+        };
+        let mut leaders = totals.iter().enumerate().filter(|(_, t)| **t == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((idx, _)), None) => idx as u8 + 1,
+            _ => 0,
+        }
+    }
+
+    // (current, next) safe zone radius; the arena radius outside battle royale.
+    pub fn zone_radii(&self) -> (f32, f32) {
+        match &self.zone {
+            Some(zone) => zone.radii(),
+            None => (self.rules.arena_radius, self.rules.arena_radius),
+        }
+    }
+
+    // Heads and a sample of body points of live snakes (spawn safety).
+    fn occupied_points(&self) -> Vec<Vec2> {
+        let mut points = Vec::new();
+        for player in self.players.values().filter(|p| p.alive) {
+            points.push(player.snake.head_pos());
+            points.extend(player.snake.segments().iter().step_by(4).copied());
+        }
+        points
+    }
+
+    // Bots pick their input from the same state clients see, then go through the
+    // normal simulation below.
+    fn think_bots(&mut self, dt: f32) {
+        if self.bots.is_empty() {
+            return;
+        }
+        let snakes: Vec<AgentSnapshot> = self
+            .players
+            .values()
+            .map(|p| AgentSnapshot {
+                id: p.id,
+                team: p.team,
+                alive: p.alive,
+                head: p.snake.head_pos(),
+                radius: p.snake.radius,
+            })
+            .collect();
+        let arena_radius = self.zone_radii().0;
+        let heavy_mode = snakes.len() >= 50;
+        for (key, brain) in self.bots.iter_mut() {
+            let Some(player) = self.players.get(key).filter(|p| p.alive) else {
+                continue;
+            };
+            let Some(me) = snakes.iter().position(|s| s.id == player.id) else {
+                continue;
+            };
+            let view = BotView {
+                me,
+                snakes: &snakes,
+                pellets: &self.pellets,
+                tokens: &self.tokens,
+                arena_radius,
+                boost_energy: player.boost_energy,
+                effects: &player.effects,
+                heavy_mode,
+            };
+            self.inputs.insert(*key, brain.think(&view, dt));
+        }
+    }
+
+    fn respawn_dead(&mut self, dt: f32) {
+        let mut occupied: Option<Vec<Vec2>> = None;
+        let keys: Vec<u64> = self.players.keys().copied().collect();
+        for session_id in keys {
+            let ready = match self.players.get_mut(&session_id) {
+                Some(player) if !player.alive => {
+                    player.respawn_left -= dt;
+                    player.respawn_left <= 0.0
                 }
+                _ => false,
+            };
+            if !ready {
+                continue;
+            }
+            let points = occupied.get_or_insert_with(|| self.occupied_points());
+            let pos = pick_safe_spawn(points, self.zone_radii().0, SPAWN_CLEARANCE);
+            points.push(pos);
+            if let Some(player) = self.players.get_mut(&session_id) {
+                player.respawn(pos, random_unit_dir(), &self.rules);
+                self.events.push(Event {
+                    kind: "respawn".to_owned(),
+                    id: player.id,
+                    ..Default::default()
+                });
             }
         }
+    }
 
-        if self.pellets.total() < PELLET_TARGET_COUNT {
-            self.pellets.refill_to(PELLET_TARGET_COUNT, PELLET_RADIUS);
+    fn update_leaderboard(&mut self) {
+        let size = self.rules.leaderboard_size;
+        for player in self.players.values().filter(|p| p.alive) {
+            let length = player.snake.target_length as u32;
+            if let Some(entry) = self.leaderboard.iter_mut().find(|e| e.id == player.id) {
+                if length > entry.length {
+                    entry.length = length;
+                    self.leaderboard_dirty = true;
+                }
+                continue;
+            }
+            let qualifies = self.leaderboard.len() < size
+                || self.leaderboard.last().map(|e| length > e.length).unwrap_or(true);
+            if qualifies && size > 0 {
+                self.leaderboard.push(LeaderboardEntry {
+                    id: player.id,
+                    name: player.name.clone(),
+                    length,
+                });
+                self.leaderboard_dirty = true;
+            }
         }
-        if self.tokens.total() < TOKEN_TARGET_COUNT {
-            self.tokens.refill_to_target();
+        if self.leaderboard_dirty {
+            self.leaderboard.sort_by_key(|e| std::cmp::Reverse(e.length));
+            self.leaderboard.truncate(size);
         }
     }
 
+    pub fn leaderboard(&self) -> &[LeaderboardEntry] {
+        &self.leaderboard
+    }
+
+    // Leaderboard, if it changed since the last call.
+    pub fn take_leaderboard_update(&mut self) -> Option<Vec<LeaderboardEntry>> {
+        if !self.leaderboard_dirty {
+            return None;
+        }
+        self.leaderboard_dirty = false;
+        Some(self.leaderboard.clone())
+    }
+
     pub fn player_states(&self) -> Vec<PlayerState> {
         self.players
             .values()
@@ -382,10 +791,30 @@ impl Room {
                 radius: id.snake.radius,
                 score: id.score,
                 boost: id.boost_energy,
+                team: id.team,
+                is_bot: id.is_bot,
+                kills: id.kills,
+                effects: id.effects.active().to_vec(),
             })
             .collect()
     }
 
+    pub fn player_infos(&self) -> Vec<PlayerInfo> {
+        self.players
+            .values()
+            .map(|p| PlayerInfo {
+                id: p.id,
+                name: p.name.clone(),
+                skin: p.skin,
+            })
+            .collect()
+    }
+
+    // (joined, left) since the last call.
+    pub fn take_roster_changes(&mut self) -> (Vec<PlayerInfo>, Vec<u32>) {
+        (std::mem::take(&mut self.joined), std::mem::take(&mut self.left))
+    }
+
     pub fn take_events(&mut self) -> Vec<Event> {
         let mut out = Vec::new();
         std::mem::swap(&mut out, &mut self.events);
@@ -399,59 +828,93 @@ impl Room {
     pub fn countdown_left(&self) -> f32 {
         self.countdown_left
     }
-}
 
-#[derive(Clone, Copy)]
-pub struct InputState {
-    pub dir: Vec2,
-    pub boost: bool,
-}
+    // Timed modes: the match clock ran out.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
 
-impl Default for InputState {
-    fn default() -> Self {
-        Self {
-            dir: vec2(0.0, 0.0),
-            boost: false,
-        }
+    // Chat is open before the match starts and once it is over; endless rooms
+    // have no end, so their chat stays open.
+    pub fn chat_open(&self) -> bool {
+        self.mode.respawns() || self.countdown_left > 0.0 || self.finished
+    }
+
+    pub fn player_by_session(&self, session_id: u64) -> Option<&PlayerEntity> {
+        self.players.get(&session_id)
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &PlayerEntity> {
+        self.players.values()
     }
 }
 
 pub struct PlayerEntity {
     pub id: u32,
+    pub name: String,
+    pub skin: Skin,
+    pub is_bot: bool,
+    pub team: u8,
     pub alive: bool,
+    pub respawn_left: f32,
     pub snake: SnakeSim,
     pub score: i32,
     pub boost_energy: f32,
-    pub magnet_left: f32,
-    pub speedup_left: f32,
+    pub effects: Effects,
+    // Fractional zone damage not yet taken from the score.
+    pub zone_debt: f32,
+    pub mass_burn: MassBurn,
+    // Snakes killed by this one (head-to-head win or ran into its body).
+    pub kills: u32,
+    // Master profile from a verified `JoinReq.profile`.
+    pub profile_id: Option<String>,
+}
+
+impl PlayerEntity {
+    fn respawn(&mut self, pos: Vec2, dir: Vec2, rules: &GameRules) {
+        self.snake = SnakeSim::new_at(pos, dir, rules);
+        self.alive = true;
+        self.respawn_left = 0.0;
+        self.score = 0;
+        self.boost_energy = rules.boost_energy_max;
+        self.effects.clear();
+        self.zone_debt = 0.0;
+        self.mass_burn = MassBurn::default();
+    }
 }
 
-fn kill_player_in_place(pellets: &mut Pellets, events: &mut Vec<Event>, player: &mut PlayerEntity) {
+fn kill_player_in_place(
+    rules: &GameRules,
+    pellets: &mut Pellets,
+    events: &mut Vec<Event>,
+    player: &mut PlayerEntity,
+    death: Death,
+) {
     if !player.alive {
         return;
     }
     pellets.spawn_corpse_score(
         player.snake.segments(),
         player.score.max(0),
-        CORPSE_DROP_MAX_PELLETS,
-        CORPSE_DROP_SPREAD_PX.max(2.0),
+        rules.corpse_drop_max_pellets,
+        rules.corpse_drop_spread_px.max(2.0),
+        player.team,
     );
     player.alive = false;
-    player.magnet_left = 0.0;
-    player.speedup_left = 0.0;
+    player.respawn_left = rules.respawn_delay_sec;
+    player.effects.clear();
     events.push(Event {
         kind: "death".to_owned(),
         id: player.id,
+        killer: death.killer,
+        cause: Some(death.cause),
     });
-}
-
-fn random_pos_in_disk(radius: f32) -> Vec2 {
-    let a = gen_range(0.0f32, std::f32::consts::TAU);
-    let r = gen_range(0.0f32, 1.0f32).sqrt() * radius;
-    vec2(a.cos() * r, a.sin() * r)
-}
-
-fn random_unit_dir() -> Vec2 {
-    let a = gen_range(0.0f32, std::f32::consts::TAU);
-    vec2(a.cos(), a.sin())
+    if death.killer.is_some() {
+        events.push(Event {
+            kind: "kill".to_owned(),
+            id: player.id,
+            killer: death.killer,
+            cause: Some(death.cause),
+        });
+    }
 }
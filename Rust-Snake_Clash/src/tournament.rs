@@ -0,0 +1,218 @@
+// Headless bot-vs-bot matches: steps `Room` directly (no sockets, no window, no
+// real-time wait) and reports per-profile results, to balance bot difficulty and
+// catch gameplay regressions when tuning rules.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::config::rules::GameRules;
+use crate::config::settings::{ReportFormat, TournamentSettings};
+use crate::game::bot_profile::BotProfile;
+use crate::state::room::Room;
+
+// Results of one roster entry name, summed over all its snakes.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileStats {
+    pub profile: String,
+    // Snakes played (matches x copies in the roster).
+    pub snakes: u32,
+    pub wins: u32,
+    pub win_rate: f32,
+    pub avg_score: f32,
+    // Seconds alive after the match start (whole match if never killed).
+    pub avg_survival_sec: f32,
+    pub kills: u32,
+    pub avg_kills: f32,
+    pub deaths: u32,
+    #[serde(skip)]
+    total_score: i64,
+    #[serde(skip)]
+    total_survival: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub matches: u32,
+    pub mode: String,
+    pub rules: String,
+    pub seed: u64,
+    pub profiles: Vec<ProfileStats>,
+}
+
+pub fn run(settings: &TournamentSettings) -> Result<Report, String> {
+    let rules = GameRules::resolve(&settings.rules, settings.rules_dir.as_deref())?;
+    let mode = settings.mode;
+    if !mode.is_timed() {
+        return Err(format!(
+            "tournament needs a timed mode (classic, teams, royale), got '{}'",
+            mode.as_str()
+        ));
+    }
+    if settings.roster.is_empty() {
+        return Err("tournament roster is empty".to_owned());
+    }
+    let roster = settings
+        .roster
+        .iter()
+        .map(|name| {
+            rules
+                .find_bot_profile(name)
+                .map(|profile| (name.clone(), profile))
+                .ok_or_else(|| format!("unknown bot profile '{}' in roster", name))
+        })
+        .collect::<Result<Vec<(String, BotProfile)>, String>>()?;
+
+    let seed = settings.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0)
+    });
+    macroquad::rand::srand(seed);
+
+    let mut stats: Vec<ProfileStats> = Vec::new();
+    for (name, _) in &roster {
+        if !stats.iter().any(|s| &s.profile == name) {
+            stats.push(ProfileStats {
+                profile: name.clone(),
+                ..Default::default()
+            });
+        }
+    }
+
+    for match_idx in 0..settings.matches {
+        play_match(match_idx, settings.tick_rate, &rules, mode, &roster, &mut stats);
+    }
+
+    for s in &mut stats {
+        let n = s.snakes.max(1) as f32;
+        s.win_rate = s.wins as f32 / n;
+        s.avg_score = s.total_score as f32 / n;
+        s.avg_survival_sec = s.total_survival / n;
+        s.avg_kills = s.kills as f32 / n;
+    }
+
+    Ok(Report {
+        matches: settings.matches,
+        mode: mode.as_str().to_owned(),
+        rules: rules.name.clone(),
+        seed,
+        profiles: stats,
+    })
+}
+
+fn play_match(
+    match_idx: u32,
+    tick_rate: u16,
+    rules: &GameRules,
+    mode: crate::game::mode::GameMode,
+    roster: &[(String, BotProfile)],
+    stats: &mut [ProfileStats],
+) {
+    let dt = 1.0 / tick_rate.max(1) as f32;
+    let max_players = roster.len().min(u8::MAX as usize) as u8;
+    let mut room = Room::new(match_idx, tick_rate, max_players, rules.clone(), mode);
+    let mut entries: HashMap<u32, usize> = HashMap::new();
+    for (name, profile) in roster {
+        let id = room.add_bot(profile.clone());
+        let slot = stats.iter().position(|s| &s.profile == name).unwrap_or(0);
+        entries.insert(id, slot);
+    }
+
+    // Time extensions (time_add tokens) can stretch a match; never loop forever.
+    let max_ticks = ((rules.match_start_countdown_sec + rules.match_duration_sec * 4.0)
+        / dt)
+        .ceil() as u32;
+    let mut started = false;
+    let mut elapsed = 0.0f32;
+    let mut died_at: HashMap<u32, f32> = HashMap::new();
+    for _ in 0..max_ticks {
+        room.step();
+        if started {
+            elapsed += dt;
+        }
+        for event in room.take_events() {
+            match event.kind.as_str() {
+                "match_start" => started = true,
+                "death" => {
+                    died_at.entry(event.id).or_insert(elapsed);
+                }
+                _ => {}
+            }
+        }
+        if room.finished() {
+            break;
+        }
+        // Same end rules as the solo game: last snake alive wins; teams play the clock.
+        if started && !mode.has_teams() && room.players().filter(|p| p.alive).count() <= 1 {
+            break;
+        }
+    }
+
+    let winners: Vec<u32> = if mode.has_teams() {
+        let team = room.winning_team();
+        room.players()
+            .filter(|p| team != 0 && p.team == team)
+            .map(|p| p.id)
+            .collect()
+    } else {
+        let alive: Vec<u32> = room.players().filter(|p| p.alive).map(|p| p.id).collect();
+        if alive.len() == 1 {
+            alive
+        } else {
+            room.players()
+                .max_by_key(|p| p.score)
+                .map(|p| vec![p.id])
+                .unwrap_or_default()
+        }
+    };
+
+    for player in room.players() {
+        let Some(s) = entries.get(&player.id).and_then(|slot| stats.get_mut(*slot)) else {
+            continue;
+        };
+        s.snakes += 1;
+        s.total_score += player.score as i64;
+        s.kills += player.kills;
+        if winners.contains(&player.id) {
+            s.wins += 1;
+        }
+        match died_at.get(&player.id) {
+            Some(t) => {
+                s.deaths += 1;
+                s.total_survival += t;
+            }
+            None => s.total_survival += elapsed,
+        }
+    }
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> Result<String, String> {
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| format!("cannot encode report: {}", e)),
+            ReportFormat::Csv => {
+                let mut out = String::from(
+                    "profile,snakes,wins,win_rate,avg_score,avg_survival_sec,kills,avg_kills,deaths\n",
+                );
+                for s in &self.profiles {
+                    out.push_str(&format!(
+                        "{},{},{},{:.3},{:.1},{:.1},{},{:.2},{}\n",
+                        s.profile,
+                        s.snakes,
+                        s.wins,
+                        s.win_rate,
+                        s.avg_score,
+                        s.avg_survival_sec,
+                        s.kills,
+                        s.avg_kills,
+                        s.deaths
+                    ));
+                }
+                Ok(out)
+            }
+        }
+    }
+}